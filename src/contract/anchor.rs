@@ -21,15 +21,23 @@
 // limitations under the License.
 
 use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
 
+use amplify::confinement::{Confined, SmallVec, U16};
 use bp::dbc::opret::OpretProof;
-use bp::dbc::tapret::TapretProof;
-use bp::dbc::Anchor;
-use bp::Txid;
-use commit_verify::mpc;
+use bp::dbc::tapret::{TapretCommitment, TapretKeyError, TapretProof};
+use bp::dbc::{self, Anchor};
+use bp::seals::txout::{CloseMethod, TxoSeal};
+use bp::{
+    InternalPk, OutputPk, ScriptPubkey, TapBranchHash, TapLeafHash, TapNodeHash, TapScript, Txid,
+};
+use commit_verify::{mpc, CommitVerify, CommitmentId, Conceal};
 use strict_encoding::StrictDumb;
 
-use crate::{BundleId, ContractId, TransitionBundle, WitnessId, WitnessOrd, XChain, LIB_NAME_RGB};
+use crate::{
+    BundleId, BundleTooLarge, ContractId, OpId, Operation, Transition, TransitionBundle, Vin,
+    WitnessId, WitnessOrd, WitnessPos, XChain, XOutputSeal, LIB_NAME_RGB,
+};
 
 #[derive(Clone, Eq, PartialEq, Debug)]
 #[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
@@ -47,6 +55,361 @@ pub struct AnchoredBundle {
 impl AnchoredBundle {
     #[inline]
     pub fn bundle_id(&self) -> BundleId { self.bundle.bundle_id() }
+
+    /// Produces pruned copy of the anchored bundle, keeping only data required
+    /// to verify the bundle commitments.
+    ///
+    /// Assignments of all known transitions are concealed, which changes
+    /// neither transition ids nor the bundle id, thus the anchor is kept intact
+    /// and the pruned bundle passes [`Self::verify`]. Global state has no
+    /// concealed form and is committed to by the transition id as is, so it is
+    /// preserved.
+    pub fn prune(&self) -> AnchoredBundle {
+        let mut pruned = self.clone();
+        pruned
+            .bundle
+            .known_transitions
+            .keyed_values_mut()
+            .for_each(|(_, transition)| *transition = transition.conceal());
+        pruned
+    }
+
+    /// Produces minimal anchored bundle for a recipient, which contains only
+    /// the transitions from the `keep` set.
+    ///
+    /// All other transitions are removed from the known transitions, staying
+    /// concealed behind their ids in the bundle input map, such that the
+    /// bundle id is not changed. The anchor is narrowed to the MPC proof of
+    /// the provided contract.
+    ///
+    /// The bundle is checked with [`Self::verify`] first, such that the anchor
+    /// is never narrowed under a contract it doesn't commit the bundle to. The
+    /// method doesn't verify the anchor against the witness transaction, which
+    /// is done during the consignment validation.
+    pub fn split_for(
+        &self,
+        contract_id: ContractId,
+        keep: &BTreeSet<OpId>,
+    ) -> Result<AnchoredBundle, SplitError> {
+        self.verify(contract_id)?;
+        let anchor = self
+            .anchor
+            .to_merkle_block(contract_id, self.bundle_id())
+            .expect("verified anchor commits to the bundle under the contract")
+            .into_merkle_proof(contract_id)
+            .expect("merkle block is constructed for the contract");
+
+        let mut known_transitions = BTreeMap::new();
+        for opid in keep {
+            let transition = self
+                .bundle
+                .known_transitions
+                .get(opid)
+                .ok_or(SplitError::UnknownTransition(*opid))?;
+            known_transitions.insert(*opid, transition.clone());
+        }
+        let known_transitions =
+            Confined::try_from(known_transitions).map_err(|_| SplitError::NoTransitions)?;
+
+        Ok(AnchoredBundle {
+            anchor,
+            bundle: TransitionBundle {
+                input_map: self.bundle.input_map.clone(),
+                known_transitions,
+            },
+        })
+    }
+
+    /// Constructs proof of inclusion of a single transition `op` of the
+    /// contract into the bundle anchored under the witness transaction.
+    ///
+    /// The bundle is checked with [`Self::verify`] first; the anchor is then
+    /// narrowed to the MPC proof of the contract, and other transitions of the
+    /// bundle are represented only by their ids in the bundle input map.
+    pub fn inclusion_proof(
+        &self,
+        op: OpId,
+        contract_id: ContractId,
+    ) -> Result<InclusionProof, SplitError> {
+        self.verify(contract_id)?;
+        let transition = self
+            .bundle
+            .known_transitions
+            .get(&op)
+            .ok_or(SplitError::UnknownTransition(op))?;
+        let anchor = self
+            .anchor
+            .to_merkle_block(contract_id, self.bundle_id())
+            .expect("verified anchor commits to the bundle under the contract")
+            .into_merkle_proof(contract_id)
+            .expect("merkle block is constructed for the contract");
+        Ok(InclusionProof {
+            anchor,
+            input_map: self.bundle.input_map.clone(),
+            transition: transition.clone(),
+        })
+    }
+
+    /// Verifies that the bundle is consistently anchored under the given
+    /// contract: the anchor has a single witness and its MPC proofs commit to
+    /// the bundle under the contract, all known transitions match their ids,
+    /// belong to the contract and are committed to by the bundle input map,
+    /// and the bundle doesn't exceed the size limits.
+    ///
+    /// The verification doesn't depend on any external data, thus the witness
+    /// transaction commitment and the seal closing are not checked - this is
+    /// done during the consignment validation.
+    pub fn verify(&self, contract_id: ContractId) -> Result<(), AnchorVerifyError> {
+        let bundle_id = self.bundle_id();
+        self.bundle.validate_size()?;
+        if self.anchor.witness_id().is_none() {
+            return Err(AnchorVerifyError::WitnessMismatch(bundle_id));
+        }
+        let message = mpc::Message::from(bundle_id);
+        let (tapret, opret) = self.anchor.as_reduced_unsafe().as_split();
+        let tapret = tapret
+            .map(|anchor| anchor.convolve(contract_id, message))
+            .transpose()
+            .map_err(|err| AnchorVerifyError::Mpc(bundle_id, err))?;
+        let opret = opret
+            .map(|anchor| anchor.convolve(contract_id, message))
+            .transpose()
+            .map_err(|err| AnchorVerifyError::Mpc(bundle_id, err))?;
+        if let (Some(tapret), Some(opret)) = (tapret, opret) {
+            if tapret != opret {
+                return Err(AnchorVerifyError::DualRootMismatch(bundle_id));
+            }
+        }
+        for (opid, transition) in &self.bundle.known_transitions {
+            let id = transition.id();
+            if id != *opid {
+                return Err(AnchorVerifyError::TransitionIdMismatch(bundle_id, *opid, id));
+            }
+            if transition.contract_id != contract_id {
+                return Err(AnchorVerifyError::ContractMismatch(*opid, transition.contract_id));
+            }
+            if !self.bundle.input_map.values().any(|id| id == opid) {
+                return Err(AnchorVerifyError::UncommittedTransition(bundle_id, *opid));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Errors verifying anchored bundle with [`AnchoredBundle::verify`].
+#[derive(Clone, PartialEq, Eq, Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum AnchorVerifyError {
+    /// anchors of bundle {0} reference different witness transactions.
+    WitnessMismatch(BundleId),
+
+    /// bundle {0} is not committed to under the contract by the anchor MPC
+    /// proof - {1}
+    Mpc(BundleId, mpc::InvalidProof),
+
+    /// tapret and opret anchors of bundle {0} commit to different MPC roots.
+    DualRootMismatch(BundleId),
+
+    /// bundle {0} keeps under id {1} a transition which has id {2}.
+    TransitionIdMismatch(BundleId, OpId, OpId),
+
+    /// transition {0} belongs to a different contract {1}.
+    ContractMismatch(OpId, ContractId),
+
+    /// transition {1} is not committed to by the input map of bundle {0}.
+    UncommittedTransition(BundleId, OpId),
+
+    /// {0}
+    #[from]
+    TooLarge(BundleTooLarge),
+}
+
+/// Verifies each of the anchored bundles with [`AnchoredBundle::verify`],
+/// returning verification results in the same order as the bundles.
+pub fn validate_bundles(
+    bundles: &[AnchoredBundle],
+    contract_id: ContractId,
+) -> Vec<Result<(), AnchorVerifyError>> {
+    bundles
+        .iter()
+        .map(|bundle| bundle.verify(contract_id))
+        .collect()
+}
+
+/// Verifies each of the anchored bundles with [`AnchoredBundle::verify`] in
+/// parallel, using the global rayon thread pool; returns verification
+/// results in the same order as the bundles.
+///
+/// Bundle verification has no side effects, so bundles are verified
+/// independently.
+#[cfg(feature = "rayon")]
+pub fn validate_bundles_par(
+    bundles: &[AnchoredBundle],
+    contract_id: ContractId,
+) -> Vec<Result<(), AnchorVerifyError>> {
+    use rayon::prelude::*;
+
+    bundles
+        .par_iter()
+        .map(|bundle| bundle.verify(contract_id))
+        .collect()
+}
+
+/// Errors splitting anchored bundle with [`AnchoredBundle::split_for`].
+#[derive(Clone, PartialEq, Eq, Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum SplitError {
+    /// the bundle is not anchored under the provided contract - {0}
+    #[from]
+    Anchor(AnchorVerifyError),
+
+    /// transition {0} is not known to the bundle.
+    UnknownTransition(OpId),
+
+    /// no transitions are left in the bundle after splitting.
+    NoTransitions,
+}
+
+/// Proof of inclusion of a single state transition into a transition bundle
+/// committed to by a witness transaction, constructed with
+/// [`AnchoredBundle::inclusion_proof`].
+///
+/// The bundle id commits to the whole bundle input map, which is thus a part
+/// of the proof; the positions of the transition in the bundle are the
+/// witness transaction inputs it is mapped to.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[derive(StrictType, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct InclusionProof {
+    /// MPC and DBC proofs of the bundle commitment under the contract.
+    pub anchor: XAnchor<mpc::MerkleProof>,
+    pub input_map: Confined<BTreeMap<Vin, OpId>, 1, U16>,
+    pub transition: Transition,
+}
+
+impl StrictDumb for InclusionProof {
+    fn strict_dumb() -> Self {
+        Self {
+            anchor: strict_dumb!(),
+            input_map: confined_bmap! { strict_dumb!() => strict_dumb!() },
+            transition: strict_dumb!(),
+        }
+    }
+}
+
+impl InclusionProof {
+    /// Returns witness transaction inputs at which the transition is placed
+    /// in the bundle.
+    pub fn positions(&self) -> impl Iterator<Item = Vin> + '_ {
+        let opid = self.transition.id();
+        self.input_map
+            .iter()
+            .filter(move |(_, id)| **id == opid)
+            .map(|(vin, _)| *vin)
+    }
+
+    /// Computes id of the bundle which includes the transition.
+    pub fn bundle_id(&self) -> BundleId {
+        TransitionBundle {
+            input_map: self.input_map.clone(),
+            known_transitions: confined_bmap! { self.transition.id() => self.transition.clone() },
+        }
+        .bundle_id()
+    }
+
+    /// Reconstructs the anchored bundle containing the transition as its only
+    /// known transition. The bundle has the same id as the bundle the proof
+    /// was constructed from.
+    pub fn to_anchored_bundle(&self) -> AnchoredBundle {
+        AnchoredBundle {
+            anchor: self.anchor.clone(),
+            bundle: TransitionBundle {
+                input_map: self.input_map.clone(),
+                known_transitions: confined_bmap! {
+                    self.transition.id() => self.transition.clone()
+                },
+            },
+        }
+    }
+
+    /// Verifies the chain of commitments from the transition up to the MPC
+    /// commitment with [`AnchoredBundle::verify`], returning the commitment
+    /// which must be present in the witness transaction.
+    ///
+    /// Checking the returned commitment against the witness transaction with
+    /// the DBC proof of the anchor requires the transaction itself and is left
+    /// to the caller.
+    pub fn verify(&self, contract_id: ContractId) -> Result<mpc::Commitment, AnchorVerifyError> {
+        let anchored = self.to_anchored_bundle();
+        anchored.verify(contract_id)?;
+        let message = mpc::Message::from(anchored.bundle_id());
+        let (tapret, opret) = self.anchor.as_reduced_unsafe().as_split();
+        let commitment = tapret
+            .map(|anchor| anchor.convolve(contract_id, message))
+            .or_else(|| opret.map(|anchor| anchor.convolve(contract_id, message)))
+            .expect("anchor set always has at least one anchor")
+            .expect("verified anchor commits to the bundle under the contract");
+        Ok(commitment)
+    }
+}
+
+/// Unified error type for anchor operations, covering all the individual anchor
+/// verification and conversion failures.
+#[derive(Clone, PartialEq, Eq, Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum AnchorError {
+    /// dual anchor commits to different witness transactions {tapret} (tapret)
+    /// and {opret} (opret).
+    TxidMismatch { tapret: Txid, opret: Txid },
+
+    /// the anchor doesn't contain information about the contract - {0}
+    #[from]
+    LeafNotKnown(mpc::LeafNotKnown),
+
+    /// the anchor MPC proof is invalid - {0}
+    #[from]
+    InvalidProof(mpc::InvalidProof),
+
+    /// {0}
+    #[from]
+    CloseMethod(CloseMethodMismatch),
+
+    /// tapret commitment can't be computed - {0}
+    #[from]
+    TapretKey(TapretKeyError),
+
+    /// {0}
+    #[from]
+    Verify(AnchorVerifyError),
+
+    /// {0}
+    #[from]
+    Split(SplitError),
+}
+
+/// Groups anchored bundles by their witness transaction, such that the anchor
+/// shared by multiple contracts may be processed once per witness.
+///
+/// The order of bundles within each group is preserved. Dual anchors with
+/// inconsistent transaction ids are grouped using the tapret anchor txid, as
+/// in [`XAnchor::witness_id_unchecked`]; such anchors are invalid and will be
+/// rejected during the validation.
+pub fn group_by_witness(
+    bundles: impl IntoIterator<Item = AnchoredBundle>,
+) -> BTreeMap<WitnessId, Vec<AnchoredBundle>> {
+    let mut groups = BTreeMap::<WitnessId, Vec<AnchoredBundle>>::new();
+    for ab in bundles {
+        groups
+            .entry(ab.anchor.witness_id_unchecked())
+            .or_default()
+            .push(ab);
+    }
+    groups
 }
 
 pub type XAnchor<P = mpc::MerkleProof> = XChain<AnchorSet<P>>;
@@ -60,12 +423,47 @@ impl<P: mpc::Proof + StrictDumb> XAnchor<P> {
 }
 
 impl XAnchor<mpc::MerkleBlock> {
-    pub fn known_bundle_ids(&self) -> impl Iterator<Item = (BundleId, ContractId)> + '_ {
+    /// Returns set of distinct bundle ids known to the anchor together with
+    /// the contracts they belong to. See [`AnchorSet::known_bundle_ids`] for
+    /// the details.
+    pub fn known_bundle_ids(&self) -> BTreeSet<(BundleId, ContractId)> {
         match self {
             XAnchor::Bitcoin(anchor) | XAnchor::Liquid(anchor) => anchor.known_bundle_ids(),
         }
     }
 
+    /// Returns ids of the bundles of a specific contract known to the anchor.
+    /// See [`AnchorSet::bundle_ids_for`] for the details.
+    pub fn bundle_ids_for(&self, contract_id: ContractId) -> impl Iterator<Item = BundleId> {
+        self.as_reduced_unsafe().bundle_ids_for(contract_id)
+    }
+
+    /// Returns close methods committing to the given bundle of the contract.
+    /// See [`AnchorSet::methods_for`] for the details.
+    pub fn methods_for(
+        &self,
+        contract_id: ContractId,
+        bundle_id: BundleId,
+    ) -> SmallVec<CloseMethod> {
+        self.as_reduced_unsafe().methods_for(contract_id, bundle_id)
+    }
+
+    /// Returns set of contracts known to the anchor. See
+    /// [`AnchorSet::committed_contracts`] for the details.
+    pub fn committed_contracts(&self) -> BTreeSet<ContractId> {
+        self.as_reduced_unsafe().committed_contracts()
+    }
+
+    /// Returns number of distinct contracts known to the anchor. See
+    /// [`AnchorSet::contract_count`] for the details.
+    pub fn contract_count(&self) -> usize { self.as_reduced_unsafe().contract_count() }
+
+    /// Checks that the anchor commits exactly to the `expected` set of
+    /// contracts. See [`AnchorSet::commits_only_to`] for the details.
+    pub fn commits_only_to(&self, expected: &BTreeSet<ContractId>) -> bool {
+        self.as_reduced_unsafe().commits_only_to(expected)
+    }
+
     pub fn to_merkle_proof(
         &self,
         contract_id: ContractId,
@@ -129,6 +527,21 @@ impl<P: mpc::Proof + StrictDumb> AnchorSet<P> {
         }
     }
 
+    /// Returns the witness transaction id, failing with
+    /// [`AnchorError::TxidMismatch`] if the dual anchor commits to different
+    /// transactions.
+    pub fn checked_txid(&self) -> Result<Txid, AnchorError> {
+        match self {
+            AnchorSet::Dual { tapret, opret } if tapret.txid != opret.txid => {
+                Err(AnchorError::TxidMismatch {
+                    tapret: tapret.txid,
+                    opret: opret.txid,
+                })
+            }
+            _ => Ok(self.txid_unchecked()),
+        }
+    }
+
     pub fn txid_unchecked(&self) -> Txid {
         match self {
             AnchorSet::Tapret(a) => a.txid,
@@ -167,6 +580,26 @@ impl<P: mpc::Proof + StrictDumb> AnchorSet<P> {
         }
     }
 
+    /// Detects whether the anchor set contains anchor using a given close
+    /// method.
+    pub fn has_method(&self, method: CloseMethod) -> bool {
+        match (self, method) {
+            (AnchorSet::Tapret(_), CloseMethod::TapretFirst) |
+            (AnchorSet::Opret(_), CloseMethod::OpretFirst) |
+            (AnchorSet::Dual { .. }, _) => true,
+            (AnchorSet::Tapret(_), CloseMethod::OpretFirst) |
+            (AnchorSet::Opret(_), CloseMethod::TapretFirst) => false,
+        }
+    }
+
+    /// Projects the anchor set down to its tapret anchor, dropping the opret
+    /// one, if any. Returns `None` if the set has no tapret anchor.
+    pub fn keep_tapret(self) -> Option<Self> { self.into_split().0.map(Self::Tapret) }
+
+    /// Projects the anchor set down to its opret anchor, dropping the tapret
+    /// one, if any. Returns `None` if the set has no opret anchor.
+    pub fn keep_opret(self) -> Option<Self> { self.into_split().1.map(Self::Opret) }
+
     pub fn mpc_proofs(&self) -> impl Iterator<Item = &P> {
         let (t, o) = self.as_split();
         t.map(|a| &a.mpc_proof)
@@ -175,6 +608,76 @@ impl<P: mpc::Proof + StrictDumb> AnchorSet<P> {
     }
 }
 
+/// Taproot tweak of the witness transaction output committing to the tapret
+/// anchor, constructed with [`AnchorSet::tapret_tweak`].
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct TapretTweak {
+    /// Internal key of the taproot output.
+    pub internal_pk: InternalPk,
+    /// Merkle root of the taproot script tree containing the tapret
+    /// commitment.
+    pub merkle_root: TapNodeHash,
+}
+
+impl TapretTweak {
+    /// Computes the output key which the witness taproot output must have.
+    pub fn output_pk(&self) -> OutputPk {
+        let (output_pk, _) = self.internal_pk.to_output_pk(Some(self.merkle_root));
+        output_pk
+    }
+}
+
+/// Seal can't be closed by an anchor since the anchor doesn't use the close
+/// method required by the seal.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Display, Error)]
+#[display("seal {seal} requires {method} close method, which is not used by the anchor")]
+pub struct CloseMethodMismatch {
+    pub seal: XOutputSeal,
+    pub method: CloseMethod,
+}
+
+/// Verifies that the anchor of the bundle uses close methods required by each
+/// of the seals closed by the bundle witness.
+///
+/// A seal defined with tapret close method can't be closed by an anchor
+/// having only opret commitment, and vice versa.
+pub fn verify_close_method_consistency(
+    bundle: &AnchoredBundle,
+    seals: &[XOutputSeal],
+) -> Result<(), CloseMethodMismatch> {
+    let anchor = bundle.anchor.as_reduced_unsafe();
+    for seal in seals {
+        let method = seal.method();
+        if !anchor.has_method(method) {
+            return Err(CloseMethodMismatch {
+                seal: *seal,
+                method,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Recommends close method to use for anchoring a set of contract bundles into
+/// a witness transaction.
+///
+/// Both methods commit to a single MPC root, so the number of contracts does
+/// not affect the size of the on-chain commitment; it affects only the size
+/// of the client-side MPC proofs, which are the same for both methods. Thus,
+/// the decision depends only on the transaction structure:
+/// - if the transaction already has a taproot output, tapret commitment adds
+///   no on-chain data and is the cheapest option;
+/// - otherwise, opret commitment requires a single `OP_RETURN` output, which
+///   is cheaper than adding a new taproot output and later spending it via
+///   the script path.
+pub fn recommend_close_method(taproot_available: bool) -> CloseMethod {
+    if taproot_available {
+        CloseMethod::TapretFirst
+    } else {
+        CloseMethod::OpretFirst
+    }
+}
+
 impl AnchorSet<mpc::MerkleProof> {
     pub fn to_merkle_block(
         &self,
@@ -198,15 +701,240 @@ impl AnchorSet<mpc::MerkleProof> {
             .transpose()?;
         Ok(AnchorSet::from_split(tapret, opret).expect("one must be non-None"))
     }
+
+    /// Checks whether the anchor set commits to the same data as the `other`
+    /// one, which holds MPC blocks instead of MPC proofs. See
+    /// [`AnchorSet::<mpc::MerkleBlock>::commits_same_as`] for the details.
+    pub fn commits_same_as(&self, other: &AnchorSet<mpc::MerkleBlock>) -> bool {
+        other.commits_same_as(self)
+    }
 }
 
 impl AnchorSet<mpc::MerkleBlock> {
-    pub fn known_bundle_ids(&self) -> impl Iterator<Item = (BundleId, ContractId)> + '_ {
-        self.mpc_proofs().flat_map(|p| {
+    /// Checks whether the anchor set commits to the same data as the `other`
+    /// one, which holds MPC proofs instead of MPC blocks.
+    ///
+    /// Unlike the derived equality, the check ignores the representation of
+    /// the MPC part: the sets are logically equal if they use the same close
+    /// methods, commit in the same witness transaction with the same DBC
+    /// proofs, and the MPC proof of each `other` anchor convolves into the MPC
+    /// commitment of this set for one of the messages revealed in its block.
+    ///
+    /// NB: since MPC proof doesn't contain the message, the check can't detect
+    /// a proof taken from a different tree of the same shape which commits to
+    /// other messages under the same protocols.
+    pub fn commits_same_as(&self, other: &AnchorSet) -> bool {
+        fn same<D: dbc::Proof + Eq>(
+            block: &Anchor<mpc::MerkleBlock, D>,
+            proof: &Anchor<mpc::MerkleProof, D>,
+        ) -> bool {
+            let commitment = block.mpc_proof.commitment_id();
+            block.txid == proof.txid &&
+                block.dbc_proof == proof.dbc_proof &&
+                block
+                    .mpc_proof
+                    .to_known_message_map()
+                    .into_iter()
+                    .any(|(protocol_id, message)| {
+                        matches!(
+                            proof.mpc_proof.convolve(protocol_id, message),
+                            Ok(c) if c == commitment
+                        )
+                    })
+        }
+
+        let (tapret1, opret1) = self.as_split();
+        let (tapret2, opret2) = other.as_split();
+        let tapret = match (tapret1, tapret2) {
+            (Some(block), Some(proof)) => same(block, proof),
+            (None, None) => true,
+            _ => false,
+        };
+        let opret = match (opret1, opret2) {
+            (Some(block), Some(proof)) => same(block, proof),
+            (None, None) => true,
+            _ => false,
+        };
+        tapret && opret
+    }
+
+    /// Returns set of distinct bundle ids known to the anchor together with
+    /// the contracts they belong to.
+    ///
+    /// In [`AnchorSet::Dual`] both tapret and opret MPC trees may commit to the
+    /// same bundle; such bundle is reported only once.
+    pub fn known_bundle_ids(&self) -> BTreeSet<(BundleId, ContractId)> {
+        self.mpc_proofs()
+            .flat_map(|p| {
+                p.to_known_message_map()
+                    .into_iter()
+                    .map(|(p, m)| (m.into(), p.into()))
+            })
+            .collect()
+    }
+
+    /// Returns ids of the bundles of a specific contract known to the anchor.
+    ///
+    /// MPC blocks expose the revealed messages only as a whole message map, so
+    /// the map is still built for each block; the contract leaf alone can be
+    /// checked against a known bundle id with [`AnchorSet::methods_for`].
+    ///
+    /// Each MPC tree commits to a single message per contract, thus the
+    /// iterator yields at most one bundle id for tapret or opret anchors. In
+    /// [`AnchorSet::Dual`] the trees may commit to different bundles of the
+    /// same contract; a bundle committed in both trees is reported only once.
+    pub fn bundle_ids_for(&self, contract_id: ContractId) -> impl Iterator<Item = BundleId> {
+        let protocol_id = mpc::ProtocolId::from(contract_id);
+        let mut ids = self.mpc_proofs().filter_map(|p| {
             p.to_known_message_map()
-                .into_iter()
-                .map(|(p, m)| (m.into(), p.into()))
-        })
+                .get(&protocol_id)
+                .map(|m| BundleId::from(*m))
+        });
+        let first = ids.next();
+        let second = ids.next().filter(|id| Some(*id) != first);
+        first.into_iter().chain(second)
+    }
+
+    /// Returns close methods whose MPC proofs commit to the given bundle of
+    /// the contract, in the order of tapret followed by opret.
+    ///
+    /// Unlike [`AnchorSet::has_method`] this reports only the methods actually
+    /// carrying the commitment to the bundle: in [`AnchorSet::Dual`] a bundle
+    /// may be committed with just one of the methods. An empty result means
+    /// the bundle is not known to the anchor.
+    pub fn methods_for(
+        &self,
+        contract_id: ContractId,
+        bundle_id: BundleId,
+    ) -> SmallVec<CloseMethod> {
+        let protocol_id = mpc::ProtocolId::from(contract_id);
+        let message = mpc::Message::from(bundle_id);
+        // The block reveals a single message per protocol, thus the proof of
+        // the contract leaf convolves into the block commitment only for the
+        // message revealed in it.
+        let commits = |block: &mpc::MerkleBlock| {
+            let commitment = block
+                .to_merkle_proof(protocol_id)
+                .ok()
+                .and_then(|proof| proof.convolve(protocol_id, message).ok());
+            commitment == Some(block.commitment_id())
+        };
+        let (tapret, opret) = self.as_split();
+        let tapret = tapret
+            .filter(|anchor| commits(&anchor.mpc_proof))
+            .map(|_| CloseMethod::TapretFirst);
+        let opret = opret
+            .filter(|anchor| commits(&anchor.mpc_proof))
+            .map(|_| CloseMethod::OpretFirst);
+        Confined::from_collection_unsafe(tapret.into_iter().chain(opret).collect())
+    }
+
+    /// Returns set of contracts known to the anchor, i.e. revealed in any of
+    /// its MPC blocks.
+    pub fn committed_contracts(&self) -> BTreeSet<ContractId> {
+        self.known_bundle_ids()
+            .into_iter()
+            .map(|(_, contract_id)| contract_id)
+            .collect()
+    }
+
+    /// Returns number of distinct contracts known to the anchor, i.e. the
+    /// size of [`AnchorSet::committed_contracts`], without collecting the
+    /// bundle ids.
+    ///
+    /// Anchors with a count above one batch multiple contracts into a single
+    /// witness transaction. As with the other accessors, only the contracts
+    /// revealed in the MPC blocks are counted.
+    pub fn contract_count(&self) -> usize {
+        match self {
+            AnchorSet::Tapret(anchor) => anchor.mpc_proof.to_known_message_map().len(),
+            AnchorSet::Opret(anchor) => anchor.mpc_proof.to_known_message_map().len(),
+            AnchorSet::Dual { tapret, opret } => {
+                let tapret = tapret.mpc_proof.to_known_message_map();
+                let opret = opret.mpc_proof.to_known_message_map();
+                tapret.len() +
+                    opret
+                        .keys()
+                        .filter(|protocol_id| !tapret.contains_key(*protocol_id))
+                        .count()
+            }
+        }
+    }
+
+    /// Checks that the contracts revealed in the anchor are exactly the
+    /// `expected` ones.
+    ///
+    /// NB: the concealed parts of the MPC blocks can't be inspected, so the
+    /// check covers only the contracts which are revealed.
+    pub fn commits_only_to(&self, expected: &BTreeSet<ContractId>) -> bool {
+        self.committed_contracts() == *expected
+    }
+
+    /// Computes scriptPubkeys which must be present in the witness transaction
+    /// outputs for each of the anchor commitment methods.
+    ///
+    /// For tapret it is the taproot output key tweaked with the MPC commitment,
+    /// and for opret it is the `OP_RETURN` output pushing the MPC commitment.
+    ///
+    /// # Errors
+    ///
+    /// If the tapret proof internal key can't be tweaked with the commitment.
+    pub fn expected_commitment_script(
+        &self,
+    ) -> Result<Vec<(CloseMethod, ScriptPubkey)>, TapretKeyError> {
+        let mut scripts = Vec::with_capacity(2);
+        if let Some(tweak) = self.tapret_tweak()? {
+            let script = ScriptPubkey::p2tr_tweaked(tweak.output_pk());
+            scripts.push((CloseMethod::TapretFirst, script));
+        }
+        if let Some(payload) = self.opret_commitment() {
+            let script = ScriptPubkey::op_return(&payload);
+            scripts.push((CloseMethod::OpretFirst, script));
+        }
+        Ok(scripts)
+    }
+
+    /// Returns the taproot tweak which the witness transaction taproot output
+    /// must commit to for the tapret anchor to verify, or `None` if the set
+    /// has no tapret anchor.
+    ///
+    /// # Errors
+    ///
+    /// If the tapret path proof partner node contains an alternative
+    /// commitment or is not ordered correctly with the commitment node.
+    pub fn tapret_tweak(&self) -> Result<Option<TapretTweak>, TapretKeyError> {
+        let (tapret, _) = self.as_split();
+        let Some(tapret) = tapret else {
+            return Ok(None);
+        };
+        let path_proof = &tapret.dbc_proof.path_proof;
+        let commitment =
+            TapretCommitment::with(tapret.mpc_proof.commitment_id(), path_proof.nonce());
+        let leaf = TapLeafHash::with_tap_script(&TapScript::commit(&commitment));
+        let merkle_root = match path_proof.partner_node() {
+            None => leaf.into(),
+            Some(ref partner) if !partner.check_no_commitment() => {
+                return Err(TapretKeyError::AlternativeCommitment(partner.clone()));
+            }
+            Some(ref partner) if !partner.check_ordering(leaf.into()) => {
+                return Err(TapretKeyError::IncorrectOrdering(partner.clone(), leaf));
+            }
+            Some(ref partner) => {
+                TapBranchHash::with_nodes(leaf.into(), partner.tap_node_hash()).into()
+            }
+        };
+        Ok(Some(TapretTweak {
+            internal_pk: tapret.dbc_proof.internal_pk,
+            merkle_root,
+        }))
+    }
+
+    /// Returns the exact `OP_RETURN` payload which the witness transaction
+    /// must contain for the opret anchor to verify, or `None` if the set has
+    /// no opret anchor.
+    pub fn opret_commitment(&self) -> Option<Vec<u8>> {
+        let (_, opret) = self.as_split();
+        opret.map(|opret| opret.mpc_proof.commitment_id().as_slice().to_vec())
     }
 
     pub fn to_merkle_proof(
@@ -270,6 +998,17 @@ impl WitnessAnchor {
             witness_id,
         }
     }
+
+    /// Constructs anchor for a witness mined at a given block height and
+    /// block timestamp, which together define the consensus position of the
+    /// witness (see [`WitnessPos`]). Returns `None` if the height is zero or
+    /// the timestamp precedes the bitcoin genesis block.
+    pub fn mined(witness_id: WitnessId, height: u32, timestamp: i64) -> Option<Self> {
+        WitnessPos::new(height, timestamp).map(|pos| WitnessAnchor {
+            witness_ord: WitnessOrd::OnChain(pos),
+            witness_id,
+        })
+    }
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Display)]
@@ -287,3 +1026,772 @@ pub enum Layer1 {
     Bitcoin = 0,
     Liquid = 1,
 }
+
+/// Specific network of a layer 1 blockchain on which contract seals are
+/// defined.
+///
+/// Seals and witness ids do not carry network information by themselves: the
+/// network is a property of the whole contract, fixed by its genesis (see
+/// [`crate::Genesis::supports_chain_net`]). Thus, all seals of a contract are
+/// always interpreted within the same network, and seals from different
+/// networks can't be mixed within one contract.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Display)]
+#[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB, tags = repr, into_u8, try_from_u8)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+#[repr(u8)]
+pub enum ChainNet {
+    #[strict_type(dumb)]
+    #[display("bitcoin")]
+    BitcoinMainnet = 0x00,
+    #[display("testnet")]
+    BitcoinTestnet = 0x01,
+    #[display("signet")]
+    BitcoinSignet = 0x02,
+    #[display("regtest")]
+    BitcoinRegtest = 0x03,
+    #[display("liquid")]
+    LiquidMainnet = 0x10,
+    #[display("liquid-testnet")]
+    LiquidTestnet = 0x11,
+}
+
+impl ChainNet {
+    /// Returns layer 1 blockchain of the network.
+    pub fn layer1(self) -> Layer1 {
+        match self {
+            ChainNet::BitcoinMainnet |
+            ChainNet::BitcoinTestnet |
+            ChainNet::BitcoinSignet |
+            ChainNet::BitcoinRegtest => Layer1::Bitcoin,
+            ChainNet::LiquidMainnet | ChainNet::LiquidTestnet => Layer1::Liquid,
+        }
+    }
+
+    /// Detects whether the network is a test network, i.e. network with no
+    /// economic value.
+    pub fn is_testnet(self) -> bool {
+        !matches!(self, ChainNet::BitcoinMainnet | ChainNet::LiquidMainnet)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use amplify::num::u5;
+    use bp::dbc::tapret::{TapretNodePartner, TapretPathProof};
+    use bp::dbc::Method;
+    use bp::LeafScript;
+    use commit_verify::{ConvolveCommit, TryCommitVerify};
+
+    use super::*;
+    use crate::{
+        Assign, AssignmentType, Assignments, GraphSeal, OutputSeal, TypedAssigns, VoidState,
+    };
+
+    fn merkle_block(contract_id: ContractId, bundle_id: BundleId) -> mpc::MerkleBlock {
+        let src = mpc::MultiSource {
+            min_depth: u5::ZERO,
+            messages: Confined::from_collection_unsafe(bmap! {
+                mpc::ProtocolId::from(contract_id) => mpc::Message::from(bundle_id),
+            }),
+            static_entropy: Some(0),
+        };
+        mpc::MerkleBlock::from(mpc::MerkleTree::try_commit(&src).unwrap())
+    }
+
+    #[test]
+    fn commitment_scripts() {
+        let contract_id = ContractId::from([1u8; 32]);
+        let block = merkle_block(contract_id, BundleId::from([2u8; 32]));
+        let commitment = block.commitment_id();
+        let anchor = Anchor {
+            txid: Txid::from([3u8; 32]),
+            mpc_proof: block.clone(),
+            dbc_proof: OpretProof::default(),
+            _method: default!(),
+        };
+        let opret = ScriptPubkey::op_return(commitment.as_slice());
+        let set = AnchorSet::Opret(anchor.clone());
+        assert_eq!(set.opret_commitment(), Some(commitment.as_slice().to_vec()));
+        let expected = vec![(CloseMethod::OpretFirst, opret.clone())];
+        assert_eq!(set.expected_commitment_script(), Ok(expected));
+
+        let tapret = Anchor {
+            txid: Txid::from([3u8; 32]),
+            mpc_proof: block,
+            dbc_proof: TapretProof::strict_dumb(),
+            _method: default!(),
+        };
+        let set = AnchorSet::Dual {
+            tapret,
+            opret: anchor,
+        };
+        assert_eq!(set.opret_commitment(), Some(commitment.as_slice().to_vec()));
+        assert_eq!(set.clone().keep_tapret().unwrap().opret_commitment(), None);
+        assert_eq!(set.clone().keep_opret().unwrap().tapret_tweak(), Ok(None));
+        let tweak = set.tapret_tweak().unwrap().unwrap();
+        let proof = TapretProof::strict_dumb();
+        let (output_pk, _) =
+            proof.internal_pk.convolve_commit(&proof.path_proof, &commitment).unwrap();
+        assert_eq!(tweak.internal_pk, proof.internal_pk);
+        assert_eq!(tweak.output_pk(), output_pk);
+        let scripts = set.expected_commitment_script().unwrap();
+        assert_eq!(scripts.len(), 2);
+        assert_eq!(scripts[0].0, CloseMethod::TapretFirst);
+        assert!(scripts[0].1.is_p2tr());
+        assert_eq!(scripts[1], (CloseMethod::OpretFirst, opret));
+    }
+
+    #[test]
+    fn tapret_tweak_partners() {
+        let contract_id = ContractId::from([1u8; 32]);
+        let block = merkle_block(contract_id, BundleId::from([2u8; 32]));
+        let commitment = block.commitment_id();
+        let internal_pk = TapretProof::strict_dumb().internal_pk;
+        let leaf = LeafScript::from_tap_script(TapScript::from_unsafe(vec![0x51]));
+        let partners = [
+            TapretNodePartner::LeftNode(TapNodeHash::from([0x80; 32])),
+            TapretNodePartner::RightLeaf(leaf),
+            TapretNodePartner::right_branch(
+                TapNodeHash::from([0x11; 32]),
+                TapNodeHash::from([0x22; 32]),
+            ),
+        ];
+        for partner in partners {
+            let (mut verified, mut failed) = (0, 0);
+            for nonce in 0..=u8::MAX {
+                let path_proof = TapretPathProof::with(partner.clone(), nonce).unwrap();
+                let set = AnchorSet::Tapret(Anchor {
+                    txid: Txid::from([3u8; 32]),
+                    mpc_proof: block.clone(),
+                    dbc_proof: TapretProof {
+                        path_proof: path_proof.clone(),
+                        internal_pk,
+                    },
+                    _method: default!(),
+                });
+                match (internal_pk.convolve_commit(&path_proof, &commitment), set.tapret_tweak()) {
+                    (Ok((output_pk, _)), Ok(Some(tweak))) => {
+                        assert_eq!(tweak.internal_pk, internal_pk);
+                        assert_eq!(tweak.output_pk(), output_pk);
+                        verified += 1;
+                    }
+                    (Err(expected), Err(err)) => {
+                        assert_eq!(err, expected);
+                        failed += 1;
+                    }
+                    (expected, tweak) => panic!("bp gives {expected:?} while tweak is {tweak:?}"),
+                }
+            }
+            assert!(verified > 0 && failed > 0);
+        }
+    }
+
+    #[test]
+    fn commits_same() {
+        let contract_id = ContractId::from([1u8; 32]);
+        let block = merkle_block(contract_id, BundleId::from([2u8; 32]));
+        let anchor = |txid: Txid| Anchor {
+            txid,
+            mpc_proof: block.clone(),
+            dbc_proof: OpretProof::default(),
+            _method: default!(),
+        };
+        let set = AnchorSet::Opret(anchor(Txid::from([3u8; 32])));
+        let proof = set.to_merkle_proof(contract_id).unwrap();
+        assert!(set.commits_same_as(&proof));
+        assert!(proof.commits_same_as(&set));
+
+        let src = mpc::MultiSource {
+            min_depth: u5::ZERO,
+            messages: Confined::from_collection_unsafe(bmap! {
+                mpc::ProtocolId::from(contract_id) => mpc::Message::from([2u8; 32]),
+                mpc::ProtocolId::from([6u8; 32]) => mpc::Message::from([7u8; 32]),
+            }),
+            static_entropy: Some(0),
+        };
+        let other = mpc::MerkleBlock::from(mpc::MerkleTree::try_commit(&src).unwrap());
+        let other = AnchorSet::Opret(Anchor {
+            txid: Txid::from([3u8; 32]),
+            mpc_proof: other,
+            dbc_proof: OpretProof::default(),
+            _method: default!(),
+        });
+        assert!(!other.commits_same_as(&proof));
+
+        let moved = AnchorSet::Opret(anchor(Txid::from([5u8; 32])));
+        assert!(!moved.commits_same_as(&proof));
+
+        let dual = AnchorSet::Dual {
+            tapret: Anchor {
+                txid: Txid::from([3u8; 32]),
+                mpc_proof: block.clone(),
+                dbc_proof: TapretProof::strict_dumb(),
+                _method: default!(),
+            },
+            opret: anchor(Txid::from([3u8; 32])),
+        };
+        assert!(!dual.commits_same_as(&proof));
+        let dual_proof = dual.to_merkle_proof(contract_id).unwrap();
+        assert!(dual.commits_same_as(&dual_proof));
+        assert!(!set.commits_same_as(&dual_proof));
+    }
+
+    #[test]
+    fn anchor_errors() {
+        let anchor = |txid: Txid| Anchor {
+            txid,
+            mpc_proof: mpc::MerkleBlock::strict_dumb(),
+            dbc_proof: OpretProof::default(),
+            _method: default!(),
+        };
+        let tapret = |txid: Txid| Anchor {
+            txid,
+            mpc_proof: mpc::MerkleBlock::strict_dumb(),
+            dbc_proof: TapretProof::strict_dumb(),
+            _method: default!(),
+        };
+        let (a, b) = (Txid::from([1u8; 32]), Txid::from([2u8; 32]));
+        assert_eq!(AnchorSet::Opret(anchor(a)).checked_txid(), Ok(a));
+        let set = AnchorSet::Dual {
+            tapret: tapret(a),
+            opret: anchor(a),
+        };
+        assert_eq!(set.checked_txid(), Ok(a));
+        let set = AnchorSet::Dual {
+            tapret: tapret(a),
+            opret: anchor(b),
+        };
+        assert_eq!(set.checked_txid(), Err(AnchorError::TxidMismatch { tapret: a, opret: b }));
+
+        let err = set
+            .to_merkle_proof(ContractId::from([3u8; 32]))
+            .map_err(AnchorError::from)
+            .unwrap_err();
+        assert!(matches!(err, AnchorError::LeafNotKnown(_)));
+        let verify = AnchorVerifyError::WitnessMismatch(BundleId::from([4u8; 32]));
+        assert_eq!(AnchorError::from(verify.clone()).to_string(), verify.to_string());
+    }
+
+    #[test]
+    fn bundle_pruning() {
+        let contract_id = ContractId::from([1u8; 32]);
+        let seal = XChain::Bitcoin(GraphSeal::with_blinded_vout(Method::OpretFirst, 0, 0));
+        let mut transition = Transition::strict_dumb();
+        transition.contract_id = contract_id;
+        transition.assignments = Assignments::from(Confined::from_collection_unsafe(bmap! {
+            AssignmentType::with(1) => TypedAssigns::Declarative(
+                Confined::from_collection_unsafe(vec![Assign::revealed(seal, VoidState::default())])
+            ),
+        }));
+        let opid = transition.id();
+        let bundle = TransitionBundle::with_single(0u32.into(), transition);
+        let mpc_proof = merkle_block(contract_id, bundle.bundle_id())
+            .into_merkle_proof(contract_id.into())
+            .unwrap();
+        let anchored = AnchoredBundle {
+            anchor: XAnchor::Bitcoin(AnchorSet::Opret(Anchor {
+                txid: Txid::from([3u8; 32]),
+                mpc_proof,
+                dbc_proof: strict_dumb!(),
+                _method: default!(),
+            })),
+            bundle,
+        };
+
+        let pruned = anchored.prune();
+        assert_eq!(pruned.bundle_id(), anchored.bundle_id());
+        assert_eq!(pruned.anchor, anchored.anchor);
+        assert_eq!(pruned.verify(contract_id), Ok(()));
+        let transition = &pruned.bundle.known_transitions[&opid];
+        assert_eq!(transition.id(), opid);
+        assert_eq!(transition.assignments.values().next().unwrap().revealed_seal_at(0), Ok(None));
+    }
+
+    #[test]
+    fn dual_known_bundle_ids() {
+        let contract_id = ContractId::from([1u8; 32]);
+        let bundle_id = BundleId::from([2u8; 32]);
+        let block = merkle_block(contract_id, bundle_id);
+        let txid = Txid::from([3u8; 32]);
+        let set = AnchorSet::Dual {
+            tapret: Anchor {
+                txid,
+                mpc_proof: block.clone(),
+                dbc_proof: strict_dumb!(),
+                _method: default!(),
+            },
+            opret: Anchor {
+                txid,
+                mpc_proof: block,
+                dbc_proof: strict_dumb!(),
+                _method: default!(),
+            },
+        };
+        assert_eq!(set.known_bundle_ids(), bset![(bundle_id, contract_id)]);
+        assert_eq!(set.contract_count(), 1);
+        assert_eq!(set.bundle_ids_for(contract_id).collect::<Vec<_>>(), vec![bundle_id]);
+        assert_eq!(set.bundle_ids_for(ContractId::from([2u8; 32])).count(), 0);
+        let xanchor = XAnchor::Bitcoin(set);
+        assert_eq!(xanchor.known_bundle_ids(), bset![(bundle_id, contract_id)]);
+        assert_eq!(xanchor.bundle_ids_for(contract_id).collect::<Vec<_>>(), vec![bundle_id]);
+    }
+
+    #[test]
+    fn methods_for() {
+        let contract_id = ContractId::from([1u8; 32]);
+        let tapret_bundle = BundleId::from([2u8; 32]);
+        let opret_bundle = BundleId::from([3u8; 32]);
+        let txid = Txid::from([4u8; 32]);
+        let set = AnchorSet::Dual {
+            tapret: Anchor {
+                txid,
+                mpc_proof: merkle_block(contract_id, tapret_bundle),
+                dbc_proof: strict_dumb!(),
+                _method: default!(),
+            },
+            opret: Anchor {
+                txid,
+                mpc_proof: merkle_block(contract_id, opret_bundle),
+                dbc_proof: strict_dumb!(),
+                _method: default!(),
+            },
+        };
+        let methods = |set: &AnchorSet<mpc::MerkleBlock>, bundle_id| {
+            set.methods_for(contract_id, bundle_id).into_inner()
+        };
+        assert_eq!(methods(&set, tapret_bundle), vec![CloseMethod::TapretFirst]);
+        assert_eq!(methods(&set, opret_bundle), vec![CloseMethod::OpretFirst]);
+        assert!(methods(&set, BundleId::from([5u8; 32])).is_empty());
+        assert!(set.methods_for(ContractId::from([6u8; 32]), tapret_bundle).is_empty());
+
+        let (Some(tapret), Some(opret)) = set.into_split() else {
+            unreachable!()
+        };
+        let set = AnchorSet::Dual {
+            tapret,
+            opret: Anchor {
+                mpc_proof: merkle_block(contract_id, tapret_bundle),
+                ..opret
+            },
+        };
+        assert_eq!(methods(&set, tapret_bundle), vec![
+            CloseMethod::TapretFirst,
+            CloseMethod::OpretFirst
+        ]);
+        let xanchor = XAnchor::Bitcoin(set.keep_opret().unwrap());
+        assert_eq!(xanchor.methods_for(contract_id, tapret_bundle).into_inner(), vec![
+            CloseMethod::OpretFirst
+        ]);
+    }
+
+    #[test]
+    fn committed_contracts() {
+        let contract1 = ContractId::from([1u8; 32]);
+        let contract2 = ContractId::from([2u8; 32]);
+        let src = mpc::MultiSource {
+            min_depth: u5::with(2),
+            messages: Confined::from_collection_unsafe(bmap! {
+                mpc::ProtocolId::from(contract1) => mpc::Message::from([3u8; 32]),
+                mpc::ProtocolId::from(contract2) => mpc::Message::from([4u8; 32]),
+            }),
+            static_entropy: Some(0),
+        };
+        let block = mpc::MerkleBlock::from(mpc::MerkleTree::try_commit(&src).unwrap());
+        let anchor = XAnchor::Liquid(AnchorSet::Opret(Anchor {
+            txid: Txid::from([3u8; 32]),
+            mpc_proof: block,
+            dbc_proof: strict_dumb!(),
+            _method: default!(),
+        }));
+
+        assert_eq!(anchor.committed_contracts(), bset![contract1, contract2]);
+        assert_eq!(anchor.contract_count(), 2);
+        assert!(anchor.commits_only_to(&bset![contract1, contract2]));
+        assert!(!anchor.commits_only_to(&bset![contract1]));
+        assert!(!anchor.commits_only_to(&bset![contract1, contract2, ContractId::from([5u8; 32])]));
+    }
+
+    #[test]
+    fn close_method_recommendation() {
+        assert_eq!(recommend_close_method(true), CloseMethod::TapretFirst);
+        assert_eq!(recommend_close_method(false), CloseMethod::OpretFirst);
+    }
+
+    #[test]
+    fn grouping_by_witness() {
+        let anchored = |txid: u8, bundle: TransitionBundle| AnchoredBundle {
+            anchor: XAnchor::Bitcoin(AnchorSet::Opret(Anchor {
+                txid: Txid::from([txid; 32]),
+                mpc_proof: strict_dumb!(),
+                dbc_proof: strict_dumb!(),
+                _method: default!(),
+            })),
+            bundle,
+        };
+        let mut bundle = TransitionBundle::strict_dumb();
+        let first = anchored(1, bundle.clone());
+        bundle.input_map = confined_bmap! { 1u32.into() => strict_dumb!() };
+        let second = anchored(1, bundle);
+        let third = anchored(2, strict_dumb!());
+
+        let groups = group_by_witness([first.clone(), third.clone(), second.clone()]);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[&XChain::Bitcoin(Txid::from([1u8; 32]))], vec![first, second]);
+        assert_eq!(groups[&XChain::Bitcoin(Txid::from([2u8; 32]))], vec![third]);
+    }
+
+    #[test]
+    fn chain_net() {
+        assert_eq!(ChainNet::BitcoinMainnet.layer1(), Layer1::Bitcoin);
+        assert_eq!(ChainNet::BitcoinSignet.layer1(), Layer1::Bitcoin);
+        assert_eq!(ChainNet::LiquidTestnet.layer1(), Layer1::Liquid);
+        assert!(!ChainNet::BitcoinMainnet.is_testnet());
+        assert!(!ChainNet::LiquidMainnet.is_testnet());
+        assert!(ChainNet::BitcoinRegtest.is_testnet());
+        assert!(ChainNet::LiquidTestnet.is_testnet());
+    }
+
+    #[test]
+    fn dual_root_mismatch() {
+        let contract_id = ContractId::from([1u8; 32]);
+        let bundle = TransitionBundle::with_single(0u32.into(), {
+            let mut transition = Transition::strict_dumb();
+            transition.contract_id = contract_id;
+            transition
+        });
+        let bundle_id = bundle.bundle_id();
+        let opret = Anchor {
+            txid: Txid::from([3u8; 32]),
+            mpc_proof: merkle_block(contract_id, bundle_id)
+                .into_merkle_proof(contract_id.into())
+                .unwrap(),
+            dbc_proof: OpretProof::default(),
+            _method: default!(),
+        };
+        let src = mpc::MultiSource {
+            min_depth: u5::ZERO,
+            messages: Confined::from_collection_unsafe(bmap! {
+                mpc::ProtocolId::from(contract_id) => mpc::Message::from(bundle_id),
+                mpc::ProtocolId::from([6u8; 32]) => mpc::Message::from([7u8; 32]),
+            }),
+            static_entropy: Some(0),
+        };
+        let tapret = Anchor {
+            txid: opret.txid,
+            mpc_proof: mpc::MerkleBlock::from(mpc::MerkleTree::try_commit(&src).unwrap())
+                .into_merkle_proof(contract_id.into())
+                .unwrap(),
+            dbc_proof: TapretProof::strict_dumb(),
+            _method: default!(),
+        };
+        let mut dual = AnchoredBundle {
+            anchor: XAnchor::Bitcoin(AnchorSet::Dual {
+                tapret: tapret.clone(),
+                opret: opret.clone(),
+            }),
+            bundle,
+        };
+        assert_eq!(dual.verify(contract_id), Err(AnchorVerifyError::DualRootMismatch(bundle_id)));
+
+        let tapret = Anchor {
+            mpc_proof: opret.mpc_proof.clone(),
+            ..tapret
+        };
+        dual.anchor = XAnchor::Bitcoin(AnchorSet::Dual { tapret, opret });
+        assert_eq!(dual.verify(contract_id), Ok(()));
+    }
+
+    #[test]
+    fn bundle_verification() {
+        let contract_id = ContractId::from([1u8; 32]);
+        let anchored = |bundle: TransitionBundle| {
+            let mpc_proof = merkle_block(contract_id, bundle.bundle_id())
+                .into_merkle_proof(contract_id.into())
+                .unwrap();
+            AnchoredBundle {
+                anchor: XAnchor::Bitcoin(AnchorSet::Opret(Anchor {
+                    txid: Txid::from([3u8; 32]),
+                    mpc_proof,
+                    dbc_proof: strict_dumb!(),
+                    _method: default!(),
+                })),
+                bundle,
+            }
+        };
+        let transition = |ty: u16, contract_id: ContractId| {
+            let mut transition = Transition::strict_dumb();
+            transition.contract_id = contract_id;
+            transition.transition_type = ty.into();
+            transition
+        };
+
+        let valid =
+            anchored(TransitionBundle::with_single(0u32.into(), transition(1, contract_id)));
+        assert_eq!(valid.verify(contract_id), Ok(()));
+
+        let t = transition(2, ContractId::from([2u8; 32]));
+        let opid = t.id();
+        let foreign = anchored(TransitionBundle::with_single(0u32.into(), t));
+        assert_eq!(
+            foreign.verify(contract_id),
+            Err(AnchorVerifyError::ContractMismatch(opid, ContractId::from([2u8; 32])))
+        );
+
+        let t = transition(3, contract_id);
+        let opid = t.id();
+        let uncommitted = anchored(TransitionBundle {
+            input_map: confined_bmap! { 0u32.into() => OpId::from([4u8; 32]) },
+            known_transitions: confined_bmap! { opid => t },
+        });
+        let bundle_id = uncommitted.bundle_id();
+        assert_eq!(
+            uncommitted.verify(contract_id),
+            Err(AnchorVerifyError::UncommittedTransition(bundle_id, opid))
+        );
+
+        let mut dual = valid.clone();
+        let XAnchor::Bitcoin(AnchorSet::Opret(opret)) = valid.anchor.clone() else {
+            unreachable!()
+        };
+        let mut tapret = Anchor {
+            txid: Txid::from([5u8; 32]),
+            mpc_proof: opret.mpc_proof.clone(),
+            dbc_proof: strict_dumb!(),
+            _method: default!(),
+        };
+        dual.anchor = XAnchor::Bitcoin(AnchorSet::Dual {
+            tapret: tapret.clone(),
+            opret: opret.clone(),
+        });
+        assert_eq!(
+            dual.verify(contract_id),
+            Err(AnchorVerifyError::WitnessMismatch(dual.bundle_id()))
+        );
+        tapret.txid = opret.txid;
+        dual.anchor = XAnchor::Bitcoin(AnchorSet::Dual { tapret, opret });
+        assert_eq!(dual.verify(contract_id), Ok(()));
+
+        let bundles = [valid, foreign, uncommitted, dual]
+            .into_iter()
+            .cycle()
+            .take(41)
+            .collect::<Vec<_>>();
+        let results = validate_bundles(&bundles, contract_id);
+        assert_eq!(results.len(), 41);
+        assert_eq!(results.iter().filter(|res| res.is_ok()).count(), 21);
+        #[cfg(feature = "rayon")]
+        {
+            assert_eq!(validate_bundles_par(&bundles, contract_id), results);
+            assert_eq!(validate_bundles_par(&[], contract_id), vec![]);
+        }
+    }
+
+    #[test]
+    fn split_for_recipient() {
+        let contract_id = ContractId::from([1u8; 32]);
+        let transition = |ty: u16| {
+            let mut transition = Transition::strict_dumb();
+            transition.contract_id = contract_id;
+            transition.transition_type = ty.into();
+            (transition.id(), transition)
+        };
+        let (opid1, t1) = transition(1);
+        let (opid2, t2) = transition(2);
+        let bundle = TransitionBundle {
+            input_map: confined_bmap! { 0u32.into() => opid1, 1u32.into() => opid2 },
+            known_transitions: confined_bmap! { opid1 => t1.clone(), opid2 => t2 },
+        };
+        let bundle_id = bundle.bundle_id();
+        let mpc_proof = merkle_block(contract_id, bundle_id)
+            .into_merkle_proof(contract_id.into())
+            .unwrap();
+        let anchored = AnchoredBundle {
+            anchor: XAnchor::Bitcoin(AnchorSet::Opret(Anchor {
+                txid: Txid::from([3u8; 32]),
+                mpc_proof,
+                dbc_proof: strict_dumb!(),
+                _method: default!(),
+            })),
+            bundle,
+        };
+
+        let split = anchored.split_for(contract_id, &bset![opid1]).unwrap();
+        assert_eq!(split.bundle_id(), bundle_id);
+        assert_eq!(split.anchor, anchored.anchor);
+        assert_eq!(split.bundle.known_transitions.len(), 1);
+        assert_eq!(split.bundle.known_transitions.get(&opid1), Some(&t1));
+
+        assert_eq!(anchored.split_for(contract_id, &bset![]), Err(SplitError::NoTransitions));
+        let opid = OpId::from([9u8; 32]);
+        assert_eq!(
+            anchored.split_for(contract_id, &bset![opid]),
+            Err(SplitError::UnknownTransition(opid))
+        );
+        let other_id = ContractId::from([2u8; 32]);
+        assert!(matches!(anchored.split_for(other_id, &bset![opid1]), Err(SplitError::Anchor(_))));
+        assert!(matches!(anchored.split_for(other_id, &bset![]), Err(SplitError::Anchor(_))));
+    }
+
+    #[test]
+    fn inclusion_proof() {
+        let contract_id = ContractId::from([1u8; 32]);
+        let transition = |ty: u16| {
+            let mut transition = Transition::strict_dumb();
+            transition.contract_id = contract_id;
+            transition.transition_type = ty.into();
+            (transition.id(), transition)
+        };
+        let (opid1, t1) = transition(1);
+        let (opid2, t2) = transition(2);
+        let bundle = TransitionBundle {
+            input_map: confined_bmap! {
+                0u32.into() => opid1,
+                1u32.into() => opid2,
+                2u32.into() => opid1
+            },
+            known_transitions: confined_bmap! { opid1 => t1.clone(), opid2 => t2 },
+        };
+        let block = merkle_block(contract_id, bundle.bundle_id());
+        let commitment = block.commitment_id();
+        let anchored = AnchoredBundle {
+            anchor: XAnchor::Bitcoin(AnchorSet::Opret(Anchor {
+                txid: Txid::from([3u8; 32]),
+                mpc_proof: block.into_merkle_proof(contract_id.into()).unwrap(),
+                dbc_proof: strict_dumb!(),
+                _method: default!(),
+            })),
+            bundle,
+        };
+
+        let proof = anchored.inclusion_proof(opid1, contract_id).unwrap();
+        assert_eq!(proof.transition, t1);
+        assert_eq!(proof.bundle_id(), anchored.bundle_id());
+        assert_eq!(proof.positions().collect::<Vec<_>>(), vec![Vin::from(0u32), Vin::from(2u32)]);
+        assert_eq!(proof.verify(contract_id), Ok(commitment));
+
+        let mut tampered = proof.clone();
+        tampered.input_map = confined_bmap! { 0u32.into() => opid1 };
+        assert_ne!(tampered.verify(contract_id), Ok(commitment));
+        let mut uncommitted = proof.clone();
+        uncommitted.input_map = confined_bmap! { 0u32.into() => opid2 };
+        assert_eq!(
+            uncommitted.verify(contract_id),
+            Err(AnchorVerifyError::UncommittedTransition(uncommitted.bundle_id(), opid1))
+        );
+        assert_eq!(uncommitted.to_anchored_bundle().bundle_id(), uncommitted.bundle_id());
+
+        let opid = OpId::from([9u8; 32]);
+        assert_eq!(
+            anchored.inclusion_proof(opid, contract_id),
+            Err(SplitError::UnknownTransition(opid))
+        );
+        let other_id = ContractId::from([2u8; 32]);
+        let err = anchored.verify(other_id).unwrap_err();
+        assert_eq!(anchored.inclusion_proof(opid1, other_id), Err(SplitError::Anchor(err.clone())));
+        assert!(matches!(err, AnchorVerifyError::ContractMismatch(_, id) if id == contract_id));
+        assert_eq!(
+            proof.verify(other_id),
+            Err(AnchorVerifyError::ContractMismatch(opid1, contract_id))
+        );
+    }
+
+    #[test]
+    fn close_method_consistency() {
+        let anchored = |anchor: AnchorSet| AnchoredBundle {
+            anchor: XAnchor::Bitcoin(anchor),
+            bundle: strict_dumb!(),
+        };
+        let tapret = Anchor {
+            txid: Txid::from([3u8; 32]),
+            mpc_proof: strict_dumb!(),
+            dbc_proof: strict_dumb!(),
+            _method: default!(),
+        };
+        let opret = Anchor {
+            txid: Txid::from([3u8; 32]),
+            mpc_proof: strict_dumb!(),
+            dbc_proof: strict_dumb!(),
+            _method: default!(),
+        };
+        let seal = |method: CloseMethod| {
+            XChain::Bitcoin(OutputSeal::new(method, bp::Outpoint::new(Txid::from([4u8; 32]), 0)))
+        };
+        let tapret_seal = seal(CloseMethod::TapretFirst);
+        let opret_seal = seal(CloseMethod::OpretFirst);
+        let seals = [tapret_seal, opret_seal];
+
+        let bundle = anchored(AnchorSet::Dual {
+            tapret: tapret.clone(),
+            opret: opret.clone(),
+        });
+        assert_eq!(verify_close_method_consistency(&bundle, &seals), Ok(()));
+
+        let bundle = anchored(AnchorSet::Tapret(tapret));
+        assert_eq!(verify_close_method_consistency(&bundle, &[tapret_seal]), Ok(()));
+        assert_eq!(
+            verify_close_method_consistency(&bundle, &seals),
+            Err(CloseMethodMismatch {
+                seal: opret_seal,
+                method: CloseMethod::OpretFirst
+            })
+        );
+
+        let bundle = anchored(AnchorSet::Opret(opret));
+        assert_eq!(verify_close_method_consistency(&bundle, &[opret_seal]), Ok(()));
+        assert_eq!(
+            verify_close_method_consistency(&bundle, &seals),
+            Err(CloseMethodMismatch {
+                seal: tapret_seal,
+                method: CloseMethod::TapretFirst
+            })
+        );
+    }
+
+    #[test]
+    fn keep_method() {
+        let tapret = Anchor {
+            txid: Txid::from([1u8; 32]),
+            mpc_proof: mpc::MerkleProof::strict_dumb(),
+            dbc_proof: TapretProof::strict_dumb(),
+            _method: default!(),
+        };
+        let opret = Anchor {
+            txid: Txid::from([2u8; 32]),
+            mpc_proof: mpc::MerkleProof::strict_dumb(),
+            dbc_proof: OpretProof::strict_dumb(),
+            _method: default!(),
+        };
+        let dual = AnchorSet::Dual {
+            tapret: tapret.clone(),
+            opret: opret.clone(),
+        };
+
+        assert_eq!(dual.clone().keep_tapret(), Some(AnchorSet::Tapret(tapret.clone())));
+        assert_eq!(dual.keep_opret(), Some(AnchorSet::Opret(opret.clone())));
+        assert_eq!(AnchorSet::Tapret(tapret.clone()).keep_opret(), None);
+        let single = AnchorSet::Tapret(tapret.clone());
+        assert_eq!(single.keep_tapret(), Some(AnchorSet::Tapret(tapret)));
+        assert_eq!(AnchorSet::Opret(opret.clone()).keep_tapret(), None);
+        assert_eq!(AnchorSet::Opret(opret.clone()).keep_opret(), Some(AnchorSet::Opret(opret)));
+    }
+
+    #[test]
+    fn mined_anchor() {
+        let witness_id = XChain::Bitcoin(Txid::from([1u8; 32]));
+        let anchor = WitnessAnchor::mined(witness_id, 800_000, 1_690_000_000).unwrap();
+        assert_eq!(anchor.witness_id, witness_id);
+        assert_eq!(
+            anchor.witness_ord,
+            WitnessOrd::OnChain(WitnessPos::new(800_000, 1_690_000_000).unwrap())
+        );
+        assert!(anchor > WitnessAnchor::mined(witness_id, 799_999, 1_689_999_000).unwrap());
+        assert!(anchor < WitnessAnchor::from_mempool(witness_id));
+        assert_eq!(WitnessAnchor::mined(witness_id, 0, 1_690_000_000), None);
+        assert_eq!(WitnessAnchor::mined(witness_id, 800_000, 0), None);
+    }
+}
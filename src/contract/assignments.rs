@@ -24,18 +24,18 @@ use core::cmp::Ordering;
 use core::fmt::Debug;
 use std::collections::BTreeSet;
 use std::hash::{Hash, Hasher};
-use std::{io, vec};
+use std::io;
 
 use amplify::confinement::{Confined, SmallVec, TinyOrdMap};
 use commit_verify::merkle::{MerkleLeaves, MerkleNode};
 use commit_verify::{CommitEncode, CommitStrategy, CommitmentId, Conceal};
-use strict_encoding::{StrictDumb, StrictEncode, StrictWriter};
+use strict_encoding::{StrictDecode, StrictDumb, StrictEncode, StrictReader, StrictWriter};
 
 use super::ExposedState;
 use crate::contract::seal::GenesisSeal;
 use crate::{
     AssignmentType, ExposedSeal, GraphSeal, RevealedAttach, RevealedData, RevealedValue,
-    SecretSeal, StateType, VoidState, XChain, LIB_NAME_RGB,
+    SecretSeal, StateData, StateType, VoidState, XChain, LIB_NAME_RGB,
 };
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Display, Error)]
@@ -43,6 +43,39 @@ use crate::{
 /// the requested data are not present.
 pub struct UnknownDataError;
 
+/// Two sets of assignments of the same type use different kinds of state.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Display, Error)]
+#[display("assignments with {expected} state were expected, while {found} state was found")]
+pub struct StateTypeMismatch {
+    pub expected: StateType,
+    pub found: StateType,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum MergeError {
+    /// assignments of type {0} can't be merged: {1}
+    StateMismatch(AssignmentType, StateTypeMismatch),
+
+    /// the number of assignments of type {0} exceeds the maximum allowed.
+    TooManyAssignments(AssignmentType),
+
+    /// the number of assignment types exceeds the maximum allowed.
+    TooManyTypes,
+}
+
+/// Assignments which don't keep their positions, as detected by
+/// [`TypedAssigns::check_order`].
+#[derive(Clone, PartialEq, Eq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum OrderError {
+    /// assignments at positions {0:?} are moved to other positions.
+    Reordered(Vec<u16>),
+
+    /// compared assignments are not the same set of assignments.
+    Mismatch,
+}
+
 pub type AssignRights<Seal> = Assign<VoidState, Seal>;
 pub type AssignFungible<Seal> = Assign<RevealedValue, Seal>;
 pub type AssignData<Seal> = Assign<RevealedData, Seal>;
@@ -207,6 +240,25 @@ impl<State: ExposedState, Seal: ExposedSeal> Assign<State, Seal> {
     }
 }
 
+impl<State: ExposedState, Seal: ExposedSeal> Assign<State, Seal>
+where Self: Clone
+{
+    /// Conceals the seal of the assignment, keeping its state as is.
+    pub fn conceal_seal(&self) -> Self {
+        match self {
+            Assign::Confidential { .. } | Assign::ConfidentialSeal { .. } => self.clone(),
+            Assign::ConfidentialState { seal, state } => Self::Confidential {
+                seal: seal.conceal(),
+                state: *state,
+            },
+            Assign::Revealed { seal, state } => Self::ConfidentialSeal {
+                seal: seal.conceal(),
+                state: state.clone(),
+            },
+        }
+    }
+}
+
 impl<State: ExposedState, Seal: ExposedSeal> Conceal for Assign<State, Seal>
 where Self: Clone
 {
@@ -341,6 +393,24 @@ impl<Seal: ExposedSeal> Conceal for TypedAssigns<Seal> {
 }
 
 impl<Seal: ExposedSeal> TypedAssigns<Seal> {
+    /// Conceals seals of all assignments, keeping their state as is.
+    pub fn conceal_seals(&self) -> Self {
+        match self {
+            TypedAssigns::Declarative(s) => TypedAssigns::Declarative(
+                SmallVec::try_from_iter(s.iter().map(Assign::conceal_seal)).expect("same size"),
+            ),
+            TypedAssigns::Fungible(s) => TypedAssigns::Fungible(
+                SmallVec::try_from_iter(s.iter().map(Assign::conceal_seal)).expect("same size"),
+            ),
+            TypedAssigns::Structured(s) => TypedAssigns::Structured(
+                SmallVec::try_from_iter(s.iter().map(Assign::conceal_seal)).expect("same size"),
+            ),
+            TypedAssigns::Attachment(s) => TypedAssigns::Attachment(
+                SmallVec::try_from_iter(s.iter().map(Assign::conceal_seal)).expect("same size"),
+            ),
+        }
+    }
+
     pub fn is_empty(&self) -> bool {
         match self {
             TypedAssigns::Declarative(set) => set.is_empty(),
@@ -359,6 +429,73 @@ impl<Seal: ExposedSeal> TypedAssigns<Seal> {
         }
     }
 
+    /// Checks that a strict encoding round-trip keeps every assignment at its
+    /// original index. [`crate::Opout`]s reference assignments by their
+    /// position, so any reordering would make them point to different
+    /// assignments.
+    pub fn check_stable_order(&self) -> Result<(), OrderError> {
+        let writer = StrictWriter::in_memory(u32::MAX as usize);
+        let data = self.strict_encode(writer).expect("in-memory encoding").unbox();
+        let mut reader = StrictReader::in_memory(data, u32::MAX as usize);
+        let decoded = Self::strict_decode(&mut reader)
+            .expect("strict encoding of valid assignments can be always decoded");
+        self.check_order(&decoded)
+    }
+
+    /// Debug assertion that a strict encoding round-trip keeps every
+    /// assignment at its original index, see [`Self::check_stable_order`].
+    #[inline]
+    pub fn assert_stable_order(&self) {
+        debug_assert_eq!(self.check_stable_order(), Ok(()), "assignments are reordered");
+    }
+
+    /// Checks that `other` keeps every assignment at the same index as `self`.
+    ///
+    /// Fails with [`OrderError::Reordered`] listing the positions holding
+    /// different assignments if `other` is the same set of assignments put in
+    /// a different order, and with [`OrderError::Mismatch`] if it is not the
+    /// same set.
+    pub fn check_order(&self, other: &Self) -> Result<(), OrderError> {
+        // We compare encodings and not the assignments themselves, since the
+        // equality of assignments is defined over their concealed form and
+        // doesn't distinguish an assignment from its concealed copy.
+        fn encode<T: StrictEncode>(items: &[T]) -> Vec<Vec<u8>> {
+            items
+                .iter()
+                .map(|item| {
+                    let writer = StrictWriter::in_memory(u32::MAX as usize);
+                    item.strict_encode(writer).expect("in-memory encoding").unbox()
+                })
+                .collect()
+        }
+        let encodings = |assigns: &Self| match assigns {
+            TypedAssigns::Declarative(set) => encode(set),
+            TypedAssigns::Fungible(set) => encode(set),
+            TypedAssigns::Structured(set) => encode(set),
+            TypedAssigns::Attachment(set) => encode(set),
+        };
+        let (ours, theirs) = (encodings(self), encodings(other));
+        let sorted = |encodings: &[Vec<u8>]| {
+            let mut encodings = encodings.to_vec();
+            encodings.sort();
+            encodings
+        };
+        if self.state_type() != other.state_type() || sorted(&ours) != sorted(&theirs) {
+            return Err(OrderError::Mismatch);
+        }
+        let moved = ours
+            .iter()
+            .zip(&theirs)
+            .enumerate()
+            .filter(|(_, (ours, theirs))| ours != theirs)
+            .map(|(index, _)| index as u16)
+            .collect::<Vec<_>>();
+        if !moved.is_empty() {
+            return Err(OrderError::Reordered(moved));
+        }
+        Ok(())
+    }
+
     #[inline]
     pub fn state_type(&self) -> StateType {
         match self {
@@ -369,6 +506,41 @@ impl<Seal: ExposedSeal> TypedAssigns<Seal> {
         }
     }
 
+    /// Checks that the `other` set of assignments has the same state type,
+    /// returning [`StateTypeMismatch`] otherwise. Must be called before
+    /// combining or comparing assignments coming from untrusted sources.
+    pub fn check_state_type(&self, other: &Self) -> Result<(), StateTypeMismatch> {
+        let expected = self.state_type();
+        let found = other.state_type();
+        if expected != found {
+            return Err(StateTypeMismatch { expected, found });
+        }
+        Ok(())
+    }
+
+    /// Appends assignments from `other` to this set of assignments.
+    ///
+    /// # Errors
+    ///
+    /// If `other` has a different state type, or if the resulting number of
+    /// assignments exceeds `u16::MAX`. In case of error `self` is not
+    /// modified.
+    pub fn extend(&mut self, ty: AssignmentType, other: Self) -> Result<(), MergeError> {
+        self.check_state_type(&other)
+            .map_err(|err| MergeError::StateMismatch(ty, err))?;
+        if self.len_u16() as usize + other.len_u16() as usize > u16::MAX as usize {
+            return Err(MergeError::TooManyAssignments(ty));
+        }
+        let res = match (self, other) {
+            (TypedAssigns::Declarative(a), TypedAssigns::Declarative(b)) => a.extend(b),
+            (TypedAssigns::Fungible(a), TypedAssigns::Fungible(b)) => a.extend(b),
+            (TypedAssigns::Structured(a), TypedAssigns::Structured(b)) => a.extend(b),
+            (TypedAssigns::Attachment(a), TypedAssigns::Attachment(b)) => a.extend(b),
+            _ => unreachable!("state type is checked above"),
+        };
+        res.map_err(|_| MergeError::TooManyAssignments(ty))
+    }
+
     #[inline]
     pub fn is_declarative(&self) -> bool { matches!(self, TypedAssigns::Declarative(_)) }
 
@@ -551,6 +723,26 @@ impl<Seal: ExposedSeal> TypedAssigns<Seal> {
             _ => Err(UnknownDataError),
         }
     }
+
+    /// Converts assignments into a list of revealed seals and their state,
+    /// skipping assignments which have either seal or state concealed.
+    pub fn into_revealed_pairs(self) -> Vec<(XChain<Seal>, StateData)> {
+        fn pairs<State: ExposedState, Seal: ExposedSeal>(
+            vec: SmallVec<Assign<State, Seal>>,
+            f: impl Fn(State) -> StateData,
+        ) -> Vec<(XChain<Seal>, StateData)> {
+            vec.into_iter()
+                .filter_map(Assign::into_revealed)
+                .map(|(seal, state)| (seal, f(state)))
+                .collect()
+        }
+        match self {
+            TypedAssigns::Declarative(vec) => pairs(vec, |_| StateData::Void),
+            TypedAssigns::Fungible(vec) => pairs(vec, StateData::Fungible),
+            TypedAssigns::Structured(vec) => pairs(vec, StateData::Structured),
+            TypedAssigns::Attachment(vec) => pairs(vec, StateData::Attachment),
+        }
+    }
 }
 
 impl<Seal: ExposedSeal> CommitStrategy for TypedAssigns<Seal> {
@@ -560,31 +752,51 @@ impl<Seal: ExposedSeal> CommitStrategy for TypedAssigns<Seal> {
 
 impl<Seal: ExposedSeal> MerkleLeaves for TypedAssigns<Seal> {
     type Leaf = MerkleNode;
-    type LeafIter<'tmp> = vec::IntoIter<MerkleNode> where Self: 'tmp;
+    type LeafIter<'tmp> = AssignmentLeaves<'tmp, Seal> where Self: 'tmp;
 
     fn merkle_leaves(&self) -> Self::LeafIter<'_> {
-        match self {
-            TypedAssigns::Declarative(vec) => vec
-                .iter()
-                .map(AssignRights::commitment_id)
-                .collect::<Vec<_>>(),
-            TypedAssigns::Fungible(vec) => vec
-                .iter()
-                .map(AssignFungible::commitment_id)
-                .collect::<Vec<_>>(),
-            TypedAssigns::Structured(vec) => vec
-                .iter()
-                .map(AssignData::commitment_id)
-                .collect::<Vec<_>>(),
-            TypedAssigns::Attachment(vec) => vec
-                .iter()
-                .map(AssignAttach::commitment_id)
-                .collect::<Vec<_>>(),
+        AssignmentLeaves {
+            assigns: self,
+            pos: 0,
         }
-        .into_iter()
     }
 }
 
+/// Iterator over merkle leaves of the assignments of a specific type, which
+/// are the commitment ids of the assignments, produced by
+/// [`TypedAssigns::merkle_leaves`].
+///
+/// The commitment ids are computed lazily, when the leaf is requested, so
+/// merklization of the assignments doesn't allocate the leaves up front.
+#[derive(Clone, Debug)]
+pub struct AssignmentLeaves<'a, Seal: ExposedSeal> {
+    assigns: &'a TypedAssigns<Seal>,
+    pos: u16,
+}
+
+impl<'a, Seal: ExposedSeal> Iterator for AssignmentLeaves<'a, Seal> {
+    type Item = MerkleNode;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let pos = self.pos as usize;
+        let leaf = match self.assigns {
+            TypedAssigns::Declarative(vec) => vec.get(pos).map(AssignRights::commitment_id),
+            TypedAssigns::Fungible(vec) => vec.get(pos).map(AssignFungible::commitment_id),
+            TypedAssigns::Structured(vec) => vec.get(pos).map(AssignData::commitment_id),
+            TypedAssigns::Attachment(vec) => vec.get(pos).map(AssignAttach::commitment_id),
+        }?;
+        self.pos += 1;
+        Some(leaf)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = (self.assigns.len_u16() - self.pos) as usize;
+        (len, Some(len))
+    }
+}
+
+impl<'a, Seal: ExposedSeal> ExactSizeIterator for AssignmentLeaves<'a, Seal> {}
+
 impl TypedAssigns<GenesisSeal> {
     pub fn transmutate_seals(&self) -> TypedAssigns<GraphSeal> {
         match self {
@@ -641,6 +853,40 @@ impl<Seal: ExposedSeal> CommitEncode for Assignments<Seal> {
     }
 }
 
+impl<Seal: ExposedSeal> Assignments<Seal> {
+    /// Conceals seals of all assignments, keeping their state revealed. This
+    /// allows to disclose the assigned state without disclosing the seals it
+    /// is assigned to.
+    pub fn conceal_seals(&self) -> Self {
+        Assignments(
+            Confined::try_from_iter(self.iter().map(|(t, a)| (*t, a.conceal_seals())))
+                .expect("same size"),
+        )
+    }
+
+    /// Merges assignments from `other` into this set of assignments; for the
+    /// types present in both sets the assignments from `other` are appended.
+    ///
+    /// # Errors
+    ///
+    /// If a type present in both sets uses different kinds of state (which
+    /// indicates corrupted data), or if the merged set exceeds the
+    /// confinement limits. In case of error `self` is not modified.
+    pub fn merge(&mut self, other: Assignments<Seal>) -> Result<(), MergeError> {
+        let mut merged = self.0.clone().into_inner();
+        for (ty, assigns) in other.0 {
+            match merged.get_mut(&ty) {
+                Some(existing) => existing.extend(ty, assigns)?,
+                None => {
+                    merged.insert(ty, assigns);
+                }
+            }
+        }
+        self.0 = Confined::try_from(merged).map_err(|_| MergeError::TooManyTypes)?;
+        Ok(())
+    }
+}
+
 impl Assignments<GenesisSeal> {
     pub fn transmutate_seals(&self) -> Assignments<GraphSeal> {
         Assignments(
@@ -690,3 +936,217 @@ impl AssignmentsRef<'_> {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use amplify::num::u5;
+    use amplify::ByteArray;
+    use bp::dbc::Method;
+
+    use super::*;
+    use crate::{AssetTag, BlindingFactor, DataState};
+
+    #[test]
+    fn empty_commitment() {
+        let mut buf = vec![];
+        Assignments::<GraphSeal>::default().commit_encode(&mut buf);
+        assert_eq!(buf, vec![0x00]);
+
+        let empty = TypedAssigns::<GraphSeal>::Declarative(empty!());
+        let mut buf = vec![];
+        empty.commit_encode(&mut buf);
+        let void = MerkleNode::void(*b"rgb:state:owned*", u5::ZERO, 0u32);
+        assert_eq!(buf, void.to_byte_array());
+        assert_eq!(buf, MerkleNode::merklize(*b"rgb:state:owned*", &empty).to_byte_array());
+    }
+
+    #[test]
+    fn lazy_merkle_leaves() {
+        let seal = |vout: u32| {
+            XChain::Bitcoin(GraphSeal::with_blinded_vout(Method::OpretFirst, vout, vout as u64))
+        };
+        let list = (0..5)
+            .map(|vout| Assign::revealed(seal(vout), VoidState::default()))
+            .collect::<Vec<_>>();
+        let leaves = list.iter().map(AssignRights::commitment_id).collect::<Vec<_>>();
+        let assigns = TypedAssigns::Declarative(Confined::from_collection_unsafe(list));
+
+        let mut iter = assigns.merkle_leaves();
+        assert_eq!(iter.len(), 5);
+        iter.next();
+        assert_eq!(iter.len(), 4);
+        assert_eq!(assigns.merkle_leaves().collect::<Vec<_>>(), leaves);
+        let leaves = SmallVec::from_collection_unsafe(leaves);
+        assert_eq!(
+            MerkleNode::merklize(*b"rgb:state:owned*", &assigns),
+            MerkleNode::merklize(*b"rgb:state:owned*", &leaves)
+        );
+    }
+
+    #[test]
+    fn revealed_pairs() {
+        let seal = |vout: u32| {
+            XChain::Bitcoin(GraphSeal::with_blinded_vout(Method::OpretFirst, vout, vout as u64))
+        };
+        let value = |v: u64| {
+            RevealedValue::with_blinding(v, BlindingFactor::EMPTY, AssetTag::from([1u8; 32]))
+        };
+        let assigns = TypedAssigns::Fungible(Confined::from_collection_unsafe(vec![
+            Assign::revealed(seal(0), value(5)),
+            Assign::Confidential {
+                seal: seal(1).conceal(),
+                state: value(6).conceal(),
+            },
+            Assign::ConfidentialSeal {
+                seal: seal(2).conceal(),
+                state: value(7),
+            },
+            Assign::ConfidentialState {
+                seal: seal(3),
+                state: value(8).conceal(),
+            },
+            Assign::revealed(seal(4), value(9)),
+        ]));
+        assert_eq!(assigns.into_revealed_pairs(), vec![
+            (seal(0), StateData::Fungible(value(5))),
+            (seal(4), StateData::Fungible(value(9))),
+        ]);
+
+        let assigns = TypedAssigns::Declarative(Confined::from_collection_unsafe(vec![
+            Assign::revealed(seal(0), VoidState::default()),
+        ]));
+        assert_eq!(assigns.into_revealed_pairs(), vec![(seal(0), StateData::Void)]);
+    }
+
+    #[test]
+    fn merge_state_mismatch() {
+        let seal = |vout: u32| {
+            XChain::Bitcoin(GraphSeal::with_blinded_vout(Method::OpretFirst, vout, vout as u64))
+        };
+        let rights = |vout: u32| {
+            TypedAssigns::Declarative(Confined::from_collection_unsafe(vec![Assign::revealed(
+                seal(vout),
+                VoidState::default(),
+            )]))
+        };
+        let data = TypedAssigns::Structured(Confined::from_collection_unsafe(vec![
+            Assign::revealed(seal(2), RevealedData::with_salt(DataState::from(small_vec!(1)), 0)),
+        ]));
+        let ty = AssignmentType::with(1);
+
+        let mut assigns = Assignments::<GraphSeal>::from(tiny_bmap! { ty => rights(0) });
+        let other = Assignments::from(tiny_bmap! { ty => data.clone() });
+        let mismatch = StateTypeMismatch {
+            expected: StateType::Void,
+            found: StateType::Structured,
+        };
+        assert_eq!(rights(0).check_state_type(&data), Err(mismatch));
+        assert_eq!(assigns.merge(other), Err(MergeError::StateMismatch(ty, mismatch)));
+        assert_eq!(assigns.len(), 1);
+        assert_eq!(assigns.get(&ty).unwrap().len_u16(), 1);
+
+        let other = Assignments::from(tiny_bmap! {
+            ty => rights(1),
+            AssignmentType::with(2) => data,
+        });
+        assigns.merge(other).unwrap();
+        assert_eq!(assigns.len(), 2);
+        assert_eq!(assigns.get(&ty).unwrap().as_declarative().len(), 2);
+    }
+
+    #[test]
+    fn stable_order() {
+        let tag = AssetTag::from_byte_array([1u8; 32]);
+        let assigns = TypedAssigns::Fungible(Confined::from_collection_unsafe(
+            (0..20u32)
+                .map(|vout| {
+                    let seal = GraphSeal::with_blinded_vout(Method::OpretFirst, vout, 0);
+                    let state = RevealedValue::with_blinding(
+                        100 - vout as u64,
+                        BlindingFactor::random(),
+                        tag,
+                    );
+                    let seal = XChain::Bitcoin(seal);
+                    match vout % 2 {
+                        0 => Assign::revealed(seal, state),
+                        _ => Assign::ConfidentialSeal {
+                            seal: seal.conceal(),
+                            state,
+                        },
+                    }
+                })
+                .collect::<Vec<_>>(),
+        ));
+        assert_eq!(assigns.check_stable_order(), Ok(()));
+        assigns.assert_stable_order();
+
+        let mut data = vec![];
+        assigns.strict_encode(StrictWriter::with(u32::MAX as usize, &mut data)).unwrap();
+        let mut reader = StrictReader::in_memory(data, u32::MAX as usize);
+        let decoded = TypedAssigns::<GraphSeal>::strict_decode(&mut reader).unwrap();
+        assert_eq!(decoded.to_confidential_seals(), assigns.to_confidential_seals());
+        for index in 0..assigns.len_u16() {
+            assert_eq!(decoded.revealed_seal_at(index), assigns.revealed_seal_at(index));
+            assert_eq!(
+                decoded.as_fungible()[index as usize].as_revealed_state(),
+                assigns.as_fungible()[index as usize].as_revealed_state()
+            );
+        }
+    }
+
+    #[test]
+    fn check_order() {
+        let assigns = |vouts: &[u32]| {
+            let assigns = vouts
+                .iter()
+                .map(|vout| {
+                    let seal = GraphSeal::with_blinded_vout(Method::OpretFirst, *vout, 0);
+                    Assign::revealed(XChain::Bitcoin(seal), VoidState::default())
+                })
+                .collect::<Vec<_>>();
+            TypedAssigns::Declarative(Confined::from_collection_unsafe(assigns))
+        };
+        let original = assigns(&[0, 1, 2, 3]);
+        assert_eq!(original.check_order(&assigns(&[0, 1, 2, 3])), Ok(()));
+        assert_eq!(
+            original.check_order(&assigns(&[0, 2, 1, 3])),
+            Err(OrderError::Reordered(vec![1, 2]))
+        );
+        assert_eq!(
+            original.check_order(&assigns(&[3, 1, 2, 0])),
+            Err(OrderError::Reordered(vec![0, 3]))
+        );
+        assert_eq!(original.check_order(&assigns(&[0, 1, 2, 4])), Err(OrderError::Mismatch));
+        assert_eq!(original.check_order(&assigns(&[0, 1, 2])), Err(OrderError::Mismatch));
+        assert_eq!(original.check_order(&original.conceal_seals()), Err(OrderError::Mismatch));
+    }
+
+    #[test]
+    fn conceal_seals() {
+        let seal = XChain::Bitcoin(GraphSeal::with_blinded_vout(Method::OpretFirst, 0, 0));
+        let data = RevealedData::with_salt(DataState::try_from_slice(&[1]).unwrap(), 0);
+        let assignments = Assignments::from(Confined::from_collection_unsafe(bmap! {
+            AssignmentType::with(1) => TypedAssigns::Declarative(Confined::from_collection_unsafe(
+                vec![Assign::revealed(seal, VoidState::default())]
+            )),
+            AssignmentType::with(2) => TypedAssigns::Structured(Confined::from_collection_unsafe(
+                vec![Assign::revealed(seal, data.clone()), Assign::Confidential {
+                    seal: seal.conceal(),
+                    state: data.conceal(),
+                }]
+            )),
+        }));
+
+        let concealed = assignments.conceal_seals();
+        assert_eq!(concealed, assignments);
+        assert_eq!(concealed.conceal_seals(), concealed);
+        for (ty, assigns) in concealed.iter() {
+            assert_eq!(assigns.len_u16(), assignments[ty].len_u16());
+            assert_eq!(assigns.revealed_seal_at(0), Ok(None));
+        }
+        let data_assigns = concealed[&AssignmentType::with(2)].as_structured();
+        assert!(matches!(data_assigns[0], Assign::ConfidentialSeal { .. }));
+        assert_eq!(data_assigns[0].as_revealed_state(), Some(&data));
+        assert_eq!(data_assigns[1].as_revealed_state(), None);
+    }
+}
@@ -31,7 +31,10 @@ use strict_encoding::StrictEncode;
 use super::{ConfidentialState, ExposedState};
 use crate::{MediaType, StateCommitment, StateData, StateType, LIB_NAME_RGB};
 
-/// Unique data attachment identifier
+/// Unique data attachment identifier.
+///
+/// The identifier is a digest of the attachment data computed outside of RGB
+/// consensus; unlike other identifiers it is not a tagged commitment.
 #[derive(Wrapper, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Display, From)]
 #[wrapper(Deref, BorrowSlice, Hex, Index, RangeOps)]
 #[display(Self::to_baid58_string)]
@@ -107,6 +110,14 @@ impl RevealedAttach {
             salt,
         }
     }
+
+    /// Checks whether the attachment media type conforms to any of the media
+    /// types from the `allowlist`.
+    pub fn is_allowed(&self, allowlist: &[MediaType]) -> bool {
+        allowlist
+            .iter()
+            .any(|media_type| self.media_type.conforms(media_type))
+    }
 }
 
 impl ExposedState for RevealedAttach {
@@ -123,6 +134,10 @@ impl Conceal for RevealedAttach {
 
 /// Confidential version of an attachment information.
 ///
+/// The revealed attachment is committed to with the strict encoding tag of
+/// [`StrictEncodedProtocol`]; the state type is separated by the
+/// [`StateCommitment`] variant.
+///
 /// See also revealed version [`RevealedAttach`].
 #[derive(Wrapper, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, From)]
 #[wrapper(Deref, BorrowSlice, Hex, Index, RangeOps)]
@@ -178,4 +193,15 @@ mod test {
             AttachId::from_str("stashfs:8JEvTX-J6sD5U4n-1p7GEERY-MPN9ijjs-9ZM4ysJ3-qhgyqM")
         );
     }
+
+    #[test]
+    fn media_type_allowlist() {
+        let id = AttachId::from_byte_array([0x6c; 32]);
+        let attach = RevealedAttach::with_salt(id, MediaType::ImagePng, 0);
+        assert!(attach.is_allowed(&[MediaType::Image]));
+        assert!(attach.is_allowed(&[MediaType::TextPlain, MediaType::ImagePng]));
+        assert!(attach.is_allowed(&[MediaType::Any]));
+        assert!(!attach.is_allowed(&[MediaType::Text, MediaType::ImageJpeg]));
+        assert!(!attach.is_allowed(&[]));
+    }
 }
@@ -0,0 +1,299 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2023 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2023 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2023 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Construction of balanced state transitions for fungible state.
+
+use std::collections::BTreeMap;
+
+use amplify::confinement::Confined;
+
+use crate::{
+    Assign, AssetTag, AssetTagMismatch, AssignmentType, Assignments, BlindingFactor, ContractId,
+    Ffv, GraphSeal, Inputs, InvalidFieldElement, Opout, RevealedValue, Transition, TransitionType,
+    TypedAssigns, XChain,
+};
+
+/// Errors constructing state transition with [`TransitionBuilder`].
+#[derive(Clone, PartialEq, Eq, Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum TransitionBuildError {
+    /// transition outputs require {outputs} units of the state, while the
+    /// inputs provide only {inputs}.
+    InsufficientInputs { inputs: u64, outputs: u64 },
+
+    /// transition has a change of {0} units, but no seal was provided for the
+    /// change output.
+    NoChangeSeal(u64),
+
+    /// transition must define at least a single output.
+    NoOutputs,
+
+    /// output {0} is already used as a transition input.
+    RepeatedInput(Opout),
+
+    /// transition can't spend more than 255 inputs.
+    TooManyInputs,
+
+    /// transition can't define more than 65535 outputs.
+    TooManyOutputs,
+
+    /// sum of the transition inputs or outputs exceeds 64-bit value.
+    ValueOverflow,
+
+    #[display(inner)]
+    #[from]
+    AssetTag(AssetTagMismatch),
+
+    /// the balancing blinding factor can't be computed since the other
+    /// blinding factors already balance.
+    #[from(InvalidFieldElement)]
+    Blinding,
+}
+
+/// Builder constructing a state transition for a single fungible assignment
+/// type, which balances its outputs against the inputs.
+///
+/// The builder adds change output for the difference between the inputs and
+/// outputs, and picks blinding factor of the last output such that the
+/// Pedersen commitments of the inputs and outputs sum up to equal values.
+#[derive(Clone, Debug)]
+pub struct TransitionBuilder {
+    contract_id: ContractId,
+    transition_type: TransitionType,
+    assignment_type: AssignmentType,
+    asset_tag: AssetTag,
+    inputs: BTreeMap<Opout, RevealedValue>,
+    outputs: Vec<(XChain<GraphSeal>, u64)>,
+    change_seal: Option<XChain<GraphSeal>>,
+}
+
+impl TransitionBuilder {
+    pub fn new(
+        contract_id: ContractId,
+        transition_type: TransitionType,
+        assignment_type: AssignmentType,
+        asset_tag: AssetTag,
+    ) -> Self {
+        TransitionBuilder {
+            contract_id,
+            transition_type,
+            assignment_type,
+            asset_tag,
+            inputs: empty!(),
+            outputs: empty!(),
+            change_seal: None,
+        }
+    }
+
+    /// Adds an input spending `prev_out`, which holds the revealed `value`.
+    ///
+    /// # Errors
+    ///
+    /// If the value is bound to a different asset tag, the output is already
+    /// spent by the transition, or the transition would exceed 255 inputs.
+    pub fn add_input(
+        mut self,
+        prev_out: Opout,
+        value: RevealedValue,
+    ) -> Result<Self, TransitionBuildError> {
+        value.check_asset_tag(self.asset_tag)?;
+        if self.inputs.contains_key(&prev_out) {
+            return Err(TransitionBuildError::RepeatedInput(prev_out));
+        }
+        if self.inputs.len() >= u8::MAX as usize {
+            return Err(TransitionBuildError::TooManyInputs);
+        }
+        self.inputs.insert(prev_out, value);
+        Ok(self)
+    }
+
+    /// Adds an output assigning `amount` to the `seal`.
+    pub fn add_output(mut self, seal: XChain<GraphSeal>, amount: u64) -> Self {
+        self.outputs.push((seal, amount));
+        self
+    }
+
+    /// Sets seal which receives the change, if the inputs exceed the outputs.
+    pub fn change_seal(mut self, seal: XChain<GraphSeal>) -> Self {
+        self.change_seal = Some(seal);
+        self
+    }
+
+    /// Constructs the transition, adding change output if required.
+    ///
+    /// # Errors
+    ///
+    /// If the outputs exceed the inputs, the change is required but no change
+    /// seal is set, or the transition has no outputs.
+    pub fn complete(self) -> Result<Transition, TransitionBuildError> {
+        let inputs = self
+            .inputs
+            .values()
+            .try_fold(0u64, |sum, value| sum.checked_add(value.value.as_u64()))
+            .ok_or(TransitionBuildError::ValueOverflow)?;
+        let outputs = self
+            .outputs
+            .iter()
+            .try_fold(0u64, |sum, (_, amount)| sum.checked_add(*amount))
+            .ok_or(TransitionBuildError::ValueOverflow)?;
+        if outputs > inputs {
+            return Err(TransitionBuildError::InsufficientInputs { inputs, outputs });
+        }
+
+        let mut amounts = self.outputs;
+        let change = inputs - outputs;
+        if change > 0 {
+            let seal = self
+                .change_seal
+                .ok_or(TransitionBuildError::NoChangeSeal(change))?;
+            amounts.push((seal, change));
+        }
+        let Some((last_seal, last_amount)) = amounts.pop() else {
+            return Err(TransitionBuildError::NoOutputs);
+        };
+
+        let mut blindings = Vec::with_capacity(amounts.len());
+        let mut assigns = Vec::with_capacity(amounts.len() + 1);
+        for (seal, amount) in amounts {
+            let value = RevealedValue::new_random_blinding(amount, self.asset_tag);
+            blindings.push(value.blinding);
+            assigns.push(Assign::revealed(seal, value));
+        }
+        let blinding = BlindingFactor::zero_balanced(
+            self.inputs.values().map(|value| value.blinding),
+            blindings,
+        )?;
+        let value = RevealedValue::with_blinding(last_amount, blinding, self.asset_tag);
+        assigns.push(Assign::revealed(last_seal, value));
+
+        let assigns =
+            Confined::try_from(assigns).map_err(|_| TransitionBuildError::TooManyOutputs)?;
+        let inputs =
+            Inputs::try_from_iter(self.inputs.into_keys()).expect("number of inputs is checked");
+        Ok(Transition {
+            ffv: Ffv::default(),
+            contract_id: self.contract_id,
+            transition_type: self.transition_type,
+            metadata: empty!(),
+            globals: none!(),
+            inputs,
+            assignments: Assignments::from(Confined::from_collection_unsafe(bmap! {
+                self.assignment_type => TypedAssigns::Fungible(assigns),
+            })),
+            valencies: none!(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use amplify::{ByteArray, Wrapper};
+    use bp::dbc::Method;
+    use commit_verify::Conceal;
+
+    use super::*;
+    use crate::{AssignmentType, Operation};
+
+    fn builder() -> TransitionBuilder {
+        TransitionBuilder::new(
+            ContractId::from([1u8; 32]),
+            TransitionType::with(1),
+            AssignmentType::with(1),
+            AssetTag::from_byte_array([2u8; 32]),
+        )
+    }
+
+    fn seal(vout: u32) -> XChain<GraphSeal> {
+        XChain::Bitcoin(GraphSeal::with_blinded_vout(Method::OpretFirst, vout, 0))
+    }
+
+    fn input(no: u16, amount: u64) -> (Opout, RevealedValue) {
+        let opout = Opout::new(strict_dumb!(), AssignmentType::with(1), no);
+        (opout, RevealedValue::new_random_blinding(amount, AssetTag::from_byte_array([2u8; 32])))
+    }
+
+    #[test]
+    fn balanced_transition() {
+        let inputs = [input(0, 10), input(1, 15)];
+        let mut builder = builder();
+        for (opout, value) in inputs {
+            builder = builder.add_input(opout, value).unwrap();
+        }
+        let transition = builder
+            .add_output(seal(0), 12)
+            .add_output(seal(1), 3)
+            .change_seal(seal(2))
+            .complete()
+            .unwrap();
+
+        assert_eq!(transition.inputs().len(), 2);
+        let outputs = transition
+            .assignments_by_type(AssignmentType::with(1))
+            .unwrap();
+        let outputs = outputs.as_fungible();
+        let amounts = outputs
+            .iter()
+            .map(|a| a.as_revealed_state().unwrap().value.as_u64())
+            .collect::<Vec<_>>();
+        assert_eq!(amounts, vec![12, 3, 10]);
+
+        let commit = |value: &RevealedValue| value.conceal().commitment.into_inner();
+        let inputs = inputs.iter().map(|(_, v)| commit(v)).collect::<Vec<_>>();
+        let outputs = outputs
+            .iter()
+            .map(|a| commit(a.as_revealed_state().unwrap()))
+            .collect::<Vec<_>>();
+        assert!(secp256k1_zkp::verify_commitments_sum_to_equal(
+            secp256k1_zkp::SECP256K1,
+            &inputs,
+            &outputs
+        ));
+    }
+
+    #[test]
+    fn build_errors() {
+        let (opout, value) = input(0, 10);
+        let builder = builder().add_input(opout, value).unwrap();
+        assert_eq!(
+            builder.clone().add_input(opout, value).unwrap_err(),
+            TransitionBuildError::RepeatedInput(opout)
+        );
+        let foreign = RevealedValue::new_random_blinding(1, AssetTag::from_byte_array([3u8; 32]));
+        assert!(matches!(
+            builder.clone().add_input(input(1, 0).0, foreign),
+            Err(TransitionBuildError::AssetTag(_))
+        ));
+        assert_eq!(
+            builder.clone().add_output(seal(0), 11).complete().unwrap_err(),
+            TransitionBuildError::InsufficientInputs {
+                inputs: 10,
+                outputs: 11
+            }
+        );
+        assert_eq!(
+            builder.clone().add_output(seal(0), 4).complete().unwrap_err(),
+            TransitionBuildError::NoChangeSeal(6)
+        );
+        assert!(builder.add_output(seal(0), 10).complete().is_ok());
+        assert_eq!(self::builder().complete(), Err(TransitionBuildError::NoOutputs));
+    }
+}
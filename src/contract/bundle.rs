@@ -20,22 +20,51 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::io::Write;
+use std::num::TryFromIntError;
 
 use amplify::confinement::{Confined, U16};
 use amplify::{Bytes32, Wrapper};
-use bp::Vout;
 use commit_verify::{mpc, CommitEncode, CommitmentId};
 use strict_encoding::{StrictDumb, StrictEncode, StrictWriter};
 
 use super::OpId;
-use crate::{Transition, LIB_NAME_RGB};
+use crate::{Operation, Transition, LIB_NAME_RGB};
 
-pub type Vin = Vout;
+/// Index of the witness transaction input spending the seal closed by a state
+/// transition.
+#[derive(Wrapper, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Default, From)]
+#[wrapper(Display, FromStr)]
+#[derive(StrictType, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", transparent)
+)]
+pub struct Vin(u32);
+
+impl Vin {
+    /// Constructs input number from the index of the witness transaction input.
+    pub const fn from_index(index: u32) -> Self { Vin(index) }
+
+    pub const fn to_u32(&self) -> u32 { self.0 }
+
+    pub const fn to_usize(&self) -> usize { self.0 as usize }
+}
+
+impl TryFrom<usize> for Vin {
+    type Error = TryFromIntError;
+
+    /// Converts index of the witness transaction input, failing if it doesn't
+    /// fit into `u32`, which is the type of input numbers in bitcoin
+    /// transactions.
+    fn try_from(index: usize) -> Result<Self, Self::Error> { u32::try_from(index).map(Vin) }
+}
 
 /// Unique state transition bundle identifier equivalent to the bundle
-/// commitment hash
+/// commitment hash, tagged with [`TransitionBundle::TAG`].
 #[derive(Wrapper, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, From)]
 #[wrapper(Deref, BorrowSlice, Display, Hex, Index, RangeOps)]
 #[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
@@ -93,6 +122,245 @@ impl StrictDumb for TransitionBundle {
     }
 }
 
+/// Transition bundle exceeds one of the size limits defined by
+/// [`TransitionBundle::validate_size`].
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum BundleTooLarge {
+    /// bundle contains {found} known transitions, while its input map
+    /// references only {max} transitions.
+    Transitions { found: usize, max: usize },
+
+    /// bundle transitions contain {found} inputs in total, exceeding the limit
+    /// of {max} inputs.
+    Inputs { found: usize, max: usize },
+
+    /// bundle transitions contain {found} assignments in total, exceeding the
+    /// limit of {max} assignments.
+    Assignments { found: usize, max: usize },
+
+    /// bundle transitions contain {found} global state items in total,
+    /// exceeding the limit of {max} items.
+    GlobalState { found: usize, max: usize },
+}
+
+/// Transition bundle doesn't match the inputs of its witness transaction.
+#[derive(Clone, PartialEq, Eq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum CoverageError {
+    /// bundle references witness transaction input {vin}, while the
+    /// transaction has only {count} inputs.
+    OutOfRange { vin: Vin, count: u32 },
+
+    /// witness transaction inputs {0:?} are not covered by the bundle.
+    Uncovered(Vec<Vin>),
+}
+
 impl TransitionBundle {
+    /// Maximum total number of inputs in all known transitions of a bundle.
+    pub const MAX_INPUTS: usize = U16;
+    /// Maximum total number of assignments in all known transitions of a
+    /// bundle.
+    pub const MAX_ASSIGNMENTS: usize = U16;
+    /// Maximum total number of global state items in all known transitions of
+    /// a bundle.
+    pub const MAX_GLOBAL_STATE: usize = U16;
+
+    /// Constructs bundle containing a single state transition, which closes
+    /// seal(s) spent by the witness transaction input number `vin`.
+    pub fn with_single(vin: Vin, transition: Transition) -> Self {
+        let opid = transition.id();
+        TransitionBundle {
+            input_map: confined_bmap! { vin => opid },
+            known_transitions: confined_bmap! { opid => transition },
+        }
+    }
+
     pub fn bundle_id(&self) -> BundleId { self.commitment_id() }
+
+    /// Checks the bundle against the size limits.
+    ///
+    /// The confinement of the bundle data structures bounds each of the
+    /// collections independently, which still allows the total size of the
+    /// bundle to be very large. The limits checked here bound the number of
+    /// known transitions to the number of distinct transitions referenced by
+    /// the input map, and the total number of inputs, assignments and global
+    /// state items to [`Self::MAX_INPUTS`], [`Self::MAX_ASSIGNMENTS`] and
+    /// [`Self::MAX_GLOBAL_STATE`].
+    ///
+    /// NB: This doesn't protect from allocations during the decoding, which are
+    /// bounded by the limit of the strict reader used for the bundle
+    /// deserialization.
+    pub fn validate_size(&self) -> Result<(), BundleTooLarge> {
+        let max = self.input_map.values().collect::<BTreeSet<_>>().len();
+        let found = self.known_transitions.len();
+        if found > max {
+            return Err(BundleTooLarge::Transitions { found, max });
+        }
+
+        let transitions = self.known_transitions.values();
+        let found = transitions.clone().map(|t| t.inputs.len()).sum();
+        if found > Self::MAX_INPUTS {
+            return Err(BundleTooLarge::Inputs {
+                found,
+                max: Self::MAX_INPUTS,
+            });
+        }
+        let found = transitions
+            .clone()
+            .flat_map(|t| t.assignments.values())
+            .map(|a| a.len_u16() as usize)
+            .sum();
+        if found > Self::MAX_ASSIGNMENTS {
+            return Err(BundleTooLarge::Assignments {
+                found,
+                max: Self::MAX_ASSIGNMENTS,
+            });
+        }
+        let found = transitions
+            .flat_map(|t| t.globals.values())
+            .map(|g| g.len())
+            .sum();
+        if found > Self::MAX_GLOBAL_STATE {
+            return Err(BundleTooLarge::GlobalState {
+                found,
+                max: Self::MAX_GLOBAL_STATE,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Checks that the bundle references only inputs present in its witness
+    /// transaction, which has `witness_input_count` inputs.
+    ///
+    /// Witness transactions may have inputs which are not related to the
+    /// contract (like inputs paying fees), thus inputs not covered by the
+    /// bundle are not reported; use [`Self::verify_full_coverage`] if each
+    /// of the inputs must be covered.
+    pub fn verify_coverage(&self, witness_input_count: u32) -> Result<(), CoverageError> {
+        match self.input_map.keys().find(|vin| vin.to_u32() >= witness_input_count) {
+            Some(vin) => Err(CoverageError::OutOfRange {
+                vin: *vin,
+                count: witness_input_count,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks that each of the witness transaction inputs is covered by the
+    /// bundle, in addition to the checks performed by
+    /// [`Self::verify_coverage`].
+    pub fn verify_full_coverage(&self, witness_input_count: u32) -> Result<(), CoverageError> {
+        self.verify_coverage(witness_input_count)?;
+        let uncovered = (0..witness_input_count)
+            .map(Vin::from_index)
+            .filter(|vin| !self.input_map.contains_key(vin))
+            .collect::<Vec<_>>();
+        if !uncovered.is_empty() {
+            return Err(CoverageError::Uncovered(uncovered));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        Assign, AssignmentType, Assignments, GraphSeal, TypedAssigns, VoidState, XChain,
+    };
+
+    #[test]
+    fn vin_conversion() {
+        let vin = Vin::from_index(7);
+        assert_eq!(vin.to_u32(), 7);
+        assert_eq!(vin.to_usize(), 7);
+        assert_eq!(Vin::try_from(7usize), Ok(vin));
+        assert_eq!(Vin::try_from(u32::MAX as usize), Ok(Vin::from(u32::MAX)));
+        if usize::BITS > u32::BITS {
+            assert!(Vin::try_from(usize::MAX).is_err());
+        }
+        assert_eq!(vin.to_string(), "7");
+        assert_eq!("7".parse::<Vin>(), Ok(vin));
+    }
+
+    #[test]
+    fn single_transition() {
+        let transition = Transition::strict_dumb();
+        let opid = transition.id();
+        let bundle = TransitionBundle::with_single(2u32.into(), transition.clone());
+        assert_eq!(bundle.input_map, confined_bmap! { 2u32.into() => opid });
+        assert_eq!(bundle.input_map.keys().next().unwrap().to_u32(), 2);
+        assert_eq!(bundle.known_transitions, confined_bmap! { opid => transition.clone() });
+        assert_eq!(bundle.validate_size(), Ok(()));
+        assert_ne!(
+            bundle.bundle_id(),
+            TransitionBundle::with_single(1u32.into(), transition).bundle_id()
+        );
+    }
+
+    #[test]
+    fn bundle_size() {
+        let bundle = TransitionBundle::strict_dumb();
+        assert_eq!(bundle.validate_size(), Ok(()));
+
+        let transition = |ty: u16, assignments: usize| {
+            let mut transition = Transition::strict_dumb();
+            transition.transition_type = ty.into();
+            let seal = XChain::Bitcoin(GraphSeal::strict_dumb());
+            let rights = TypedAssigns::Declarative(Confined::from_collection_unsafe(vec![
+                Assign::revealed(seal, VoidState::default());
+                assignments
+            ]));
+            transition.assignments = Assignments::from(Confined::from_collection_unsafe(bmap! {
+                AssignmentType::with(1) => rights,
+            }));
+            (transition.id(), transition)
+        };
+        let (opid1, t1) = transition(1, 1);
+        let (opid2, t2) = transition(2, 1);
+        let mut bundle = TransitionBundle {
+            input_map: confined_bmap! { 0u32.into() => opid1, 1u32.into() => opid1 },
+            known_transitions: confined_bmap! { opid1 => t1.clone(), opid2 => t2 },
+        };
+        assert_eq!(bundle.validate_size(), Err(BundleTooLarge::Transitions { found: 2, max: 1 }));
+
+        let (opid2, t2) = transition(2, U16);
+        bundle.input_map = confined_bmap! { 0u32.into() => opid1, 1u32.into() => opid2 };
+        bundle.known_transitions = confined_bmap! { opid1 => t1, opid2 => t2 };
+        assert_eq!(
+            bundle.validate_size(),
+            Err(BundleTooLarge::Assignments {
+                found: U16 + 1,
+                max: TransitionBundle::MAX_ASSIGNMENTS
+            })
+        );
+    }
+
+    #[test]
+    fn input_coverage() {
+        let transition = Transition::strict_dumb();
+        let opid = transition.id();
+        let bundle = TransitionBundle {
+            input_map: confined_bmap! { 0u32.into() => opid, 2u32.into() => opid },
+            known_transitions: confined_bmap! { opid => transition },
+        };
+        assert_eq!(bundle.verify_coverage(3), Ok(()));
+        assert_eq!(bundle.verify_coverage(4), Ok(()));
+        assert_eq!(
+            bundle.verify_coverage(2),
+            Err(CoverageError::OutOfRange {
+                vin: 2u32.into(),
+                count: 2
+            })
+        );
+        assert_eq!(
+            bundle.verify_full_coverage(4),
+            Err(CoverageError::Uncovered(vec![1u32.into(), 3u32.into()]))
+        );
+        assert_eq!(bundle.verify_full_coverage(2), bundle.verify_coverage(2));
+        let bundle = TransitionBundle::with_single(0u32.into(), Transition::strict_dumb());
+        assert_eq!(bundle.verify_full_coverage(1), Ok(()));
+    }
 }
@@ -23,20 +23,27 @@
 //! Extraction of contract state.
 
 use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt::Debug;
 use std::hash::Hash;
+use std::{io, mem};
 use std::num::ParseIntError;
 use std::ops::{Deref, DerefMut};
 use std::str::FromStr;
 
-use amplify::confinement::{LargeOrdMap, LargeOrdSet, SmallVec, TinyOrdMap};
-use amplify::hex;
-use strict_encoding::{StrictDecode, StrictDumb, StrictEncode};
+use amplify::confinement::{Confined, LargeOrdMap, LargeOrdSet, LargeVec, SmallVec, TinyOrdMap};
+use amplify::{hex, Wrapper};
+use strict_encoding::{
+    DecodeError, DeserializeError, StrictDecode, StrictDeserialize, StrictDumb, StrictEncode,
+    StrictReader, StrictSerialize, TypedRead,
+};
 
+use crate::schema::AssignmentsSchema;
 use crate::{
-    Assign, AssignmentType, Assignments, AssignmentsRef, ContractId, DataState, ExposedSeal,
-    ExposedState, Extension, Genesis, GlobalStateType, OpId, Operation, RevealedAttach,
-    RevealedData, RevealedValue, SchemaId, SubSchema, Transition, TypedAssigns, VoidState,
+    Assign, AssignmentType, Assignments, AssignmentsRef, AttachId, ContractId, DataState,
+    ExposedSeal, ExposedState, Extension, ExtensionType, Genesis, GlobalStateType, OpId, OpRef,
+    OpType, Operation, RedemptionError, RevealedAttach, RevealedData, RevealedValue, SchemaId,
+    StateData, SubSchema, Transition, TypedAssigns, Valencies, ValencyType, VoidState,
     WitnessAnchor, WitnessId, XChain, XOutputSeal, LIB_NAME_RGB,
 };
 
@@ -51,6 +58,11 @@ use crate::{
 #[display("{op}/{ty}/{no}")]
 /// RGB contract operation output pointer, defined by the operation ID and
 /// output number.
+///
+/// Output pointers are ordered by the operation id, then by the assignment
+/// type and then by the output number. This order defines the canonical order
+/// of the transition inputs and thus affects the operation ids, so the field
+/// order must not be changed.
 pub struct Opout {
     pub op: OpId,
     pub ty: AssignmentType,
@@ -129,6 +141,23 @@ impl From<Option<WitnessId>> for AssignmentWitness {
     }
 }
 
+/// Provenance of an [`OutputAssignment`]: the kind of the operation which has
+/// defined the state.
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Display)]
+pub enum AssignmentOrigin {
+    /// State defined by the contract genesis.
+    #[display("genesis")]
+    Genesis,
+
+    /// State defined by a state transition with the given id.
+    #[display("transition {0}")]
+    Transition(OpId),
+
+    /// State defined by a state extension with the given id.
+    #[display("extension {0}")]
+    Extension(OpId),
+}
+
 #[derive(Copy, Clone, Eq, Debug)]
 #[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
 #[strict_type(lib = LIB_NAME_RGB)]
@@ -139,6 +168,7 @@ impl From<Option<WitnessId>> for AssignmentWitness {
 )]
 pub struct OutputAssignment<State: KnownState> {
     pub opout: Opout,
+    pub op_type: OpType,
     pub seal: XOutputSeal,
     pub state: State,
     pub witness: AssignmentWitness,
@@ -187,11 +217,13 @@ impl<State: KnownState> OutputAssignment<State> {
         witness_id: WitnessId,
         state: State,
         opid: OpId,
+        op_type: OpType,
         ty: AssignmentType,
         no: u16,
     ) -> Self {
         OutputAssignment {
             opout: Opout::new(opid, ty, no),
+            op_type,
             seal: seal.try_to_output_seal(witness_id).expect(
                 "processing contract from unverified/invalid stash: witness seal chain doesn't \
                  match anchor's chain",
@@ -209,11 +241,13 @@ impl<State: KnownState> OutputAssignment<State> {
         seal: XChain<Seal>,
         state: State,
         opid: OpId,
+        op_type: OpType,
         ty: AssignmentType,
         no: u16,
     ) -> Self {
         OutputAssignment {
             opout: Opout::new(opid, ty, no),
+            op_type,
             seal: seal.to_output_seal().expect(
                 "processing contract from unverified/invalid stash: seal must have txid \
                  information since it comes from genesis or extension",
@@ -223,10 +257,25 @@ impl<State: KnownState> OutputAssignment<State> {
         }
     }
 
-    pub fn transmute<S: KnownState>(self) -> OutputAssignment<S>
-    where S: From<State> {
+    /// Returns the kind and the id of the operation which has defined the
+    /// assignment.
+    ///
+    /// Unlike [`AssignmentWitness`], which is also absent for the state
+    /// defined by unwitnessed operations, the origin unambiguously tells the
+    /// genesis state from the state defined by other operations.
+    pub fn origin(&self) -> AssignmentOrigin {
+        match self.op_type {
+            OpType::Genesis => AssignmentOrigin::Genesis,
+            OpType::StateTransition => AssignmentOrigin::Transition(self.opout.op),
+            OpType::StateExtension => AssignmentOrigin::Extension(self.opout.op),
+        }
+    }
+
+    pub fn transmute<S>(self) -> OutputAssignment<S>
+    where S: KnownState + From<State> {
         OutputAssignment {
             opout: self.opout,
+            op_type: self.op_type,
             seal: self.seal,
             state: self.state.into(),
             witness: self.witness,
@@ -281,6 +330,40 @@ impl GlobalOrd {
     }
 }
 
+/// Known outputs defined by a single contract operation, as they are present in
+/// the [`ContractHistory`].
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct OpOutputs<'h> {
+    pub rights: Vec<&'h OutputAssignment<VoidState>>,
+    pub fungibles: Vec<&'h OutputAssignment<RevealedValue>>,
+    pub data: Vec<&'h OutputAssignment<RevealedData>>,
+    pub attach: Vec<&'h OutputAssignment<RevealedAttach>>,
+}
+
+impl OpOutputs<'_> {
+    /// Returns total number of known outputs defined by the operation.
+    pub fn len(&self) -> usize {
+        self.rights.len() + self.fungibles.len() + self.data.len() + self.attach.len()
+    }
+
+    /// Detects whether no outputs of the operation are known.
+    pub fn is_empty(&self) -> bool { self.len() == 0 }
+
+    /// Lists output pointers of all known outputs defined by the operation.
+    pub fn opouts(&self) -> impl Iterator<Item = Opout> + '_ {
+        self.rights
+            .iter()
+            .map(|a| a.opout)
+            .chain(self.fungibles.iter().map(|a| a.opout))
+            .chain(self.data.iter().map(|a| a.opout))
+            .chain(self.attach.iter().map(|a| a.opout))
+    }
+}
+
+/// Index of the contract history outputs by the id of the operation which
+/// defined them. Constructed with [`ContractHistory::index`].
+pub type OpIndex<'h> = BTreeMap<OpId, OpOutputs<'h>>;
+
 /// Contract history accumulates raw data from the contract history, extracted
 /// from a series of consignments over the time. It does consensus ordering of
 /// the state data, but it doesn't interpret or validates the state against the
@@ -308,6 +391,13 @@ pub struct ContractHistory {
     fungibles: LargeOrdSet<OutputAssignment<RevealedValue>>,
     data: LargeOrdSet<OutputAssignment<RevealedData>>,
     attach: LargeOrdSet<OutputAssignment<RevealedAttach>>,
+    /// Valencies defined by the known operations.
+    valencies: LargeOrdMap<OpId, Valencies>,
+    /// Valencies of the known operations redeemed by the known state
+    /// extensions.
+    redeemed: LargeOrdMap<OpId, Valencies>,
+    /// Outputs of the known operations with concealed seal or state.
+    concealed: LargeOrdSet<Opout>,
 }
 
 impl ContractHistory {
@@ -330,6 +420,9 @@ impl ContractHistory {
             fungibles: empty!(),
             data: empty!(),
             attach: empty!(),
+            valencies: empty!(),
+            redeemed: empty!(),
+            concealed: empty!(),
         };
         state.update_genesis(genesis);
         state
@@ -355,6 +448,269 @@ impl ContractHistory {
     /// against the schema before adding to the history.
     pub fn add_extension(&mut self, extension: &Extension, witness_anchor: WitnessAnchor) {
         self.add_operation(extension, Some(witness_anchor));
+        self.add_redemptions(extension);
+    }
+
+    /// Detects whether the valency defined by the operation `prev_id` is
+    /// redeemed by one of the known state extensions.
+    pub fn is_redeemed(&self, prev_id: OpId, valency: ValencyType) -> bool {
+        self.redeemed
+            .get(&prev_id)
+            .map(|redeemed| redeemed.contains(&valency))
+            .unwrap_or_default()
+    }
+
+    /// Builds index of all known outputs by the id of the operation which
+    /// defined them.
+    ///
+    /// The history does not keep the operations themselves, only the state
+    /// they have assigned, thus the index provides access to the operation
+    /// outputs. The index is constructed in a single pass over the history,
+    /// and should be used instead of [`Self::operation`] when multiple
+    /// operations have to be resolved.
+    pub fn index(&self) -> OpIndex<'_> {
+        let mut index = OpIndex::new();
+        for a in &self.rights {
+            index.entry(a.opout.op).or_default().rights.push(a);
+        }
+        for a in &self.fungibles {
+            index.entry(a.opout.op).or_default().fungibles.push(a);
+        }
+        for a in &self.data {
+            index.entry(a.opout.op).or_default().data.push(a);
+        }
+        for a in &self.attach {
+            index.entry(a.opout.op).or_default().attach.push(a);
+        }
+        index
+    }
+
+    /// Resolves a batch of output pointers into the known assignments, building
+    /// the operation index only once.
+    ///
+    /// Output pointers which are not known to the history are omitted from
+    /// the returned map.
+    pub fn resolve_opouts(&self, opouts: &[Opout]) -> BTreeMap<Opout, OutputAssignment<StateData>> {
+        fn find<State: ExposedState>(
+            list: &[&OutputAssignment<State>],
+            opout: Opout,
+        ) -> Option<OutputAssignment<StateData>> {
+            list.iter().find(|a| a.opout == opout).map(|a| OutputAssignment {
+                opout: a.opout,
+                op_type: a.op_type,
+                seal: a.seal,
+                state: a.state.state_data(),
+                witness: a.witness,
+            })
+        }
+
+        let index = self.index();
+        opouts
+            .iter()
+            .filter_map(|opout| {
+                let outputs = index.get(&opout.op)?;
+                find(&outputs.rights, *opout)
+                    .or_else(|| find(&outputs.fungibles, *opout))
+                    .or_else(|| find(&outputs.data, *opout))
+                    .or_else(|| find(&outputs.attach, *opout))
+                    .map(|a| (*opout, a))
+            })
+            .collect()
+    }
+
+    /// Returns known outputs defined by a specific operation, or `None` if the
+    /// history has no outputs assigned by the operation.
+    ///
+    /// The lookup requires a scan over the history; for resolving multiple
+    /// operations use [`Self::index`].
+    pub fn operation(&self, id: OpId) -> Option<OpOutputs<'_>> {
+        let outputs = OpOutputs {
+            rights: self.rights.iter().filter(|a| a.opout.op == id).collect(),
+            fungibles: self.fungibles.iter().filter(|a| a.opout.op == id).collect(),
+            data: self.data.iter().filter(|a| a.opout.op == id).collect(),
+            attach: self.attach.iter().filter(|a| a.opout.op == id).collect(),
+        };
+        if outputs.is_empty() {
+            return None;
+        }
+        Some(outputs)
+    }
+
+    /// Lists ids of all attachments referenced by the known contract state.
+    ///
+    /// Attachments may be referenced only by the owned state of attachment
+    /// type, defined by the genesis, state transitions or extensions. The
+    /// attachments which are concealed in all the known operations are not
+    /// listed.
+    pub fn referenced_attachments(&self) -> BTreeSet<AttachId> {
+        self.attach.iter().map(|a| a.state.id).collect()
+    }
+
+    /// Adds an operation with possibly more revealed data than it had when it
+    /// was added to the history before, reporting the outputs which became
+    /// known.
+    ///
+    /// The history keeps only the outputs with both seal and state revealed,
+    /// so an output is reported once its last concealed part gets revealed.
+    /// The `witness_anchor` must be `None` for the genesis only.
+    ///
+    /// # Panics
+    ///
+    /// If the operation violates RGB consensus rules and wasn't checked
+    /// against the schema before adding to the history.
+    pub fn apply_reveals(
+        &mut self,
+        op: OpRef,
+        witness_anchor: Option<WitnessAnchor>,
+    ) -> RevealReport {
+        let opid = op.id();
+        let known = self
+            .operation(opid)
+            .map(|outputs| outputs.opouts().collect::<BTreeSet<_>>())
+            .unwrap_or_default();
+        self.add_operation(&op, witness_anchor);
+        if let OpRef::Extension(extension) = op {
+            self.add_redemptions(extension);
+        }
+        let revealed = self
+            .operation(opid)
+            .map(|outputs| {
+                outputs
+                    .opouts()
+                    .filter(|opout| !known.contains(opout))
+                    .collect()
+            })
+            .unwrap_or_default();
+        RevealReport { revealed }
+    }
+
+    /// Merges another partial history of the same contract into this one.
+    ///
+    /// Global state and assignments are united; assignments which are known
+    /// to `other` only (for instance, since they were concealed in the data
+    /// this history was built from) are added. Global state of an operation
+    /// known to both histories under anchors of different finality is kept
+    /// once, with the more final anchor. Since the history keeps only
+    /// revealed state, the merge is idempotent and commutative.
+    ///
+    /// Fails if the histories belong to different contracts or use different
+    /// schemata, or if they have different state for the same operation
+    /// output or global state position. All checks are performed before the
+    /// history gets modified, so on failure it is left intact.
+    pub fn merge(&mut self, other: ContractHistory) -> Result<(), HistoryMergeError> {
+        fn added<State: KnownState>(
+            known: &LargeOrdSet<OutputAssignment<State>>,
+            other: LargeOrdSet<OutputAssignment<State>>,
+        ) -> Result<Vec<OutputAssignment<State>>, HistoryMergeError> {
+            let index = known.iter().map(|a| (a.opout, a)).collect::<BTreeMap<_, _>>();
+            let mut added = vec![];
+            for a in other {
+                match index.get(&a.opout) {
+                    None => added.push(a),
+                    // We do not use `PartialEq` here since it panics on conflicts
+                    Some(b)
+                        if a.op_type == b.op_type &&
+                            a.seal == b.seal &&
+                            a.state == b.state &&
+                            a.witness == b.witness => {}
+                    Some(_) => return Err(HistoryMergeError::AssignmentConflict(a.opout)),
+                }
+            }
+            Ok(added)
+        }
+        // Global state of the same operation may be known with anchors of
+        // different finality (for instance, once with the witness in mempool
+        // and once mined), thus the entries are matched by the witness id and
+        // index, ignoring the witness ordering.
+        type EntryKey = (Option<WitnessId>, u16);
+        fn entry_key(ord: &GlobalOrd) -> EntryKey {
+            (ord.witness_anchor.map(|wa| wa.witness_id), ord.idx)
+        }
+        fn entries(known: &LargeOrdMap<GlobalOrd, RevealedData>) -> BTreeMap<EntryKey, GlobalOrd> {
+            known.keys().map(|ord| (entry_key(ord), *ord)).collect()
+        }
+        fn extend<State: KnownState>(
+            known: &mut LargeOrdSet<OutputAssignment<State>>,
+            added: Vec<OutputAssignment<State>>,
+        ) {
+            for a in added {
+                known
+                    .push(a)
+                    .expect("contract state exceeded 2^32 items, which is unrealistic");
+            }
+        }
+
+        if self.contract_id != other.contract_id {
+            return Err(HistoryMergeError::ContractMismatch(self.contract_id, other.contract_id));
+        }
+        if self.schema_id != other.schema_id || self.root_schema_id != other.root_schema_id {
+            return Err(HistoryMergeError::SchemaMismatch);
+        }
+        for (ty, map) in &other.global {
+            let Some(known) = self.global.get(ty) else {
+                continue;
+            };
+            let index = entries(known);
+            let conflict = |(ord, s): (&GlobalOrd, &RevealedData)| {
+                index.get(&entry_key(ord)).map(|prev| &known[prev] != s).unwrap_or_default()
+            };
+            if map.iter().any(conflict) {
+                return Err(HistoryMergeError::GlobalConflict(*ty));
+            }
+        }
+        let new_types = other.global.keys().filter(|ty| !self.global.contains_key(*ty));
+        if self.global.len() + new_types.count() > u8::MAX as usize {
+            return Err(HistoryMergeError::TooManyGlobalTypes);
+        }
+        let rights = added(&self.rights, other.rights)?;
+        let fungibles = added(&self.fungibles, other.fungibles)?;
+        let data = added(&self.data, other.data)?;
+        let attach = added(&self.attach, other.attach)?;
+
+        for (ty, map) in other.global {
+            match self.global.get_mut(&ty) {
+                Some(known) => {
+                    let index = entries(known);
+                    for (ord, s) in map {
+                        if let Some(prev) = index.get(&entry_key(&ord)) {
+                            // Keep the more final anchor: mined witnesses are
+                            // ordered before the ones in mempool, and witnesses
+                            // mined deeper go first.
+                            let finality =
+                                |ord: &GlobalOrd| ord.witness_anchor.map(|a| a.witness_ord);
+                            if finality(&ord) >= finality(prev) {
+                                continue;
+                            }
+                            known.remove(prev).expect("collection allows zero elements");
+                        }
+                        known.insert(ord, s).expect(
+                            "contract global state exceeded 2^32 items, which is unrealistic",
+                        );
+                    }
+                }
+                None => {
+                    self.global.insert(ty, map).expect("number of types is checked above");
+                }
+            }
+        }
+        extend(&mut self.rights, rights);
+        extend(&mut self.fungibles, fungibles);
+        extend(&mut self.data, data);
+        extend(&mut self.attach, attach);
+        for (opid, valencies) in other.valencies {
+            self.add_valencies(opid, &valencies);
+        }
+        for (opid, redeemed) in other.redeemed {
+            for valency in redeemed.iter() {
+                self.add_redemption(opid, *valency);
+            }
+        }
+        for opout in other.concealed {
+            self.concealed
+                .push(opout)
+                .expect("contract state exceeded 2^32 items, which is unrealistic");
+        }
+        Ok(())
     }
 
     fn add_operation(&mut self, op: &impl Operation, witness_anchor: Option<WitnessAnchor>) {
@@ -416,40 +772,81 @@ impl ContractHistory {
         }
          */
 
+        self.add_valencies(opid, op.valencies());
+
         let witness_id = witness_anchor.map(|wa| wa.witness_id);
+        let op_type = op.op_type();
         match op.assignments() {
             AssignmentsRef::Genesis(assignments) => {
-                self.add_assignments(witness_id, opid, assignments)
+                self.add_assignments(witness_id, opid, op_type, assignments)
             }
             AssignmentsRef::Graph(assignments) => {
-                self.add_assignments(witness_id, opid, assignments)
+                self.add_assignments(witness_id, opid, op_type, assignments)
             }
         }
     }
 
+    fn add_valencies(&mut self, opid: OpId, valencies: &Valencies) {
+        if valencies.is_empty() {
+            return;
+        }
+        self.valencies
+            .insert(opid, valencies.clone())
+            .expect("contract state exceeded 2^32 items, which is unrealistic");
+    }
+
+    fn add_redemptions(&mut self, extension: &Extension) {
+        for (valency, prev_id) in &extension.redeemed {
+            self.add_redemption(*prev_id, *valency);
+        }
+    }
+
+    fn add_redemption(&mut self, prev_id: OpId, valency: ValencyType) {
+        if !self.redeemed.contains_key(&prev_id) {
+            self.redeemed
+                .insert(prev_id, none!())
+                .expect("contract state exceeded 2^32 items, which is unrealistic");
+        }
+        self.redeemed
+            .get_mut(&prev_id)
+            .expect("just inserted")
+            .push(valency)
+            .expect("valencies of an operation are limited by the same number");
+    }
+
     fn add_assignments<Seal: ExposedSeal>(
         &mut self,
         witness_id: Option<WitnessId>,
         opid: OpId,
+        op_type: OpType,
         assignments: &Assignments<Seal>,
     ) {
         fn process<State: ExposedState, Seal: ExposedSeal>(
             contract_state: &mut LargeOrdSet<OutputAssignment<State>>,
+            concealed: &mut LargeOrdSet<Opout>,
             assignments: &[Assign<State, Seal>],
             opid: OpId,
+            op_type: OpType,
             ty: AssignmentType,
             witness_id: Option<WitnessId>,
         ) {
-            for (no, seal, state) in assignments
-                .iter()
-                .enumerate()
-                .filter_map(|(n, a)| a.to_revealed().map(|(seal, state)| (n, seal, state)))
-            {
+            for (no, a) in assignments.iter().enumerate() {
+                let no = no as u16;
+                let opout = Opout::new(opid, ty, no);
+                let Some((seal, state)) = a.to_revealed() else {
+                    concealed
+                        .push(opout)
+                        .expect("contract state exceeded 2^32 items, which is unrealistic");
+                    continue;
+                };
+                concealed.remove(&opout).expect("collection allows zero elements");
                 let assigned_state = match witness_id {
-                    Some(witness_id) => {
-                        OutputAssignment::with_witness(seal, witness_id, state, opid, ty, no as u16)
+                    Some(witness_id) => OutputAssignment::with_witness(
+                        seal, witness_id, state, opid, op_type, ty, no,
+                    ),
+                    None => {
+                        OutputAssignment::with_no_witness(seal, state, opid, op_type, ty, no)
                     }
-                    None => OutputAssignment::with_no_witness(seal, state, opid, ty, no as u16),
                 };
                 contract_state
                     .push(assigned_state)
@@ -459,21 +856,159 @@ impl ContractHistory {
 
         for (ty, assignments) in assignments.iter() {
             match assignments {
-                TypedAssigns::Declarative(assignments) => {
-                    process(&mut self.rights, assignments, opid, *ty, witness_id)
-                }
-                TypedAssigns::Fungible(assignments) => {
-                    process(&mut self.fungibles, assignments, opid, *ty, witness_id)
-                }
-                TypedAssigns::Structured(assignments) => {
-                    process(&mut self.data, assignments, opid, *ty, witness_id)
+                TypedAssigns::Declarative(assignments) => process(
+                    &mut self.rights,
+                    &mut self.concealed,
+                    assignments,
+                    opid,
+                    op_type,
+                    *ty,
+                    witness_id,
+                ),
+                TypedAssigns::Fungible(assignments) => process(
+                    &mut self.fungibles,
+                    &mut self.concealed,
+                    assignments,
+                    opid,
+                    op_type,
+                    *ty,
+                    witness_id,
+                ),
+                TypedAssigns::Structured(assignments) => process(
+                    &mut self.data,
+                    &mut self.concealed,
+                    assignments,
+                    opid,
+                    op_type,
+                    *ty,
+                    witness_id,
+                ),
+                TypedAssigns::Attachment(assignments) => process(
+                    &mut self.attach,
+                    &mut self.concealed,
+                    assignments,
+                    opid,
+                    op_type,
+                    *ty,
+                    witness_id,
+                ),
+            }
+        }
+    }
+}
+
+/// Outputs which became known to the contract history, as reported by
+/// [`ContractHistory::apply_reveals`].
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct RevealReport {
+    /// Outputs whose seal and state are now both revealed.
+    pub revealed: BTreeSet<Opout>,
+}
+
+impl RevealReport {
+    /// Detects whether no new outputs were revealed.
+    pub fn is_empty(&self) -> bool { self.revealed.is_empty() }
+}
+
+/// Errors merging contract histories with [`ContractHistory::merge`].
+#[derive(Clone, PartialEq, Eq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum HistoryMergeError {
+    /// histories belong to different contracts {0} and {1}.
+    ContractMismatch(ContractId, ContractId),
+
+    /// histories use different schemata.
+    SchemaMismatch,
+
+    /// histories contain different state assigned to the operation output {0}.
+    AssignmentConflict(Opout),
+
+    /// histories contain different global state of type {0} at the same
+    /// position.
+    GlobalConflict(GlobalStateType),
+
+    /// merged history has too many global state types.
+    TooManyGlobalTypes,
+}
+
+/// Operations form a cycle, which violates the requirement for RGB contract
+/// operations to form a directed acyclic graph.
+#[derive(Clone, PartialEq, Eq, Debug, Display, Error)]
+#[display("contract operations {cycle:?} form a cycle")]
+pub struct CycleError {
+    /// Operations participating in the cycle, starting from a descendant and
+    /// ending with the operation which references it as a parent.
+    pub cycle: Vec<OpId>,
+}
+
+/// Checks that the provided operations form a directed acyclic graph, where
+/// edges are references from transition inputs and redeemed extension
+/// valencies to the parent operations. References to operations which are not
+/// provided are ignored.
+///
+/// Since operation ids commit to the references to their parents, a cycle may
+/// not be constructed without a hash collision; however consumers walking the
+/// operation graph must not rely on this and should run the check before
+/// processing operations coming from untrusted sources.
+///
+/// The check uses an iterative depth-first search, so it doesn't overflow the
+/// stack on long operation chains.
+pub fn check_acyclic<'op>(ops: impl IntoIterator<Item = OpRef<'op>>) -> Result<(), CycleError> {
+    let graph = ops
+        .into_iter()
+        .map(|op| {
+            let parents = match op {
+                OpRef::Genesis(_) => vec![],
+                OpRef::Transition(transition) => transition
+                    .inputs
+                    .iter()
+                    .map(|input| input.prev_out.op)
+                    .collect(),
+                OpRef::Extension(extension) => extension.redeemed.values().copied().collect(),
+            };
+            (op.id(), parents)
+        })
+        .collect::<BTreeMap<_, _>>();
+    match find_cycle(&graph) {
+        None => Ok(()),
+        Some(cycle) => Err(CycleError { cycle }),
+    }
+}
+
+fn find_cycle(graph: &BTreeMap<OpId, Vec<OpId>>) -> Option<Vec<OpId>> {
+    // `false` marks operations on the current path, `true` - fully visited ones
+    let mut visited = BTreeMap::<OpId, bool>::new();
+    for start in graph.keys() {
+        if visited.contains_key(start) {
+            continue;
+        }
+        visited.insert(*start, false);
+        let mut path = vec![(*start, 0usize)];
+        while let Some((opid, next)) = path.last().copied() {
+            let Some(parent) = graph[&opid].get(next).copied() else {
+                visited.insert(opid, true);
+                path.pop();
+                continue;
+            };
+            path.last_mut().expect("non-empty path").1 += 1;
+            match visited.get(&parent) {
+                Some(true) => {}
+                Some(false) => {
+                    let pos = path
+                        .iter()
+                        .position(|(id, _)| *id == parent)
+                        .expect("operation is on the current path");
+                    return Some(path[pos..].iter().map(|(id, _)| *id).collect());
                 }
-                TypedAssigns::Attachment(assignments) => {
-                    process(&mut self.attach, assignments, opid, *ty, witness_id)
+                None if graph.contains_key(&parent) => {
+                    visited.insert(parent, false);
+                    path.push((parent, 0));
                 }
+                None => {}
             }
         }
     }
+    None
 }
 
 /// Contract state provides API to read consensus-valid data from the
@@ -487,10 +1022,14 @@ impl ContractHistory {
     serde(crate = "serde_crate", rename_all = "camelCase")
 )]
 pub struct ContractState {
+    pub version: StateVersion,
     pub schema: SubSchema,
     pub history: ContractHistory,
 }
 
+impl StrictSerialize for ContractState {}
+impl StrictDeserialize for ContractState {}
+
 impl Deref for ContractState {
     type Target = ContractHistory;
     fn deref(&self) -> &Self::Target { &self.history }
@@ -500,7 +1039,202 @@ impl DerefMut for ContractState {
     fn deref_mut(&mut self) -> &mut Self::Target { &mut self.history }
 }
 
+/// Version of the [`ContractState`] serialization layout.
+///
+/// Contract state snapshots serialized before the introduction of the version
+/// tag start with the schema fast-forward version, which is always zero; thus
+/// zero value of the tag denotes such legacy snapshots.
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Default, Display)]
+#[derive(StrictType, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB, tags = repr, into_u8, try_from_u8)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+#[repr(u8)]
+pub enum StateVersion {
+    /// First version of the layout having explicit version tag and recording
+    /// the type of the operation which has defined each of the output
+    /// assignments.
+    #[default]
+    #[display("v1")]
+    V1 = 1,
+}
+
+impl StateVersion {
+    /// The tag used by snapshots serialized without explicit version.
+    pub const LEGACY: u8 = 0;
+}
+
+/// Errors reading contract state snapshots with [`ContractState::migrate`].
+#[derive(Clone, PartialEq, Eq, Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum MigrationError {
+    /// contract state snapshot is empty.
+    Empty,
+
+    /// contract state snapshot uses unsupported version {0} of the layout,
+    /// which probably belongs to a future RGB version.
+    UnsupportedVersion(u8),
+
+    /// type of the operation which has defined legacy output assignment {0}
+    /// can't be derived from the contract schema, since the assignment type
+    /// is defined either by both state transitions and extensions, or by none
+    /// of them.
+    AmbiguousOpType(Opout),
+
+    /// invalid contract state snapshot data. Details: {0}
+    #[from]
+    #[from(DecodeError)]
+    Deserialize(DeserializeError),
+}
+
+/// Global state entry with the same layout as the items of the
+/// [`ContractHistory`] global state maps.
+///
+/// Legacy snapshots are serialized with the order of [`XChain`] data where
+/// Liquid goes before Bitcoin, thus their global state maps, keyed by the
+/// witness ids, may be unsorted in respect to the current order. Reading the
+/// maps as lists of entries allows to re-sort them instead of rejecting.
+#[derive(Clone, Debug)]
+#[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB)]
+struct GlobalEntry {
+    ord: GlobalOrd,
+    data: RevealedData,
+}
+
+/// Output assignment with the layout used by the legacy snapshots, which
+/// doesn't contain the operation type.
+#[derive(Clone, Debug)]
+#[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB)]
+struct LegacyAssignment<State: KnownState> {
+    opout: Opout,
+    seal: XOutputSeal,
+    state: State,
+    witness: AssignmentWitness,
+}
+
+impl ContractHistory {
+    /// Reads contract history serialized with the legacy layout, where global
+    /// state may be ordered with the legacy order of [`XChain`] data (see
+    /// [`GlobalEntry`]) and the output assignments don't have operation type.
+    ///
+    /// Legacy snapshots don't keep valencies and concealed outputs, thus the
+    /// valencies of the known operations, their redemptions and the concealed
+    /// outputs are not restored.
+    ///
+    /// The operation type is derived from the contract id, which is the
+    /// genesis id, and the schema: the state defined by other operations is
+    /// attributed to state extensions or transitions if its assignment type is
+    /// defined only by the schema extensions or only by the transitions.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`MigrationError::AmbiguousOpType`] if the operation type of
+    /// some non-genesis assignment can't be derived that way, since the legacy
+    /// snapshots don't contain the operations.
+    fn strict_decode_legacy(
+        reader: &mut impl TypedRead,
+        schema: &SubSchema,
+    ) -> Result<Self, MigrationError> {
+        fn assignments<State: KnownState>(
+            reader: &mut impl TypedRead,
+            op_type: impl Fn(Opout) -> Result<OpType, MigrationError>,
+        ) -> Result<LargeOrdSet<OutputAssignment<State>>, MigrationError> {
+            let mut set = BTreeSet::new();
+            for a in LargeVec::<LegacyAssignment<State>>::strict_decode(reader)? {
+                let a = OutputAssignment {
+                    opout: a.opout,
+                    op_type: op_type(a.opout)?,
+                    seal: a.seal,
+                    state: a.state,
+                    witness: a.witness,
+                };
+                if !set.insert(a) {
+                    return Err(DecodeError::RepeatedSetValue.into());
+                }
+            }
+            Ok(Confined::try_from(set).expect("same size as the list of assignments"))
+        }
+
+        let schema_id = StrictDecode::strict_decode(reader)?;
+        let root_schema_id = StrictDecode::strict_decode(reader)?;
+        let contract_id = ContractId::strict_decode(reader)?;
+        let entries = TinyOrdMap::<GlobalStateType, LargeVec<GlobalEntry>>::strict_decode(reader)?;
+        let mut global = BTreeMap::new();
+        for (ty, entries) in entries {
+            let mut map = BTreeMap::new();
+            for GlobalEntry { ord, data } in entries {
+                if map.insert(ord, data).is_some() {
+                    return Err(DecodeError::RepeatedMapValue.into());
+                }
+            }
+            global.insert(ty, Confined::try_from(map).expect("same size as the list of entries"));
+        }
+
+        let genesis_id = OpId::from_inner(contract_id.into_inner());
+        let op_type = |opout: Opout| {
+            if opout.op == genesis_id {
+                return Ok(OpType::Genesis);
+            }
+            let defines = |assignments: &AssignmentsSchema| assignments.contains_key(&opout.ty);
+            let extension = schema.extensions.values().any(|s| defines(&s.assignments));
+            let transition = schema.transitions.values().any(|s| defines(&s.assignments));
+            match (extension, transition) {
+                (true, false) => Ok(OpType::StateExtension),
+                (false, true) => Ok(OpType::StateTransition),
+                _ => Err(MigrationError::AmbiguousOpType(opout)),
+            }
+        };
+        Ok(ContractHistory {
+            schema_id,
+            root_schema_id,
+            contract_id,
+            global: Confined::try_from(global).expect("same size as the decoded map"),
+            rights: assignments(reader, op_type)?,
+            fungibles: assignments(reader, op_type)?,
+            data: assignments(reader, op_type)?,
+            attach: assignments(reader, op_type)?,
+            valencies: empty!(),
+            redeemed: empty!(),
+            concealed: empty!(),
+        })
+    }
+}
+
 impl ContractState {
+    /// Constructs contract state with the current version of the layout.
+    pub fn with(schema: SubSchema, history: ContractHistory) -> Self {
+        ContractState {
+            version: StateVersion::default(),
+            schema,
+            history,
+        }
+    }
+
+    /// Reads contract state snapshot serialized with any of the supported
+    /// versions of the layout, upgrading it to the current version.
+    pub fn migrate(bytes: &[u8]) -> Result<ContractState, MigrationError> {
+        let version = *bytes.first().ok_or(MigrationError::Empty)?;
+        let mut reader = StrictReader::with(bytes.len(), io::Cursor::new(bytes));
+        let state = match version {
+            StateVersion::LEGACY => {
+                let schema = SubSchema::strict_decode(&mut reader)?;
+                let history = ContractHistory::strict_decode_legacy(&mut reader, &schema)?;
+                ContractState::with(schema, history)
+            }
+            v if v == StateVersion::V1 as u8 => ContractState::strict_decode(&mut reader)?,
+            v => return Err(MigrationError::UnsupportedVersion(v)),
+        };
+        if reader.unbox().position() != bytes.len() as u64 {
+            return Err(DeserializeError::DataNotEntirelyConsumed.into());
+        }
+        Ok(state)
+    }
+
     /// # Safety
     ///
     /// If the specified state type is not part of the schema.
@@ -517,3 +1251,1172 @@ impl ContractState {
         SmallVec::try_from_iter(iter).expect("same size as previous confined collection")
     }
 }
+
+/// Errors applying operations to the contract state with
+/// [`ContractState::apply_extension`].
+#[derive(Clone, PartialEq, Eq, Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum StateUpdateError {
+    /// operation of contract {1} can't be applied to the state of contract
+    /// {0}.
+    ContractMismatch(ContractId, ContractId),
+
+    /// state extension type {0} is not defined by the contract schema.
+    UnknownExtensionType(ExtensionType),
+
+    /// state extension {0} redeems valency {1} which is not allowed by the
+    /// contract schema.
+    UnexpectedValency(OpId, ValencyType),
+
+    /// state extension {0} is already applied to the contract state.
+    AlreadyApplied(OpId),
+
+    /// state extension {opid} redeems valency {valency} of operation {prev_id},
+    /// which is already redeemed by another state extension.
+    DoubleRedemption {
+        opid: OpId,
+        prev_id: OpId,
+        valency: ValencyType,
+    },
+
+    /// state extension has invalid valency redemptions: {0}
+    #[from]
+    Redemption(RedemptionError),
+}
+
+impl ContractState {
+    /// Applies state extension to the contract state, adding its global state
+    /// and assignments.
+    ///
+    /// The extension is checked against the contract id and the valencies it
+    /// may redeem according to the schema. Each of the redeemed valencies must
+    /// be defined by an operation known to the contract history and must not
+    /// be redeemed by another extension before; the applied extension marks
+    /// the valencies it redeems as consumed. The full validation of the
+    /// extension must be performed before calling this method.
+    ///
+    /// An extension whose outputs are already known is reported as
+    /// [`StateUpdateError::AlreadyApplied`].
+    pub fn apply_extension(
+        &mut self,
+        ext: &Extension,
+        anchor: &WitnessAnchor,
+    ) -> Result<(), StateUpdateError> {
+        if ext.contract_id != self.contract_id() {
+            return Err(StateUpdateError::ContractMismatch(self.contract_id(), ext.contract_id));
+        }
+        let opid = ext.id();
+        let schema = self
+            .schema
+            .extensions
+            .get(&ext.extension_type)
+            .ok_or(StateUpdateError::UnknownExtensionType(ext.extension_type))?;
+        if let Some(ty) = ext
+            .redeemed
+            .keys()
+            .find(|ty| !schema.redeems.contains(*ty))
+        {
+            return Err(StateUpdateError::UnexpectedValency(opid, *ty));
+        }
+        if self.operation(opid).is_some() {
+            return Err(StateUpdateError::AlreadyApplied(opid));
+        }
+        for (valency, prev_id) in &ext.redeemed {
+            let (valency, prev_id) = (*valency, *prev_id);
+            match self.valencies.get(&prev_id) {
+                Some(valencies) if valencies.contains(&valency) => {}
+                None if self.operation(prev_id).is_none() => {
+                    return Err(RedemptionError::UnknownParent {
+                        opid,
+                        prev_id,
+                        valency,
+                    }
+                    .into());
+                }
+                _ => {
+                    return Err(RedemptionError::UndefinedValency {
+                        opid,
+                        prev_id,
+                        valency,
+                    }
+                    .into());
+                }
+            }
+            if self.is_redeemed(prev_id, valency) {
+                return Err(StateUpdateError::DoubleRedemption {
+                    opid,
+                    prev_id,
+                    valency,
+                });
+            }
+        }
+        self.add_extension(ext, *anchor);
+        Ok(())
+    }
+}
+
+impl ContractState {
+    /// Lists all known assignments of any type assigned to the given seal.
+    ///
+    /// The state is indexed by the assignments and not by the seals, so this
+    /// performs a full scan of the contract state.
+    pub fn assignments_at(&self, seal: XOutputSeal) -> Vec<OutputAssignment<StateData>> {
+        fn filter<'a, State: ExposedState + 'a>(
+            set: &'a LargeOrdSet<OutputAssignment<State>>,
+            seal: XOutputSeal,
+        ) -> impl Iterator<Item = OutputAssignment<StateData>> + 'a {
+            set.iter()
+                .filter(move |a| a.seal == seal)
+                .map(|a| OutputAssignment {
+                    opout: a.opout,
+                    op_type: a.op_type,
+                    seal: a.seal,
+                    state: a.state.state_data(),
+                    witness: a.witness,
+                })
+        }
+
+        filter(&self.rights, seal)
+            .chain(filter(&self.fungibles, seal))
+            .chain(filter(&self.data, seal))
+            .chain(filter(&self.attach, seal))
+            .collect()
+    }
+
+    /// Estimates number of bytes allocated on heap by the contract state.
+    ///
+    /// The estimate accounts for the owned assignments, global state and its
+    /// index by the state type, together with the structured state data they
+    /// own. The overhead of the collection nodes and the schema are not taken
+    /// into account, so the actual memory consumption is somewhat larger.
+    pub fn heap_size(&self) -> usize {
+        fn set_size<State: KnownState>(set: &LargeOrdSet<OutputAssignment<State>>) -> usize {
+            set.len() * mem::size_of::<OutputAssignment<State>>()
+        }
+
+        let data = self
+            .data
+            .iter()
+            .map(|a| a.state.value.len())
+            .sum::<usize>();
+        let global = self
+            .global
+            .values()
+            .map(|map| {
+                mem::size_of::<GlobalStateType>() +
+                    mem::size_of::<LargeOrdMap<GlobalOrd, RevealedData>>() +
+                    map.len() * (mem::size_of::<GlobalOrd>() + mem::size_of::<RevealedData>()) +
+                    map.values().map(|d| d.value.len()).sum::<usize>()
+            })
+            .sum::<usize>();
+
+        set_size(&self.rights) +
+            set_size(&self.fungibles) +
+            set_size(&self.data) +
+            data +
+            set_size(&self.attach) +
+            global
+    }
+
+    /// Computes the difference between this (older) state and a `newer`
+    /// snapshot of the same contract state.
+    ///
+    /// Outputs known to the older state which are absent from the newer one
+    /// (i.e. pruned from it after being spent) are reported as spent.
+    pub fn diff(&self, newer: &Self) -> StateDiff {
+        fn added<State: KnownState>(
+            older: &LargeOrdSet<OutputAssignment<State>>,
+            newer: &LargeOrdSet<OutputAssignment<State>>,
+        ) -> BTreeSet<OutputAssignment<State>> {
+            newer.difference(older).cloned().collect()
+        }
+        fn opouts(history: &ContractHistory) -> BTreeSet<Opout> {
+            let opouts = history.rights.iter().map(|a| a.opout);
+            let opouts = opouts.chain(history.fungibles.iter().map(|a| a.opout));
+            let opouts = opouts.chain(history.data.iter().map(|a| a.opout));
+            opouts.chain(history.attach.iter().map(|a| a.opout)).collect()
+        }
+
+        let mut global = BTreeMap::<GlobalStateType, BTreeMap<GlobalOrd, RevealedData>>::new();
+        for (ty, values) in &newer.global {
+            let old = self.global.get(ty);
+            let new = values
+                .iter()
+                .filter(|(ord, _)| !old.map(|old| old.contains_key(ord)).unwrap_or_default())
+                .map(|(ord, data)| (*ord, data.clone()))
+                .collect::<BTreeMap<_, _>>();
+            if !new.is_empty() {
+                global.insert(*ty, new);
+            }
+        }
+
+        StateDiff {
+            rights: added(&self.rights, &newer.rights),
+            fungibles: added(&self.fungibles, &newer.fungibles),
+            data: added(&self.data, &newer.data),
+            attach: added(&self.attach, &newer.attach),
+            spent: opouts(self).difference(&opouts(newer)).copied().collect(),
+            global,
+        }
+    }
+
+    /// Projects the contract history into an ordered list of events, each
+    /// classifying a known operation by its effect on the contract state.
+    ///
+    /// Genesis events go first. Other events are ordered by the
+    /// [`crate::WitnessOrd`] of their witness anchors. The history keeps the
+    /// anchors only with the global state, so the anchor of an operation which
+    /// defines no global state is known only if another operation with the
+    /// same witness has defined some. Operations with unknown witness anchors
+    /// go last, and their order is unspecified: it does not reflect the
+    /// ordering of the witness transactions and must not be relied upon.
+    /// Within the same witness transaction the global state update follows
+    /// the events for the owned state.
+    ///
+    /// NB: the history does not keep operation inputs, so the events describe
+    /// only the state created by the operations.
+    pub fn events(&self) -> Vec<ContractEvent> {
+        let anchors = self
+            .global
+            .values()
+            .flat_map(|map| map.keys())
+            .filter_map(|ord| ord.witness_anchor)
+            .map(|anchor| (anchor.witness_id, anchor))
+            .collect::<BTreeMap<_, _>>();
+
+        let mut events = Vec::new();
+        for (opid, outputs) in self.index() {
+            let Some((op_type, witness)) = outputs
+                .rights
+                .first()
+                .map(|a| (a.op_type, a.witness))
+                .or_else(|| outputs.fungibles.first().map(|a| (a.op_type, a.witness)))
+                .or_else(|| outputs.data.first().map(|a| (a.op_type, a.witness)))
+                .or_else(|| outputs.attach.first().map(|a| (a.op_type, a.witness)))
+            else {
+                continue;
+            };
+            let mut amounts = BTreeMap::<AssignmentType, u64>::new();
+            for a in outputs.fungibles {
+                let sum = amounts.entry(a.opout.ty).or_default();
+                *sum = sum.saturating_add(a.state.value.as_u64());
+            }
+            events.push(match op_type {
+                OpType::Genesis => ContractEvent::Issuance {
+                    origin: AssignmentOrigin::Genesis,
+                    witness,
+                    amounts,
+                },
+                OpType::StateExtension => ContractEvent::Issuance {
+                    origin: AssignmentOrigin::Extension(opid),
+                    witness,
+                    amounts,
+                },
+                OpType::StateTransition => ContractEvent::Transfer {
+                    opid,
+                    witness,
+                    amounts,
+                },
+            });
+        }
+
+        let mut updates = BTreeMap::<Option<WitnessAnchor>, BTreeSet<GlobalStateType>>::new();
+        for (ty, map) in &self.global {
+            for ord in map.keys() {
+                updates.entry(ord.witness_anchor).or_default().insert(*ty);
+            }
+        }
+        events.extend(
+            updates
+                .into_iter()
+                .map(|(witness_anchor, global_types)| ContractEvent::GlobalUpdate {
+                    witness_anchor,
+                    global_types,
+                }),
+        );
+
+        events.sort_by_key(|event| match event {
+            ContractEvent::Issuance {
+                origin: AssignmentOrigin::Genesis,
+                ..
+            } |
+            ContractEvent::GlobalUpdate {
+                witness_anchor: None,
+                ..
+            } => (0u8, None, AssignmentWitness::Absent),
+            ContractEvent::GlobalUpdate {
+                witness_anchor: Some(anchor),
+                ..
+            } => (1, Some(*anchor), AssignmentWitness::Present(anchor.witness_id)),
+            ContractEvent::Issuance { witness, .. } | ContractEvent::Transfer { witness, .. } => {
+                let anchor = match witness {
+                    AssignmentWitness::Present(id) => anchors.get(id).copied(),
+                    AssignmentWitness::Absent => None,
+                };
+                (if anchor.is_some() { 1 } else { 2 }, anchor, *witness)
+            }
+        });
+        events
+    }
+
+    /// Aggregates the owned state assigned by the known operations, per
+    /// assignment type.
+    ///
+    /// Fungible amounts are summed over the assignments with both seal and
+    /// state revealed; the outputs whose seal or state is concealed are
+    /// counted as confidential, since their state can't be accounted for.
+    /// NB: the history doesn't track spending of the outputs, thus the
+    /// balances include the spent state.
+    pub fn balances(&self) -> BTreeMap<AssignmentType, Balance> {
+        let mut balances = BTreeMap::<AssignmentType, Balance>::new();
+        let mut revealed = BTreeSet::new();
+        for a in &self.fungibles {
+            let balance = balances.entry(a.opout.ty).or_default();
+            balance.fungible = balance.fungible.saturating_add(a.state.value.as_u64());
+            revealed.insert(a.opout);
+        }
+        let items = self
+            .rights
+            .iter()
+            .map(|a| a.opout)
+            .chain(self.data.iter().map(|a| a.opout))
+            .chain(self.attach.iter().map(|a| a.opout));
+        for opout in items {
+            balances.entry(opout.ty).or_default().items += 1;
+            revealed.insert(opout);
+        }
+        // Outputs concealed in one of the merged histories may be revealed
+        // in another one.
+        for opout in self.concealed.iter().filter(|opout| !revealed.contains(*opout)) {
+            balances.entry(opout.ty).or_default().confidential += 1;
+        }
+        balances
+    }
+}
+
+/// Owned state assigned to a single assignment type, as aggregated by
+/// [`ContractState::balances`].
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub struct Balance {
+    /// Sum of the known fungible amounts, saturating at `u64::MAX`.
+    pub fungible: u64,
+    /// Number of the outputs with concealed seal or state.
+    pub confidential: usize,
+    /// Number of the known outputs with declarative, structured or attachment
+    /// state.
+    pub items: usize,
+}
+
+/// Event in the contract activity, as projected from the contract history by
+/// [`ContractState::events`].
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum ContractEvent {
+    /// Issuance of new state by the genesis or a state extension.
+    Issuance {
+        origin: AssignmentOrigin,
+        witness: AssignmentWitness,
+        /// Total amounts of the issued fungible state per assignment type.
+        amounts: BTreeMap<AssignmentType, u64>,
+    },
+
+    /// Reassignment of existing state by a state transition.
+    Transfer {
+        opid: OpId,
+        witness: AssignmentWitness,
+        /// Total amounts of the assigned fungible state per assignment type.
+        amounts: BTreeMap<AssignmentType, u64>,
+    },
+
+    /// Update of the global state by the operations committed in the same
+    /// witness transaction, or by the genesis if the anchor is absent.
+    GlobalUpdate {
+        witness_anchor: Option<WitnessAnchor>,
+        global_types: BTreeSet<GlobalStateType>,
+    },
+}
+
+/// Difference between two snapshots of the same contract state, computed with
+/// [`ContractState::diff`].
+///
+/// The contract history keeps no spending information, so the outputs are
+/// reported as spent only once they are removed from the newer state.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct StateDiff {
+    /// Newly created outputs with declarative state.
+    pub rights: BTreeSet<OutputAssignment<VoidState>>,
+    /// Newly created outputs with fungible state.
+    pub fungibles: BTreeSet<OutputAssignment<RevealedValue>>,
+    /// Newly created outputs with structured state.
+    pub data: BTreeSet<OutputAssignment<RevealedData>>,
+    /// Newly created outputs with attachments.
+    pub attach: BTreeSet<OutputAssignment<RevealedAttach>>,
+    /// Outputs of the older state which are absent from the newer state.
+    pub spent: BTreeSet<Opout>,
+    /// Global state values added in the newer state.
+    pub global: BTreeMap<GlobalStateType, BTreeMap<GlobalOrd, RevealedData>>,
+}
+
+impl StateDiff {
+    /// Detects whether the newer state neither adds nor spends anything.
+    pub fn is_empty(&self) -> bool {
+        self.rights.is_empty() &&
+            self.fungibles.is_empty() &&
+            self.data.is_empty() &&
+            self.attach.is_empty() &&
+            self.spent.is_empty() &&
+            self.global.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use amplify::hex::{FromHex, ToHex};
+    use bp::dbc::Method;
+    use bp::Txid;
+    use strict_encoding::StrictWriter;
+
+    use super::*;
+    use crate::schema::{ExtensionSchema, MediaType, Occurrences, TransitionSchema};
+    use crate::{
+        AssetTag, BlindingFactor, Ffv, GenesisSeal, GlobalState, GraphSeal, Input, Inputs, Redeemed,
+        TransitionType, UnknownInput, Valencies, WitnessOrd,
+    };
+
+    fn genesis() -> Genesis {
+        let seal = |vout: u32| {
+            XChain::Bitcoin(GenesisSeal::with_blinding(
+                Method::TapretFirst,
+                Txid::from([0xA5; 32]),
+                vout,
+                vout as u64,
+            ))
+        };
+        let rights = TypedAssigns::Declarative(Confined::from_collection_unsafe(vec![
+            Assign::revealed(seal(0), VoidState::default()),
+            Assign::revealed(seal(1), VoidState::default()),
+        ]));
+        let data = RevealedData::with_salt(DataState::from(small_vec!(1)), 0);
+        let data = TypedAssigns::Structured(Confined::from_collection_unsafe(vec![
+            Assign::revealed(seal(2), data),
+        ]));
+        let assignments = Assignments::from(Confined::from_collection_unsafe(bmap! {
+            AssignmentType::with(1) => rights,
+            AssignmentType::with(2) => data,
+        }));
+        Genesis {
+            ffv: Ffv::default(),
+            schema_id: strict_dumb!(),
+            timestamp: 1700000000,
+            testnet: true,
+            alt_layers1: none!(),
+            metadata: empty!(),
+            globals: none!(),
+            assignments,
+            valencies: none!(),
+        }
+    }
+
+    fn history() -> (ContractHistory, OpId) {
+        let genesis = genesis();
+        let contract_id = genesis.contract_id();
+        let history = ContractHistory::with(strict_dumb!(), None, contract_id, &genesis);
+        (history, genesis.id())
+    }
+
+    fn transition(prev_out: Opout) -> Transition {
+        let rights = TypedAssigns::Declarative(Confined::from_collection_unsafe(vec![
+            Assign::revealed(
+                XChain::Bitcoin(GraphSeal::with_blinded_vout(Method::TapretFirst, 0, 0)),
+                VoidState::default(),
+            ),
+        ]));
+        let mut globals = GlobalState::default();
+        globals
+            .add_state(
+                GlobalStateType::with(1),
+                RevealedData::with_salt(DataState::from(small_vec!(2)), 0),
+            )
+            .unwrap();
+        Transition {
+            ffv: Ffv::default(),
+            contract_id: strict_dumb!(),
+            transition_type: TransitionType::with(1),
+            metadata: empty!(),
+            globals,
+            inputs: Confined::from_collection_unsafe(bset![Input::with(prev_out)]).into(),
+            assignments: Assignments::from(Confined::from_collection_unsafe(bmap! {
+                AssignmentType::with(1) => rights,
+            })),
+            valencies: none!(),
+        }
+    }
+
+    fn witness_anchor() -> WitnessAnchor {
+        WitnessAnchor {
+            witness_ord: WitnessOrd::OffChain,
+            witness_id: XChain::Bitcoin(Txid::from([0x5A; 32])),
+        }
+    }
+
+    #[test]
+    fn opout_order() {
+        let opout = |op: u8, ty: u16, no: u16| {
+            Opout::new(OpId::from([op; 32]), AssignmentType::with(ty), no)
+        };
+        let opouts = bset![
+            opout(2, 1, 0),
+            opout(1, 2, 0),
+            opout(1, 1, 2),
+            opout(1, 1, 1),
+            opout(1, 0x100, 0),
+            opout(2, 0, 5),
+        ];
+        assert_eq!(opouts.into_iter().collect::<Vec<_>>(), vec![
+            opout(1, 1, 1),
+            opout(1, 1, 2),
+            opout(1, 2, 0),
+            opout(1, 0x100, 0),
+            opout(2, 0, 5),
+            opout(2, 1, 0),
+        ]);
+
+        let inputs = Inputs::from_iter([opout(2, 1, 0), opout(1, 2, 0), opout(1, 1, 1)]);
+        let writer = StrictWriter::in_memory(0xFFFF);
+        let encoded = inputs.strict_encode(writer).unwrap().unbox();
+        assert_eq!(
+            encoded.to_hex(),
+            "03\
+             0101010101010101010101010101010101010101010101010101010101010101\
+             0100010000\
+             0101010101010101010101010101010101010101010101010101010101010101\
+             0200000000\
+             0202020202020202020202020202020202020202020202020202020202020202\
+             0100000000"
+        );
+    }
+
+    #[test]
+    fn index_by_opid() {
+        let (history, genesis_id) = history();
+        let index = history.index();
+        assert_eq!(index.len(), 1);
+        let outputs = &index[&genesis_id];
+        assert_eq!(outputs.len(), 3);
+        assert_eq!(outputs.rights.len(), 2);
+        assert_eq!(outputs.data.len(), 1);
+        assert_eq!(history.operation(genesis_id).as_ref(), Some(outputs));
+        assert_eq!(history.operation(OpId::from([0u8; 32])), None);
+    }
+
+    #[test]
+    fn batch_resolution() {
+        let (history, genesis_id) = history();
+        let known = history.index()[&genesis_id].opouts().collect::<Vec<_>>();
+        let unknown = Opout::new(OpId::from([0u8; 32]), AssignmentType::with(1), 0);
+        let mut opouts = known.clone();
+        opouts.push(unknown);
+
+        let resolved = history.resolve_opouts(&opouts);
+        assert_eq!(resolved.len(), known.len());
+        assert!(!resolved.contains_key(&unknown));
+        for opout in known {
+            assert_eq!(resolved[&opout].opout, opout);
+        }
+        let data = history.data().first().unwrap();
+        assert_eq!(resolved[&data.opout].state, data.state.state_data());
+    }
+
+    #[test]
+    fn assignment_origin() {
+        let (history, genesis_id) = history();
+        let mut state = ContractState::with(strict_dumb!(), history);
+        let transition = transition(Opout::new(genesis_id, AssignmentType::with(1), 0));
+        state.add_transition(&transition, witness_anchor());
+
+        for a in state.data() {
+            assert_eq!(a.origin(), AssignmentOrigin::Genesis);
+        }
+        let origins = state.rights().iter().map(|a| a.origin()).collect::<BTreeSet<_>>();
+        assert_eq!(origins, bset![
+            AssignmentOrigin::Genesis,
+            AssignmentOrigin::Transition(transition.id())
+        ]);
+        assert_eq!(AssignmentOrigin::Genesis.to_string(), "genesis");
+    }
+
+    #[test]
+    fn history_merge() {
+        let (history, genesis_id) = history();
+        let other_anchor = WitnessAnchor::from_mempool(XChain::Bitcoin(Txid::from([0xA6; 32])));
+        let mut a = history.clone();
+        a.add_transition(
+            &transition(Opout::new(genesis_id, AssignmentType::with(1), 0)),
+            witness_anchor(),
+        );
+        let mut b = history.clone();
+        b.add_transition(
+            &transition(Opout::new(genesis_id, AssignmentType::with(1), 1)),
+            other_anchor,
+        );
+
+        let mut ab = a.clone();
+        ab.merge(b.clone()).unwrap();
+        let mut ba = b.clone();
+        ba.merge(a.clone()).unwrap();
+        assert_eq!(ab, ba);
+        assert_eq!(ab.rights().len(), 4);
+        assert_eq!(ab.global[&GlobalStateType::with(1)].len(), 2);
+        let mut merged = ab.clone();
+        merged.merge(ab.clone()).unwrap();
+        assert_eq!(merged, ab);
+        let mut merged = history.clone();
+        merged.merge(a.clone()).unwrap();
+        assert_eq!(merged, a);
+
+        let mut conflict = history.clone();
+        conflict.add_transition(
+            &transition(Opout::new(genesis_id, AssignmentType::with(1), 0)),
+            other_anchor,
+        );
+        let opout = a.rights().iter().find(|r| r.op_type == OpType::StateTransition).unwrap().opout;
+        let mut merged = a.clone();
+        assert_eq!(merged.merge(conflict), Err(HistoryMergeError::AssignmentConflict(opout)));
+        assert_eq!(merged, a);
+
+        let contract_id = ContractId::from([1u8; 32]);
+        let foreign = ContractHistory::with(strict_dumb!(), None, contract_id, &genesis());
+        assert_eq!(
+            a.clone().merge(foreign),
+            Err(HistoryMergeError::ContractMismatch(a.contract_id(), contract_id))
+        );
+    }
+
+    #[test]
+    fn history_merge_finality() {
+        let (history, genesis_id) = history();
+        let transition = transition(Opout::new(genesis_id, AssignmentType::with(1), 0));
+        let witness_id = XChain::Bitcoin(Txid::from([0x5A; 32]));
+        let mined = WitnessAnchor::mined(witness_id, 100, 1_700_000_000).unwrap();
+        let mut mempool = history.clone();
+        mempool.add_transition(&transition, WitnessAnchor::from_mempool(witness_id));
+        let mut confirmed = history.clone();
+        confirmed.add_transition(&transition, mined);
+
+        let mut ab = mempool.clone();
+        ab.merge(confirmed.clone()).unwrap();
+        let mut ba = confirmed.clone();
+        ba.merge(mempool.clone()).unwrap();
+        assert_eq!(ab, ba);
+        assert_eq!(ab, confirmed);
+        let global = &ab.global[&GlobalStateType::with(1)];
+        assert_eq!(global.len(), 1);
+        assert_eq!(global.keys().next().unwrap().witness_anchor, Some(mined));
+    }
+
+    #[test]
+    fn reveal_report() {
+        let (mut history, genesis_id) = history();
+        let transition = transition(Opout::new(genesis_id, AssignmentType::with(1), 0));
+        let mut concealed = transition.clone();
+        let rights = concealed
+            .assignments
+            .get_mut(&AssignmentType::with(1))
+            .unwrap()
+            .as_declarative_mut()
+            .unwrap();
+        rights[0] = rights[0].conceal_seal();
+        assert_eq!(concealed.id(), transition.id());
+
+        let report = history.apply_reveals(OpRef::Transition(&concealed), Some(witness_anchor()));
+        assert!(report.is_empty());
+        assert_eq!(history.operation(transition.id()), None);
+
+        let report = history.apply_reveals(OpRef::Transition(&transition), Some(witness_anchor()));
+        assert_eq!(report.revealed, bset![Opout::new(
+            transition.id(),
+            AssignmentType::with(1),
+            0
+        )]);
+        let report = history.apply_reveals(OpRef::Transition(&transition), Some(witness_anchor()));
+        assert!(report.is_empty());
+    }
+
+    #[test]
+    fn apply_extension() {
+        let mut genesis = genesis();
+        genesis.valencies = Valencies::from_inner(tiny_bset![ValencyType::with(1)]);
+        let genesis_id = genesis.id();
+        let history = ContractHistory::with(strict_dumb!(), None, genesis.contract_id(), &genesis);
+        let mut schema = SubSchema::strict_dumb();
+        schema.extensions = Confined::from_collection_unsafe(bmap! {
+            ExtensionType::with(1) => ExtensionSchema {
+                redeems: Confined::from_collection_unsafe(bset![ValencyType::with(1)]),
+                ..default!()
+            },
+        });
+        let mut state = ContractState::with(schema, history);
+
+        let seal = XChain::Bitcoin(GenesisSeal::with_blinding(
+            Method::TapretFirst,
+            Txid::from([0xA6; 32]),
+            0,
+            0,
+        ));
+        let rights = TypedAssigns::Declarative(Confined::from_collection_unsafe(vec![
+            Assign::revealed(seal, VoidState::default()),
+        ]));
+        let mut ext = Extension {
+            ffv: Ffv::default(),
+            contract_id: state.contract_id(),
+            extension_type: ExtensionType::with(1),
+            metadata: empty!(),
+            globals: none!(),
+            assignments: Assignments::from(Confined::from_collection_unsafe(bmap! {
+                AssignmentType::with(1) => rights,
+            })),
+            redeemed: Redeemed::from_iter([(ValencyType::with(1), genesis_id)]),
+            valencies: none!(),
+        };
+
+        let mut foreign = ext.clone();
+        foreign.contract_id = ContractId::from([1u8; 32]);
+        assert_eq!(
+            state.apply_extension(&foreign, &witness_anchor()),
+            Err(StateUpdateError::ContractMismatch(state.contract_id(), foreign.contract_id))
+        );
+
+        let mut unknown = ext.clone();
+        unknown.extension_type = ExtensionType::with(2);
+        assert_eq!(
+            state.apply_extension(&unknown, &witness_anchor()),
+            Err(StateUpdateError::UnknownExtensionType(ExtensionType::with(2)))
+        );
+
+        let mut unexpected = ext.clone();
+        unexpected.redeemed = Redeemed::from_iter([(ValencyType::with(2), genesis_id)]);
+        assert_eq!(
+            state.apply_extension(&unexpected, &witness_anchor()),
+            Err(StateUpdateError::UnexpectedValency(unexpected.id(), ValencyType::with(2)))
+        );
+
+        assert_eq!(state.apply_extension(&ext, &witness_anchor()), Ok(()));
+        assert_eq!(state.rights().len(), 3);
+        assert!(state.operation(ext.id()).is_some());
+        assert_eq!(
+            state.apply_extension(&ext, &witness_anchor()),
+            Err(StateUpdateError::AlreadyApplied(ext.id()))
+        );
+
+        ext.metadata = small_vec!(1);
+        assert_eq!(
+            state.apply_extension(&ext, &witness_anchor()),
+            Err(StateUpdateError::DoubleRedemption {
+                opid: ext.id(),
+                prev_id: genesis_id,
+                valency: ValencyType::with(1),
+            })
+        );
+        assert!(state.operation(ext.id()).is_none());
+
+        ext.redeemed = Redeemed::from_iter([(ValencyType::with(1), OpId::from([0u8; 32]))]);
+        assert_eq!(
+            state.apply_extension(&ext, &witness_anchor()),
+            Err(StateUpdateError::Redemption(RedemptionError::UnknownParent {
+                opid: ext.id(),
+                prev_id: OpId::from([0u8; 32]),
+                valency: ValencyType::with(1),
+            }))
+        );
+        assert_eq!(state.rights().len(), 3);
+    }
+
+    #[test]
+    fn heap_size() {
+        let (history, genesis_id) = history();
+        let mut state = ContractState::with(strict_dumb!(), history);
+        let size = state.heap_size();
+        let data = state.data().first().unwrap().state.value.len();
+        assert_eq!(
+            size,
+            2 * mem::size_of::<OutputAssignment<VoidState>>() +
+                mem::size_of::<OutputAssignment<RevealedData>>() +
+                data
+        );
+
+        let transition = transition(Opout::new(genesis_id, AssignmentType::with(1), 0));
+        state.add_transition(&transition, witness_anchor());
+        assert!(state.heap_size() > size + mem::size_of::<OutputAssignment<VoidState>>());
+    }
+
+    #[test]
+    fn state_diff() {
+        let (history, genesis_id) = history();
+        let older = ContractState::with(strict_dumb!(), history);
+        let mut newer = older.clone();
+        let transition = transition(Opout::new(genesis_id, AssignmentType::with(1), 0));
+        newer.add_transition(&transition, witness_anchor());
+
+        assert!(older.diff(&older).is_empty());
+
+        let diff = older.diff(&newer);
+        assert_eq!(diff.rights.len(), 1);
+        assert_eq!(diff.rights.first().unwrap().opout.op, transition.id());
+        assert!(diff.fungibles.is_empty());
+        assert!(diff.data.is_empty());
+        assert!(diff.spent.is_empty());
+        assert_eq!(diff.global[&GlobalStateType::with(1)].len(), 1);
+
+        let diff = newer.diff(&older);
+        assert!(diff.rights.is_empty());
+        assert!(diff.global.is_empty());
+        assert_eq!(diff.spent, bset![Opout::new(transition.id(), AssignmentType::with(1), 0)]);
+
+        // The output spent by the transition is pruned from the newer state
+        let spent = Opout::new(genesis_id, AssignmentType::with(1), 0);
+        let right = *newer.rights.iter().find(|a| a.opout == spent).unwrap();
+        newer.rights.remove(&right).unwrap();
+        let diff = older.diff(&newer);
+        assert_eq!(diff.rights.len(), 1);
+        assert_eq!(diff.spent, bset![spent]);
+    }
+
+    #[test]
+    fn contract_events() {
+        let (history, genesis_id) = history();
+        let mut state = ContractState::with(strict_dumb!(), history);
+        let transition = transition(Opout::new(genesis_id, AssignmentType::with(1), 0));
+        state.add_transition(&transition, witness_anchor());
+
+        let witness = AssignmentWitness::Present(witness_anchor().witness_id);
+        assert_eq!(state.events(), vec![
+            ContractEvent::Issuance {
+                origin: AssignmentOrigin::Genesis,
+                witness: AssignmentWitness::Absent,
+                amounts: none!(),
+            },
+            ContractEvent::Transfer {
+                opid: transition.id(),
+                witness,
+                amounts: none!(),
+            },
+            ContractEvent::GlobalUpdate {
+                witness_anchor: Some(witness_anchor()),
+                global_types: bset![GlobalStateType::with(1)],
+            },
+        ]);
+    }
+
+    #[test]
+    fn input_seals() {
+        let (history, genesis_id) = history();
+        let seal = |vout: u32| {
+            XChain::Bitcoin(GenesisSeal::with_blinding(
+                Method::TapretFirst,
+                Txid::from([0xA5; 32]),
+                vout,
+                vout as u64,
+            ))
+            .to_output_seal()
+            .unwrap()
+        };
+        let data = Opout::new(genesis_id, AssignmentType::with(2), 0);
+        let right = Opout::new(genesis_id, AssignmentType::with(1), 1);
+        let inputs = Inputs::from_iter([data, right]);
+        assert_eq!(inputs.resolve_seals(&history), Ok(vec![seal(1), seal(2)]));
+
+        let unknown = Opout::new(genesis_id, AssignmentType::with(1), 2);
+        let inputs = Inputs::from_iter([data, unknown]);
+        assert_eq!(inputs.resolve_seals(&history), Err(UnknownInput(unknown)));
+    }
+
+    #[test]
+    fn balances() {
+        let (mut history, genesis_id) = history();
+        let transition = transition(Opout::new(genesis_id, AssignmentType::with(1), 0));
+        let mut concealed = transition.clone();
+        concealed.assignments = transition.assignments.conceal_seals();
+        history.add_transition(&concealed, witness_anchor());
+        let mut state = ContractState::with(strict_dumb!(), history);
+        let seal = state.data.first().unwrap().seal;
+        let tag = AssetTag::from([1u8; 32]);
+        for (no, amount) in [(0u16, 10u64), (1, u64::MAX)] {
+            let state_value = RevealedValue::with_blinding(amount, BlindingFactor::EMPTY, tag);
+            state
+                .fungibles
+                .push(OutputAssignment {
+                    opout: Opout::new(genesis_id, AssignmentType::with(4), no),
+                    op_type: OpType::Genesis,
+                    seal,
+                    state: state_value,
+                    witness: AssignmentWitness::Absent,
+                })
+                .unwrap();
+        }
+
+        assert_eq!(state.balances(), bmap! {
+            AssignmentType::with(1) => Balance { items: 2, confidential: 1, ..default!() },
+            AssignmentType::with(2) => Balance { items: 1, ..default!() },
+            AssignmentType::with(4) => Balance { fungible: u64::MAX, ..default!() },
+        });
+
+        state.apply_reveals(OpRef::Transition(&transition), Some(witness_anchor()));
+        assert_eq!(
+            state.balances()[&AssignmentType::with(1)],
+            Balance { items: 3, ..default!() }
+        );
+    }
+
+    #[test]
+    fn assignments_by_seal() {
+        let (history, genesis_id) = history();
+        let mut state = ContractState::with(strict_dumb!(), history);
+        let data = state.data.first().unwrap().clone();
+        let seal = data.seal;
+        let right = OutputAssignment::<VoidState> {
+            opout: Opout::new(genesis_id, AssignmentType::with(3), 0),
+            op_type: OpType::Genesis,
+            seal,
+            state: VoidState::default(),
+            witness: AssignmentWitness::Absent,
+        };
+        state.rights.push(right).unwrap();
+
+        let assignments = state.assignments_at(seal);
+        assert_eq!(assignments.len(), 2);
+        assert_eq!(assignments[0].opout, right.opout);
+        assert_eq!(assignments[0].state, StateData::Void);
+        assert_eq!(assignments[1].opout, data.opout);
+        assert_eq!(assignments[1].state, StateData::Structured(data.state));
+
+        let other = state.rights.first().unwrap().seal;
+        assert_eq!(state.assignments_at(other).len(), 1);
+    }
+
+    #[test]
+    fn attachment_refs() {
+        let (mut history, genesis_id) = history();
+        assert!(history.referenced_attachments().is_empty());
+
+        let seal = history.data().first().unwrap().seal;
+        let attach = |no: u16, id: u8| OutputAssignment::<RevealedAttach> {
+            opout: Opout::new(genesis_id, AssignmentType::with(3), no),
+            op_type: OpType::Genesis,
+            seal,
+            state: RevealedAttach::with_salt(AttachId::from([id; 32]), MediaType::Any, 0),
+            witness: AssignmentWitness::Absent,
+        };
+        history.attach.push(attach(0, 1)).unwrap();
+        history.attach.push(attach(1, 2)).unwrap();
+        history.attach.push(attach(2, 1)).unwrap();
+        assert_eq!(
+            history.referenced_attachments(),
+            bset![AttachId::from([1u8; 32]), AttachId::from([2u8; 32])]
+        );
+    }
+
+    #[test]
+    fn cycle_detection() {
+        let id = |n: u8| OpId::from([n; 32]);
+        let graph = bmap! {
+            id(1) => vec![],
+            id(2) => vec![id(1)],
+            id(3) => vec![id(1), id(2), id(9)],
+        };
+        assert_eq!(find_cycle(&graph), None);
+
+        let mut cyclic = graph.clone();
+        cyclic.insert(id(1), vec![id(4)]);
+        cyclic.insert(id(4), vec![id(3)]);
+        assert_eq!(find_cycle(&cyclic), Some(vec![id(1), id(4), id(3)]));
+
+        let mut cyclic = graph;
+        cyclic.insert(id(5), vec![id(5)]);
+        assert_eq!(find_cycle(&cyclic), Some(vec![id(5)]));
+
+        let genesis = genesis();
+        let genesis_id = genesis.id();
+        let transition = transition(Opout::new(genesis_id, AssignmentType::with(1), 0));
+        assert_eq!(
+            check_acyclic([OpRef::Genesis(&genesis), OpRef::Transition(&transition)]),
+            Ok(())
+        );
+    }
+
+    /// Snapshot of the [`history`] with a transition added and the
+    /// [`legacy_schema`] defining its assignments only by state transitions,
+    /// serialized with the legacy layout without the version tag and the
+    /// operation type.
+    const STATE_LEGACY: &str = "\
+        000000000000d83fbee02f0de5b46cf80fe11ef7fdf061c78d975d31ade9eea2bc4099339e6c000000000101\
+        00d83fbee02f0de5b46cf80fe11ef7fdf061c78d975d31ade9eea2bc4099339e6c0000010100010001000000\
+        000000000000000000000000000000000000000000000000000000000000000000000000000000e1f8aa32ff\
+        53814e6ca6c7c9554c6749c3463411d3826bc2ebb6734e25bdb7b8010100010000000101005a5a5a5a5a5a5a\
+        5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a00000100020000000000000000000000000000\
+        000003000000ce299784146ad6a90aa712fe6ab6d0246ed5a72272a2f15927a77e1d5798f276010000000001\
+        5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a0000000001005a5a5a5a5a5a\
+        5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5ae1f8aa32ff53814e6ca6c7c9554c6749c346\
+        3411d3826bc2ebb6734e25bdb7b8010000000001a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5\
+        a5a5a5a5a5a5a5a50000000000e1f8aa32ff53814e6ca6c7c9554c6749c3463411d3826bc2ebb6734e25bdb7\
+        b8010001000001a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a50100000000\
+        0000000001000000e1f8aa32ff53814e6ca6c7c9554c6749c3463411d3826bc2ebb6734e25bdb7b802000000\
+        0001a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a502000000010001000000\
+        000000000000000000000000000000000000";
+
+    /// Schema defining assignment type 1 by the state extensions and/or the
+    /// state transitions.
+    fn legacy_schema(extension: bool, transition: bool) -> SubSchema {
+        let assignments: AssignmentsSchema = Confined::from_collection_unsafe(bmap! {
+            AssignmentType::with(1) => Occurrences::Once,
+        });
+        let mut schema = SubSchema::strict_dumb();
+        if extension {
+            schema.extensions = Confined::from_collection_unsafe(bmap! {
+                ExtensionType::with(1) => ExtensionSchema {
+                    assignments: assignments.clone(),
+                    ..default!()
+                },
+            });
+        }
+        if transition {
+            schema.transitions = Confined::from_collection_unsafe(bmap! {
+                TransitionType::with(1) => TransitionSchema {
+                    assignments,
+                    ..default!()
+                },
+            });
+        }
+        schema
+    }
+
+    fn encode_legacy(state: &ContractState) -> Vec<u8> {
+        fn assignments<State: KnownState>(
+            set: &LargeOrdSet<OutputAssignment<State>>,
+        ) -> LargeVec<LegacyAssignment<State>> {
+            Confined::from_collection_unsafe(
+                set.iter()
+                    .map(|a| LegacyAssignment {
+                        opout: a.opout,
+                        seal: a.seal,
+                        state: a.state.clone(),
+                        witness: a.witness,
+                    })
+                    .collect(),
+            )
+        }
+
+        let history = &state.history;
+        let writer = StrictWriter::in_memory(u32::MAX as usize);
+        let writer = state.schema.strict_encode(writer).unwrap();
+        let writer = history.schema_id.strict_encode(writer).unwrap();
+        let writer = history.root_schema_id.strict_encode(writer).unwrap();
+        let writer = history.contract_id.strict_encode(writer).unwrap();
+        let writer = history.global.strict_encode(writer).unwrap();
+        let writer = assignments(&history.rights).strict_encode(writer).unwrap();
+        let writer = assignments(&history.fungibles).strict_encode(writer).unwrap();
+        let writer = assignments(&history.data).strict_encode(writer).unwrap();
+        let writer = assignments(&history.attach).strict_encode(writer).unwrap();
+        writer.unbox()
+    }
+
+    #[test]
+    fn state_migration() {
+        let (mut history, genesis_id) = history();
+        let transition = transition(Opout::new(genesis_id, AssignmentType::with(1), 0));
+        history.add_transition(&transition, witness_anchor());
+        let state = ContractState::with(legacy_schema(false, true), history);
+
+        let current = state.to_strict_serialized::<{ u32::MAX as usize }>().unwrap();
+        assert_eq!(current[0], StateVersion::V1 as u8);
+        assert_eq!(ContractState::migrate(&current), Ok(state.clone()));
+
+        let legacy = Vec::<u8>::from_hex(STATE_LEGACY).unwrap();
+        assert_eq!(legacy[0], StateVersion::LEGACY);
+        assert_eq!(encode_legacy(&state), legacy);
+        let migrated = ContractState::migrate(&legacy).unwrap();
+        assert_eq!(migrated, state);
+        assert_eq!(migrated.version, StateVersion::V1);
+        for a in migrated.rights.iter().chain(&state.rights) {
+            assert_eq!(a.origin(), match a.opout.op {
+                id if id == genesis_id => AssignmentOrigin::Genesis,
+                id => AssignmentOrigin::Transition(id),
+            });
+        }
+        assert!(migrated.data.iter().all(|a| a.op_type == OpType::Genesis));
+
+        // State defined only by the extensions of the schema is attributed to them
+        let state = ContractState::with(legacy_schema(true, false), state.history.clone());
+        let migrated = ContractState::migrate(&encode_legacy(&state)).unwrap();
+        let origin = migrated
+            .rights
+            .iter()
+            .find(|a| a.opout.op == transition.id())
+            .map(OutputAssignment::origin);
+        assert_eq!(origin, Some(AssignmentOrigin::Extension(transition.id())));
+
+        // The operation type is not guessed when the schema doesn't define it
+        let opout = Opout::new(transition.id(), AssignmentType::with(1), 0);
+        for schema in [legacy_schema(true, true), legacy_schema(false, false)] {
+            let state = ContractState::with(schema, state.history.clone());
+            assert_eq!(
+                ContractState::migrate(&encode_legacy(&state)),
+                Err(MigrationError::AmbiguousOpType(opout))
+            );
+        }
+
+        let mut extra = legacy.clone();
+        extra.push(0);
+        assert_eq!(
+            ContractState::migrate(&extra),
+            Err(MigrationError::Deserialize(DeserializeError::DataNotEntirelyConsumed))
+        );
+        assert!(matches!(
+            ContractState::migrate(&legacy[..legacy.len() - 1]),
+            Err(MigrationError::Deserialize(_))
+        ));
+        assert_eq!(ContractState::migrate(&[7, 0, 0]), Err(MigrationError::UnsupportedVersion(7)));
+        assert_eq!(ContractState::migrate(&[]), Err(MigrationError::Empty));
+    }
+
+    #[test]
+    fn legacy_chain_order() {
+        let (mut history, genesis_id) = history();
+        let bitcoin = transition(Opout::new(genesis_id, AssignmentType::with(1), 0));
+        let mut liquid = transition(Opout::new(genesis_id, AssignmentType::with(1), 1));
+        liquid.assignments = none!();
+        history.add_transition(&bitcoin, witness_anchor());
+        history.add_transition(&liquid, WitnessAnchor {
+            witness_ord: WitnessOrd::OffChain,
+            witness_id: XChain::Liquid(Txid::from([0x5A; 32])),
+        });
+        let state = ContractState::with(legacy_schema(false, true), history.clone());
+
+        // Before the change of the XChain order Liquid witnesses went first
+        let entries = history.global[&GlobalStateType::with(1)]
+            .iter()
+            .map(|(ord, data)| {
+                let writer = StrictWriter::in_memory(0xFFFF);
+                let writer = ord.strict_encode(writer).unwrap();
+                data.strict_encode(writer).unwrap().unbox()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(entries.len(), 2);
+        let sorted = [entries[0].as_slice(), &entries[1]].concat();
+        let unsorted = [entries[1].as_slice(), &entries[0]].concat();
+
+        let mut legacy = encode_legacy(&state);
+        let pos = legacy
+            .windows(sorted.len())
+            .position(|window| window == sorted)
+            .unwrap();
+        legacy[pos..pos + sorted.len()].copy_from_slice(&unsorted);
+
+        type Global = TinyOrdMap<GlobalStateType, LargeOrdMap<GlobalOrd, RevealedData>>;
+        let mut reader = StrictReader::with(legacy.len(), io::Cursor::new(&legacy));
+        SubSchema::strict_decode(&mut reader).unwrap();
+        SchemaId::strict_decode(&mut reader).unwrap();
+        Option::<SchemaId>::strict_decode(&mut reader).unwrap();
+        ContractId::strict_decode(&mut reader).unwrap();
+        assert_eq!(Global::strict_decode(&mut reader), Err(DecodeError::BrokenMapOrder));
+        assert_eq!(ContractState::migrate(&legacy), Ok(state));
+    }
+}
@@ -22,14 +22,15 @@
 
 use core::fmt::{self, Debug, Formatter};
 use std::cmp::Ordering;
-use std::io::Write;
+use std::io::{self, Write};
 
-use amplify::confinement::SmallBlob;
+use amplify::confinement::{self, SmallBlob, U16};
 use amplify::hex::ToHex;
 use amplify::{Bytes32, Wrapper};
 use bp::secp256k1::rand::{random, Rng, RngCore};
 use commit_verify::{CommitEncode, CommitVerify, Conceal, StrictEncodedProtocol};
-use strict_encoding::{StrictSerialize, StrictType};
+use strict_encoding::{FieldName, StrictSerialize, StrictType};
+use strict_types::{decode, SemId, Ty, TypeSystem};
 
 use super::{ConfidentialState, ExposedState};
 use crate::{StateCommitment, StateData, StateType, LIB_NAME_RGB};
@@ -71,6 +72,25 @@ impl Conceal for VoidState {
 pub struct DataState(SmallBlob);
 impl StrictSerialize for DataState {}
 
+impl DataState {
+    /// Maximal length of the structured state data, in bytes.
+    pub const MAX_LEN: usize = U16;
+
+    /// Constructs structured state from untrusted data, failing if the data
+    /// exceed [`Self::MAX_LEN`].
+    pub fn try_from_slice(data: &[u8]) -> Result<Self, confinement::Error> {
+        SmallBlob::try_from(data.to_vec()).map(Self)
+    }
+
+    /// Returns length of the structured state data, in bytes.
+    #[inline]
+    pub fn len(&self) -> usize { self.0.len() }
+
+    /// Detects whether the structured state data are empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool { self.0.is_empty() }
+}
+
 impl From<RevealedData> for DataState {
     fn from(data: RevealedData) -> Self { data.value }
 }
@@ -102,6 +122,61 @@ impl RevealedData {
             salt,
         }
     }
+
+    /// Extracts strict-encoded value of a (possibly nested) structure field
+    /// from the state data of `sem_id` type.
+    ///
+    /// The `path` lists field names starting from the outermost structure.
+    /// Since strict encoding of a structure is a concatenation of the encoded
+    /// fields, only the fields preceding the requested ones are decoded, and
+    /// the returned value is the exact slice of the state data. Fields
+    /// following the requested one are not checked.
+    pub fn field(
+        &self,
+        path: &[FieldName],
+        sem_id: SemId,
+        type_system: &TypeSystem,
+    ) -> Result<DataState, FieldError> {
+        let data = self.value.as_slice();
+        let mut cursor = io::Cursor::new(data);
+        let mut sem_id = sem_id;
+        for name in path {
+            let ty = type_system
+                .get(sem_id)
+                .ok_or_else(|| decode::Error::TypeAbsent(sem_id.into()))?;
+            let Ty::Struct(fields) = ty else {
+                return Err(FieldError::NotStruct(name.clone()));
+            };
+            let mut found = None;
+            for field in fields.iter() {
+                if field.name == *name {
+                    found = Some(field.ty);
+                    break;
+                }
+                type_system.strict_read_type(field.ty, &mut cursor)?;
+            }
+            sem_id = found.ok_or_else(|| FieldError::UnknownField(name.clone()))?;
+        }
+        let start = cursor.position() as usize;
+        type_system.strict_read_type(sem_id, &mut cursor)?;
+        let end = cursor.position() as usize;
+        Ok(DataState::try_from_slice(&data[start..end]).expect("part of the state data"))
+    }
+}
+
+/// Errors extracting structure fields with [`RevealedData::field`].
+#[derive(Clone, PartialEq, Eq, Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum FieldError {
+    /// field `{0}` is requested from a value which is not a structure.
+    NotStruct(FieldName),
+
+    /// structure doesn't have field named `{0}`.
+    UnknownField(FieldName),
+
+    /// structured state data don't match the type. Details: {0}
+    #[from]
+    Decode(decode::Error),
 }
 
 impl ExposedState for RevealedData {
@@ -116,11 +191,10 @@ impl Conceal for RevealedData {
     fn conceal(&self) -> Self::Concealed { ConcealedData::commit(self) }
 }
 
+// Revealed state commits to its concealed form, thus operation ids do not
+// change when the state gets concealed.
 impl CommitEncode for RevealedData {
-    fn commit_encode(&self, e: &mut impl Write) {
-        e.write_all(&self.value).ok();
-        e.write_all(&self.salt.to_le_bytes()).ok();
-    }
+    fn commit_encode(&self, e: &mut impl Write) { self.conceal().commit_encode(e) }
 }
 
 impl PartialOrd for RevealedData {
@@ -149,6 +223,10 @@ impl Debug for RevealedData {
 
 /// Confidential version of an structured state data.
 ///
+/// The revealed data are committed to with the strict encoding tag of
+/// [`StrictEncodedProtocol`]; the state type is separated by the
+/// [`StateCommitment`] variant.
+///
 /// See also revealed version [`RevealedData`].
 #[derive(Wrapper, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, From)]
 #[wrapper(Deref, BorrowSlice, Hex, Index, RangeOps)]
@@ -175,3 +253,93 @@ impl ConfidentialState for ConcealedData {
 impl CommitVerify<RevealedData, StrictEncodedProtocol> for ConcealedData {
     fn commit(revealed: &RevealedData) -> Self { Bytes32::commit(revealed).into() }
 }
+
+#[cfg(test)]
+mod test {
+    use amplify::confinement::TinyOrdSet;
+    use strict_encoding::{StrictDumb, StrictEncode};
+    use strict_types::typelib::LibBuilder;
+    use strict_types::SystemBuilder;
+
+    use super::*;
+
+    #[test]
+    fn data_state_len() {
+        let data = DataState::try_from_slice(b"rgb").unwrap();
+        assert_eq!(data.len(), 3);
+        assert!(!data.is_empty());
+        assert!(DataState::default().is_empty());
+
+        let max = vec![0u8; DataState::MAX_LEN];
+        assert_eq!(DataState::try_from_slice(&max).unwrap().len(), DataState::MAX_LEN);
+        assert_eq!(
+            DataState::try_from_slice(&[max.as_slice(), &[0]].concat()),
+            Err(confinement::Error::Oversize {
+                len: DataState::MAX_LEN + 1,
+                max_len: DataState::MAX_LEN
+            })
+        );
+    }
+
+    #[test]
+    fn structured_field() {
+        #[derive(Clone, Debug)]
+        #[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
+        #[strict_type(lib = "TestLib")]
+        struct Precision {
+            decimals: u8,
+            scale: u16,
+        }
+
+        #[derive(Clone, Debug)]
+        #[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
+        #[strict_type(lib = "TestLib")]
+        struct Asset {
+            name: SmallBlob,
+            precision: Precision,
+            supply: u64,
+        }
+        impl StrictSerialize for Asset {}
+
+        let lib = LibBuilder::new(libname!("TestLib"), TinyOrdSet::new())
+            .transpile::<Asset>()
+            .compile()
+            .unwrap();
+        let sys = SystemBuilder::new().import(lib).unwrap().finalize().unwrap();
+        let sem_id = sys.to_sem_id("TestLib.Asset").unwrap();
+        let type_system = sys.into_type_system();
+
+        let asset = Asset {
+            name: SmallBlob::try_from(b"RGB".to_vec()).unwrap(),
+            precision: Precision {
+                decimals: 8,
+                scale: 0x0102,
+            },
+            supply: 21_000_000,
+        };
+        let data = RevealedData::with_salt(asset.to_strict_serialized::<0xFFFF>().unwrap(), 0);
+        let field = |path: &[&'static str]| {
+            let path = path.iter().map(|name| fname!(*name)).collect::<Vec<_>>();
+            data.field(&path, sem_id, &type_system)
+        };
+
+        assert_eq!(field(&[]).unwrap(), data.value);
+        assert_eq!(field(&["name"]).unwrap().as_slice(), &[3, 0, b'R', b'G', b'B']);
+        assert_eq!(field(&["precision"]).unwrap().as_slice(), &[8, 0x02, 0x01]);
+        assert_eq!(field(&["precision", "scale"]).unwrap().as_slice(), &[0x02, 0x01]);
+        assert_eq!(field(&["supply"]).unwrap().as_slice(), &21_000_000u64.to_le_bytes());
+        assert_eq!(field(&["ticker"]), Err(FieldError::UnknownField(fname!("ticker"))));
+        assert_eq!(field(&["supply", "value"]), Err(FieldError::NotStruct(fname!("value"))));
+
+        let truncated = DataState::try_from_slice(&data.value[..5]).unwrap();
+        let truncated = RevealedData::with_salt(truncated, 0);
+        assert!(matches!(
+            truncated.field(&[fname!("supply")], sem_id, &type_system),
+            Err(FieldError::Decode(_))
+        ));
+        assert!(matches!(
+            data.field(&[fname!("name")], strict_dumb!(), &type_system),
+            Err(FieldError::Decode(decode::Error::TypeAbsent(_)))
+        ));
+    }
+}
@@ -35,6 +35,7 @@ use core::fmt::Debug;
 use core::num::ParseIntError;
 use core::ops::Deref;
 use core::str::FromStr;
+use std::collections::BTreeMap;
 use std::io;
 use std::io::Write;
 use std::time::SystemTime;
@@ -56,10 +57,13 @@ use strict_encoding::{
 };
 
 use super::{ConfidentialState, ExposedState};
-use crate::{schema, AssignmentType, StateCommitment, StateData, StateType, LIB_NAME_RGB};
+use crate::{
+    schema, AssignmentType, ContractId, StateCommitment, StateData, StateType, LIB_NAME_RGB,
+};
 
-#[derive(Wrapper, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, From)]
+#[derive(Wrapper, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Display, From)]
 #[wrapper(Deref, BorrowSlice, Hex, Index, RangeOps)]
+#[display(Self::to_hex)]
 #[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
 #[strict_type(lib = LIB_NAME_RGB)]
 #[cfg_attr(
@@ -133,6 +137,40 @@ impl FungibleState {
     }
 
     pub fn as_u64(&self) -> u64 { (*self).into() }
+
+    /// Checks that the amount doesn't exceed the `max` value, which may be
+    /// used to enforce supply ceilings.
+    pub fn validate_max(&self, max: u64) -> Result<(), ValueTooLarge> {
+        let value = self.as_u64();
+        if value > max {
+            return Err(ValueTooLarge { value, max });
+        }
+        Ok(())
+    }
+
+    /// Wraps the amount into the revealed state item using the provided
+    /// blinding factor and asset tag.
+    pub fn into_revealed(self, blinding: BlindingFactor, tag: AssetTag) -> RevealedValue {
+        RevealedValue::with_blinding(self, blinding, tag)
+    }
+
+    /// Formats the value as a decimal number with `divisibility` digits after
+    /// the decimal point. Trailing zeros of the fractional part are trimmed,
+    /// and the decimal point is omitted if no fractional part is left.
+    pub fn display(&self, divisibility: u8) -> String {
+        let value = self.as_u64().to_string();
+        let precision = divisibility as usize;
+        if precision == 0 {
+            return value;
+        }
+        let value = format!("{value:0>width$}", width = precision + 1);
+        let (int, fract) = value.split_at(value.len() - precision);
+        let fract = fract.trim_end_matches('0');
+        if fract.is_empty() {
+            return int.to_owned();
+        }
+        format!("{int}.{fract}")
+    }
 }
 
 /// value provided for a blinding factor overflows prime field order for
@@ -156,6 +194,14 @@ pub enum BlindingParseError {
     InvalidFieldElement,
 }
 
+/// Fungible amount exceeds the maximum allowed value.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Display, Error)]
+#[display("fungible amount {value} exceeds the maximum of {max}")]
+pub struct ValueTooLarge {
+    pub value: u64,
+    pub max: u64,
+}
+
 /// Blinding factor used in creating Pedersen commitment to an [`AtomicValue`].
 ///
 /// Knowledge of the blinding factor is important to reproduce the commitment
@@ -173,6 +219,9 @@ pub struct BlindingFactor(Bytes32);
 
 impl BlindingFactor {
     pub const EMPTY: Self = BlindingFactor(Bytes32::from_array([0x7E; 32]));
+
+    /// Tag used by [`BlindingFactor::derive`] for the tagged hash.
+    pub const TAG: [u8; 32] = *b"urn:lnpbp:rgb:blinding:v01#2024A";
 }
 
 impl Deref for BlindingFactor {
@@ -211,6 +260,45 @@ impl BlindingFactor {
         secp256k1_zkp::SecretKey::new(rng).into()
     }
 
+    /// Deterministically derives blinding factor for a given contract
+    /// operation output from a wallet master `seed`, such that it can be
+    /// recovered from the seed without being stored.
+    ///
+    /// The operation id commits to the blinded state, thus it can't be used
+    /// to identify the output; instead, the caller provides a `nonce` which
+    /// must be unique for each operation created with the same seed under the
+    /// contract (for instance, a wallet counter), and the output is identified
+    /// by its assignment type and number.
+    ///
+    /// The blinding factor is computed as a tagged SHA256 hash (using
+    /// [`BlindingFactor::TAG`]) of the seed, contract id, assignment type,
+    /// output number and nonce (the last three as little-endian integers),
+    /// followed by a 32-bit little-endian counter, which starts with zero. If
+    /// the hash value is not a valid non-zero field element the counter is
+    /// increased and the hash is computed once again.
+    pub fn derive(
+        seed: &[u8; 32],
+        contract_id: ContractId,
+        ty: AssignmentType,
+        no: u16,
+        nonce: u64,
+    ) -> Self {
+        let mut counter = 0u32;
+        loop {
+            let mut hasher = Sha256::from_tag(Self::TAG);
+            hasher.input_raw(seed);
+            hasher.input_raw(contract_id.as_slice());
+            hasher.input_raw(&ty.to_le_bytes());
+            hasher.input_raw(&no.to_le_bytes());
+            hasher.input_raw(&nonce.to_le_bytes());
+            hasher.input_raw(&counter.to_le_bytes());
+            if let Ok(bf) = Self::try_from(hasher.finish()) {
+                return bf;
+            }
+            counter += 1;
+        }
+    }
+
     /// Generates new blinding factor which balances a given set of negatives
     /// and positives into zero.
     ///
@@ -310,6 +398,65 @@ impl RevealedValue {
             tag,
         }
     }
+
+    /// Returns the plain amount of the state, dropping its blinding factor and
+    /// asset tag.
+    pub fn to_fungible_state(&self) -> FungibleState { self.value }
+
+    /// Detects whether both states hold the same amount, ignoring their
+    /// blinding factors.
+    ///
+    /// Unlike the structural equality, which also compares blinding factors,
+    /// the states for the same amount re-blinded in different operations are
+    /// considered the same. The asset tags are not compared either; use
+    /// [`RevealedValue::check_asset_tag`] where the states may belong to
+    /// different assets.
+    pub fn same_value(&self, other: &Self) -> bool { self.value == other.value }
+
+    /// Returns asset tag to which the state is bound.
+    pub fn asset_tag(&self) -> AssetTag { self.tag }
+
+    /// Checks that the state is bound to the `expected` asset tag.
+    pub fn check_asset_tag(&self, expected: AssetTag) -> Result<(), AssetTagMismatch> {
+        if self.tag != expected {
+            return Err(AssetTagMismatch {
+                expected,
+                found: self.tag,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Fungible state is bound to an asset tag different from the one defined by
+/// the contract for its assignment type.
+///
+/// Since the asset tag defines the generator used by Pedersen commitments,
+/// commitments bound to different tags can't be summed up, and mixing them
+/// must be rejected.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Display, Error)]
+#[display("fungible state uses asset tag {found} instead of {expected}")]
+pub struct AssetTagMismatch {
+    pub expected: AssetTag,
+    pub found: AssetTag,
+}
+
+/// Detects reuse of the same blinding factor by several revealed values, which
+/// breaks confidentiality of the amounts under their Pedersen commitments.
+///
+/// Returns all pairs of indexes `(i, j)`, with `i < j`, of values sharing a
+/// blinding factor, or `None` if all blinding factors are unique. Wallets are
+/// expected to run this check before committing to a newly constructed
+/// transition.
+pub fn detect_blinding_reuse(values: &[RevealedValue]) -> Option<Vec<(usize, usize)>> {
+    let mut seen = BTreeMap::<BlindingFactor, Vec<usize>>::new();
+    let mut pairs = vec![];
+    for (index, value) in values.iter().enumerate() {
+        let prev = seen.entry(value.blinding).or_default();
+        pairs.extend(prev.iter().map(|first| (*first, index)));
+        prev.push(index);
+    }
+    if pairs.is_empty() { None } else { Some(pairs) }
 }
 
 impl ExposedState for RevealedValue {
@@ -527,6 +674,101 @@ mod test {
 
     use super::*;
 
+    #[test]
+    fn blinding_derivation() {
+        let seed = [0xA5u8; 32];
+        let contract_id = ContractId::from([1u8; 32]);
+        let ty = AssignmentType::with(1);
+
+        let bf = BlindingFactor::derive(&seed, contract_id, ty, 0, 0);
+        assert_eq!(bf, BlindingFactor::derive(&seed, contract_id, ty, 0, 0));
+        assert_ne!(bf, BlindingFactor::derive(&seed, contract_id, ty, 1, 0));
+        assert_ne!(bf, BlindingFactor::derive(&seed, contract_id, ty, 0, 1));
+        assert_ne!(bf, BlindingFactor::derive(&seed, contract_id, AssignmentType::with(2), 0, 0));
+        assert_ne!(bf, BlindingFactor::derive(&[0x5Au8; 32], contract_id, ty, 0, 0));
+        assert_ne!(bf, BlindingFactor::derive(&seed, ContractId::from([3u8; 32]), ty, 0, 0));
+        assert_eq!(BlindingFactor::try_from(*bf), Ok(bf));
+    }
+
+    #[test]
+    fn blinding_reuse() {
+        let tag = AssetTag::from_byte_array([1u8; 32]);
+        let bf1 = BlindingFactor::random();
+        let bf2 = BlindingFactor::random();
+        let value = |amount: u64, bf| RevealedValue::with_blinding(amount, bf, tag);
+
+        assert_eq!(detect_blinding_reuse(&[]), None);
+        assert_eq!(detect_blinding_reuse(&[value(1, bf1), value(1, bf2)]), None);
+        assert_eq!(
+            detect_blinding_reuse(&[value(1, bf1), value(2, bf2), value(3, bf1), value(4, bf1)]),
+            Some(vec![(0, 2), (0, 3), (2, 3)])
+        );
+    }
+
+    #[test]
+    fn same_value() {
+        let tag = AssetTag::from_byte_array([1u8; 32]);
+        let value = RevealedValue::new_random_blinding(100u64, tag);
+        let reblinded = RevealedValue::new_random_blinding(100u64, tag);
+        assert_ne!(value, reblinded);
+        assert!(value.same_value(&reblinded));
+        assert!(value.same_value(&value));
+        assert!(!value.same_value(&RevealedValue::with_blinding(99, value.blinding, tag)));
+    }
+
+    #[test]
+    fn asset_tag_check() {
+        let tag = AssetTag::from_byte_array([1u8; 32]);
+        let other = AssetTag::from_byte_array([2u8; 32]);
+        let revealed = RevealedValue::new_random_blinding(100u64, tag);
+        assert_eq!(revealed.asset_tag(), tag);
+        assert_eq!(revealed.check_asset_tag(tag), Ok(()));
+        assert_eq!(
+            revealed.check_asset_tag(other),
+            Err(AssetTagMismatch {
+                expected: other,
+                found: tag
+            })
+        );
+        assert_eq!(tag.to_string(), "01".repeat(32));
+    }
+
+    #[test]
+    fn max_value() {
+        let value = FungibleState::Bits64(1000);
+        assert_eq!(value.validate_max(1000), Ok(()));
+        assert_eq!(value.validate_max(u64::MAX), Ok(()));
+        assert_eq!(value.validate_max(999), Err(ValueTooLarge { value: 1000, max: 999 }));
+        assert_eq!(FungibleState::Bits64(0).validate_max(0), Ok(()));
+    }
+
+    #[test]
+    fn revealed_conversion() {
+        let value = FungibleState::Bits64(100);
+        let blinding = BlindingFactor::random();
+        let tag = AssetTag::from_byte_array([1u8; 32]);
+        let revealed = value.into_revealed(blinding, tag);
+        assert_eq!(revealed, RevealedValue::with_blinding(100u64, blinding, tag));
+        assert_eq!(revealed.to_fungible_state(), value);
+    }
+
+    #[test]
+    fn fungible_display() {
+        let display = |value: u64, precision: u8| FungibleState::Bits64(value).display(precision);
+        assert_eq!(display(0, 0), "0");
+        assert_eq!(display(0, 8), "0");
+        assert_eq!(display(12345, 0), "12345");
+        assert_eq!(display(12345, 2), "123.45");
+        assert_eq!(display(12300, 2), "123");
+        assert_eq!(display(12340, 2), "123.4");
+        assert_eq!(display(5, 3), "0.005");
+        assert_eq!(display(100_000_000, 8), "1");
+        assert_eq!(display(u64::MAX, 0), "18446744073709551615");
+        assert_eq!(display(u64::MAX, 8), "184467440737.09551615");
+        assert_eq!(display(u64::MAX, 20), "0.18446744073709551615");
+        assert_eq!(display(1, 25), "0.0000000000000000000000001");
+    }
+
     #[test]
     fn commitments_determinism() {
         let tag = AssetTag::from_byte_array([1u8; 32]);
@@ -20,14 +20,16 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::collections::btree_map;
+use std::collections::{btree_map, BTreeMap};
 use std::vec;
 
 use amplify::confinement::{Confined, TinyOrdMap, U16};
 use amplify::{confinement, Wrapper};
+use commit_verify::Conceal;
 use strict_encoding::StrictDumb;
 
-use crate::{schema, RevealedData, LIB_NAME_RGB};
+use crate::schema::OccurrencesMismatch;
+use crate::{schema, ConcealedData, DataState, RevealedData, LIB_NAME_RGB};
 
 #[derive(Wrapper, WrapperMut, Clone, PartialEq, Eq, Hash, Debug, From)]
 #[wrapper(Deref)]
@@ -47,6 +49,17 @@ impl StrictDumb for GlobalValues {
 
 impl GlobalValues {
     pub fn with(state: RevealedData) -> Self { GlobalValues(Confined::with(state)) }
+
+    /// Checks that the number of values lies within `min..=max` bounds, which
+    /// are usually taken from the schema occurrences for the global state
+    /// type.
+    pub fn validate_cardinality(&self, min: u16, max: u16) -> Result<(), OccurrencesMismatch> {
+        let found = self.len() as u16;
+        if found < min || found > max {
+            return Err(OccurrencesMismatch { min, max, found });
+        }
+        Ok(())
+    }
 }
 
 impl IntoIterator for GlobalValues {
@@ -102,3 +115,118 @@ impl<'a> IntoIterator for &'a GlobalState {
 
     fn into_iter(self) -> Self::IntoIter { self.0.iter() }
 }
+
+impl GlobalState {
+    /// Projects global state into a plain map of the state values by their type,
+    /// dropping the salts.
+    ///
+    /// Values of each type are kept in the order they are defined by the
+    /// operation, which is the ascending order of their [`crate::GlobalOrd`]
+    /// once the operation is added to the contract history.
+    pub fn to_ordered_map(&self) -> BTreeMap<schema::GlobalStateType, Vec<DataState>> {
+        self.0
+            .iter()
+            .map(|(ty, values)| (*ty, values.iter().map(|data| data.value.clone()).collect()))
+            .collect()
+    }
+
+    /// Conceals all global state values, keeping their types and order.
+    ///
+    /// Since the position of each value is retained, the value at a given
+    /// index of the concealed state corresponds to the same [`crate::GlobalOrd`]
+    /// as the revealed one.
+    ///
+    /// NB: global state is committed to in the operation id in its revealed
+    /// form, thus the concealed global state can't replace the original
+    /// global state inside the operation.
+    pub fn conceal(&self) -> ConcealedGlobalState {
+        ConcealedGlobalState(Confined::from_collection_unsafe(
+            self.0
+                .iter()
+                .map(|(ty, values)| {
+                    let concealed = values.iter().map(RevealedData::conceal);
+                    (*ty, Confined::try_from_iter(concealed).expect("same size"))
+                })
+                .collect(),
+        ))
+    }
+}
+
+/// Global state with all values concealed, constructed by
+/// [`GlobalState::conceal`].
+#[derive(Wrapper, Clone, PartialEq, Eq, Hash, Default, Debug, From)]
+#[wrapper(Deref)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", transparent)
+)]
+pub struct ConcealedGlobalState(
+    TinyOrdMap<schema::GlobalStateType, Confined<Vec<ConcealedData>, 1, U16>>,
+);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn ordered_map() {
+        let data = |v: u8| RevealedData::with_salt(DataState::from(small_vec!(v)), v as u128);
+        let mut state = GlobalState::default();
+        state.add_state(2.into(), data(3)).unwrap();
+        state.add_state(1.into(), data(2)).unwrap();
+        state.add_state(2.into(), data(1)).unwrap();
+        assert_eq!(state.to_ordered_map(), bmap! {
+            schema::GlobalStateType::from(1) => vec![DataState::from(small_vec!(2))],
+            schema::GlobalStateType::from(2) => vec![
+                DataState::from(small_vec!(3)),
+                DataState::from(small_vec!(1))
+            ],
+        });
+    }
+
+    #[test]
+    fn cardinality() {
+        let mut values = GlobalValues::with(strict_dumb!());
+        assert_eq!(values.validate_cardinality(1, 1), Ok(()));
+        assert_eq!(values.validate_cardinality(0, u16::MAX), Ok(()));
+        assert_eq!(
+            values.validate_cardinality(2, 3),
+            Err(OccurrencesMismatch {
+                min: 2,
+                max: 3,
+                found: 1
+            })
+        );
+
+        values.push(strict_dumb!()).unwrap();
+        assert_eq!(values.validate_cardinality(2, 3), Ok(()));
+        assert_eq!(
+            values.validate_cardinality(0, 1),
+            Err(OccurrencesMismatch {
+                min: 0,
+                max: 1,
+                found: 2
+            })
+        );
+    }
+
+    #[test]
+    fn conceal_keeps_order() {
+        let data = |n: u8| RevealedData::with_salt(DataState::from(small_vec!(n)), n as u128);
+        let mut state = GlobalState::default();
+        state
+            .extend_state(2.into(), [data(3), data(1), data(2)])
+            .unwrap();
+        state.add_state(1.into(), data(4)).unwrap();
+
+        let concealed = state.conceal();
+        assert_eq!(concealed.len(), 2);
+        assert_eq!(concealed[&schema::GlobalStateType::with(1)].as_slice(), &[data(4).conceal()]);
+        assert_eq!(concealed[&schema::GlobalStateType::with(2)].as_slice(), &[
+            data(3).conceal(),
+            data(1).conceal(),
+            data(2).conceal()
+        ]);
+    }
+}
@@ -30,36 +30,52 @@ pub mod seal;
 pub mod assignments;
 mod operations;
 mod bundle;
+mod builder;
 #[allow(clippy::module_inception)]
 mod contract;
 mod xchain;
+#[cfg(feature = "serde")]
+mod typed;
 
-pub use anchor::{AnchorSet, AnchoredBundle, Layer1, WitnessAnchor, XAnchor};
+#[cfg(feature = "rayon")]
+pub use anchor::validate_bundles_par;
+pub use anchor::{
+    group_by_witness, recommend_close_method, validate_bundles, verify_close_method_consistency,
+    AnchorError, AnchorSet, AnchorVerifyError, AnchoredBundle, ChainNet, CloseMethodMismatch,
+    InclusionProof, Layer1, SplitError, TapretTweak, WitnessAnchor, XAnchor,
+};
 pub use assignments::{
     Assign, AssignAttach, AssignData, AssignFungible, AssignRights, Assignments, AssignmentsRef,
-    TypedAssigns,
+    MergeError, OrderError, StateTypeMismatch, TypedAssigns,
 };
 pub use attachment::{AttachId, ConcealedAttach, RevealedAttach};
-pub use bundle::{BundleId, TransitionBundle, Vin};
+pub use builder::{TransitionBuildError, TransitionBuilder};
+pub use bundle::{BundleId, BundleTooLarge, CoverageError, TransitionBundle, Vin};
 pub use contract::{
-    AssignmentWitness, ContractHistory, ContractState, GlobalOrd, KnownState, Opout,
-    OpoutParseError, OutputAssignment,
+    check_acyclic, AssignmentOrigin, AssignmentWitness, Balance, ContractEvent, ContractHistory,
+    ContractState, CycleError, GlobalOrd, HistoryMergeError, KnownState, MigrationError, OpIndex,
+    OpOutputs, Opout, OpoutParseError, OutputAssignment, RevealReport, StateDiff, StateUpdateError,
+    StateVersion,
 };
-pub use data::{ConcealedData, DataState, RevealedData, VoidState};
+pub use data::{ConcealedData, DataState, FieldError, RevealedData, VoidState};
 pub use fungible::{
-    AssetTag, BlindingFactor, BlindingParseError, ConcealedValue, FungibleState,
-    InvalidFieldElement, NoiseDumb, PedersenCommitment, RangeProof, RangeProofError, RevealedValue,
+    detect_blinding_reuse, AssetTag, AssetTagMismatch, BlindingFactor, BlindingParseError,
+    ConcealedValue, FungibleState, InvalidFieldElement, NoiseDumb, PedersenCommitment, RangeProof,
+    RangeProofError, RevealedValue, ValueTooLarge,
 };
-pub use global::{GlobalState, GlobalValues};
+pub use global::{ConcealedGlobalState, GlobalState, GlobalValues};
 pub use operations::{
-    ContractId, Extension, Genesis, Input, Inputs, OpId, OpRef, Operation, Redeemed, Transition,
-    Valencies,
+    AssignmentRef, ContractId, ContractIdMismatch, Extension, Genesis, Input, Inputs, OpId,
+    OpIdCache, OpRef, Operation, OpoutResolveError, Redeemed, RedemptionError, Transition,
+    UnknownInput, Valencies,
 };
 pub use seal::{
-    ExposedSeal, GenesisSeal, GraphSeal, OutputSeal, SecretSeal, TxoSeal, WitnessId, WitnessOrd,
-    WitnessPos, XGenesisSeal, XGraphSeal, XOutputSeal, XPubWitness, XWitness,
+    ExposedSeal, GenesisSeal, GraphSeal, OutputSeal, SecretSeal, TxoSeal, WitnessFinality,
+    WitnessId, WitnessOrd, WitnessPos, XGenesisSeal, XGraphSeal, XOutputSeal, XPubWitness, XWitness,
 };
 pub use state::{ConfidentialState, ExposedState, StateCommitment, StateData, StateType};
+#[cfg(feature = "serde")]
+pub use typed::TypedOperation;
 pub use xchain::{
     AltLayer1, AltLayer1Set, XChain, XChainParseError, XOutpoint, XCHAIN_BITCOIN_PREFIX,
     XCHAIN_LIQUID_PREFIX,
@@ -20,22 +20,27 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::collections::{btree_map, btree_set};
+use std::cmp::Ordering;
+use std::collections::{btree_map, btree_set, BTreeSet};
 use std::fmt::{self, Display, Formatter};
 use std::iter;
+use std::ops::Deref;
 use std::str::FromStr;
 
-use amplify::confinement::{SmallBlob, TinyOrdMap, TinyOrdSet};
+use amplify::confinement::{self, SmallBlob, TinyOrdMap, TinyOrdSet};
 use amplify::hex::{FromHex, ToHex};
 use amplify::{hex, ByteArray, Bytes32, FromSliceError, Wrapper};
 use baid58::{Baid58ParseError, Chunking, FromBaid58, ToBaid58, CHUNKING_32CHECKSUM};
-use commit_verify::{mpc, CommitmentId, Conceal};
+use bp::seals::txout::{CloseMethod, TxPtr};
+use commit_verify::{mpc, CommitEncode, CommitmentId, Conceal};
 use strict_encoding::{StrictDeserialize, StrictEncode, StrictSerialize};
 
 use crate::schema::{self, ExtensionType, OpFullType, OpType, SchemaId, TransitionType};
 use crate::{
-    AltLayer1Set, AssignmentType, Assignments, AssignmentsRef, Ffv, GenesisSeal, GlobalState,
-    GraphSeal, Opout, ReservedByte, TypedAssigns, LIB_NAME_RGB,
+    AltLayer1, AltLayer1Set, Assign, AssignmentType, Assignments, AssignmentsRef, ChainNet,
+    ContractHistory, CycleError, ExposedState, Ffv, GenesisSeal, GlobalState, GraphSeal, Layer1,
+    Opout, OutputAssignment, ReservedByte, StateData, TypedAssigns, XChain, XOutputSeal,
+    LIB_NAME_RGB,
 };
 
 #[derive(Wrapper, WrapperMut, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Default, From)]
@@ -80,6 +85,40 @@ impl<'a> IntoIterator for &'a Redeemed {
     fn into_iter(self) -> Self::IntoIter { self.0.iter() }
 }
 
+impl Redeemed {
+    /// Constructs redeemed valencies from an iterator over pairs of valency
+    /// type and the id of the operation defining the valency.
+    ///
+    /// The valencies are kept in the canonical order. If the same valency type
+    /// is provided multiple times, the last occurrence is used.
+    ///
+    /// # Errors
+    ///
+    /// If the number of distinct valency types exceeds 255.
+    pub fn try_from_iter(
+        iter: impl IntoIterator<Item = (schema::ValencyType, OpId)>,
+    ) -> Result<Self, confinement::Error> {
+        TinyOrdMap::try_from_iter(iter).map(Self)
+    }
+}
+
+impl FromIterator<(schema::ValencyType, OpId)> for Redeemed {
+    /// # Panics
+    ///
+    /// If the number of distinct valency types exceeds 255.
+    fn from_iter<T: IntoIterator<Item = (schema::ValencyType, OpId)>>(iter: T) -> Self {
+        Self::try_from_iter(iter).expect("too many redeemed valencies")
+    }
+}
+
+/// Set of operation inputs.
+///
+/// Inputs are always kept in the canonical order, defined by the ordering of
+/// their [`Opout`]s: first by the operation id, then by the assignment type
+/// and finally by the output number. Since the inputs are committed to as a
+/// part of the operation id, the strict decoding rejects data where inputs
+/// are not in the canonical order or repeat, making the operation id
+/// non-malleable.
 #[derive(Wrapper, WrapperMut, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Default, From)]
 #[wrapper(Deref)]
 #[wrapper_mut(DerefMut)]
@@ -101,6 +140,49 @@ impl<'a> IntoIterator for &'a Inputs {
     fn into_iter(self) -> Self::IntoIter { self.0.iter().copied() }
 }
 
+impl Inputs {
+    /// Constructs inputs spending the provided outputs, removing duplicates
+    /// and putting the inputs into the canonical order.
+    ///
+    /// # Errors
+    ///
+    /// If the number of distinct outputs exceeds 255.
+    pub fn try_from_iter(
+        iter: impl IntoIterator<Item = Opout>,
+    ) -> Result<Self, confinement::Error> {
+        TinyOrdSet::try_from_iter(iter.into_iter().map(Input::with)).map(Self)
+    }
+
+    /// Resolves the inputs into the seals they close, in the order of the
+    /// inputs, using the outputs known to the contract state.
+    ///
+    /// # Errors
+    ///
+    /// If any of the inputs spends an output unknown to the state.
+    pub fn resolve_seals(&self, state: &ContractHistory) -> Result<Vec<XOutputSeal>, UnknownInput> {
+        let opouts = self.into_iter().map(|input| input.prev_out).collect::<Vec<_>>();
+        let known = state.resolve_opouts(&opouts);
+        opouts
+            .into_iter()
+            .map(|opout| known.get(&opout).map(|a| a.seal).ok_or(UnknownInput(opout)))
+            .collect()
+    }
+}
+
+/// Operation input spends an output unknown to the contract state.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Display, Error)]
+#[display("input spends output {0} unknown to the contract state")]
+pub struct UnknownInput(pub Opout);
+
+impl FromIterator<Opout> for Inputs {
+    /// # Panics
+    ///
+    /// If the number of distinct outputs exceeds 255.
+    fn from_iter<T: IntoIterator<Item = Opout>>(iter: T) -> Self {
+        Self::try_from_iter(iter).expect("too many operation inputs")
+    }
+}
+
 #[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Display)]
 #[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
 #[strict_type(lib = LIB_NAME_RGB)]
@@ -125,7 +207,11 @@ impl Input {
 }
 
 /// Unique operation (genesis, extensions & state transition) identifier
-/// equivalent to the commitment hash
+/// equivalent to the commitment hash.
+///
+/// Each operation kind is hashed with its own tag (see [`Genesis::TAG`],
+/// [`Transition::TAG`] and [`Extension::TAG`]), so operations of different
+/// kinds can't have the same id.
 #[derive(Wrapper, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Display, From)]
 #[wrapper(Deref, BorrowSlice, Hex, Index, RangeOps)]
 #[display(Self::to_hex)]
@@ -153,7 +239,12 @@ impl OpId {
     }
 }
 
-/// Unique contract identifier equivalent to the contract genesis commitment
+/// Unique contract identifier equivalent to the contract genesis commitment,
+/// i.e. to the [`OpId`] of the genesis.
+///
+/// The string representation uses Baid58 encoding with `rgb` human-readable
+/// identifier and a checksum, such that typos in the identifier are detected
+/// during parsing.
 #[derive(Wrapper, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, From)]
 #[wrapper(Deref, BorrowSlice, Hex, Index, RangeOps)]
 #[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
@@ -250,9 +341,15 @@ pub trait Operation {
     /// Returns reference to a full set of metadata (in form of [`GlobalState`]
     /// wrapper structure) for the contract operation.
     fn globals(&self) -> &GlobalState;
+
+    /// Returns valencies defined by the contract operation. Operations not
+    /// defining valencies return an empty set.
     fn valencies(&self) -> &Valencies;
 
-    fn assignments(&self) -> AssignmentsRef;
+    /// Returns reference to the assignments of the contract operation, which
+    /// allows generic code to traverse operation state without knowing the
+    /// concrete operation type.
+    fn assignments(&self) -> AssignmentsRef<'_>;
 
     fn assignments_by_type(&self, t: AssignmentType) -> Option<TypedAssigns<GraphSeal>>;
 
@@ -274,6 +371,8 @@ pub trait Operation {
 pub struct Genesis {
     pub ffv: Ffv,
     pub schema_id: SchemaId,
+    /// Contract creation time, as a UNIX timestamp, or zero if unknown.
+    pub timestamp: i64,
     pub testnet: bool,
     pub alt_layers1: AltLayer1Set,
     pub metadata: SmallBlob,
@@ -382,11 +481,194 @@ impl CommitmentId for Extension {
     type Id = OpId;
 }
 
+/// Contract id claimed for a genesis doesn't match the genesis itself.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Display, Error)]
+#[display("contract id {claimed} doesn't match id {found} derived from the genesis")]
+pub struct ContractIdMismatch {
+    pub claimed: ContractId,
+    pub found: ContractId,
+}
+
+impl Genesis {
+    /// Re-derives contract id from the genesis and checks that it matches the
+    /// `claimed` one. Any consignment importer must perform this check before
+    /// trusting the contract id provided with the genesis.
+    pub fn verify_contract_id(&self, claimed: ContractId) -> Result<(), ContractIdMismatch> {
+        let found = self.contract_id();
+        if found != claimed {
+            return Err(ContractIdMismatch { claimed, found });
+        }
+        Ok(())
+    }
+
+    /// Writes commitment encoding of the genesis into the buffer, replacing
+    /// its content; see [`Transition::commit_into`].
+    pub fn commit_into(&self, buf: &mut Vec<u8>) {
+        buf.clear();
+        self.commit_encode(buf);
+    }
+
+    /// Lists revealed genesis assignments together with the seals they are
+    /// assigned to, i.e. the initial set of the contract unspent outputs.
+    ///
+    /// Genesis seals always define the transaction id, thus they resolve to
+    /// concrete outputs. Assignments with concealed seal or state are skipped.
+    pub fn initial_assignments(&self) -> Vec<(XOutputSeal, OutputAssignment<StateData>)> {
+        fn resolve<State: ExposedState>(
+            list: &mut Vec<(XOutputSeal, OutputAssignment<StateData>)>,
+            assignments: &[Assign<State, GenesisSeal>],
+            opid: OpId,
+            ty: AssignmentType,
+        ) {
+            for (no, (seal, state)) in assignments
+                .iter()
+                .enumerate()
+                .filter_map(|(no, a)| a.to_revealed().map(|revealed| (no, revealed)))
+            {
+                let assignment = OutputAssignment::with_no_witness(
+                    seal,
+                    state.state_data(),
+                    opid,
+                    OpType::Genesis,
+                    ty,
+                    no as u16,
+                );
+                list.push((assignment.seal, assignment));
+            }
+        }
+
+        let opid = self.id();
+        let mut list = vec![];
+        for (ty, assignments) in self.assignments.iter() {
+            match assignments {
+                TypedAssigns::Declarative(a) => resolve(&mut list, a, opid, *ty),
+                TypedAssigns::Fungible(a) => resolve(&mut list, a, opid, *ty),
+                TypedAssigns::Structured(a) => resolve(&mut list, a, opid, *ty),
+                TypedAssigns::Attachment(a) => resolve(&mut list, a, opid, *ty),
+            }
+        }
+        list
+    }
+
+    /// Returns contract creation time as a UNIX timestamp, if known.
+    pub fn timestamp(&self) -> Option<i64> {
+        match self.timestamp {
+            0 => None,
+            timestamp => Some(timestamp),
+        }
+    }
+
+    /// Orders contracts by their creation time, putting contracts with
+    /// unknown creation time last. Contracts created at the same time are
+    /// ordered by their contract ids.
+    pub fn cmp_by_creation(&self, other: &Self) -> Ordering {
+        match (self.timestamp(), other.timestamp()) {
+            (Some(a), Some(b)) if a != b => a.cmp(&b),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            _ => self.contract_id().cmp(&other.contract_id()),
+        }
+    }
+
+    /// Returns set of layer 1 chains on which the contract may have seals and
+    /// witnesses: bitcoin and all alternative layers enabled by the genesis.
+    pub fn layers1(&self) -> BTreeSet<Layer1> {
+        let mut layers1 = bset! { Layer1::Bitcoin };
+        layers1.extend(self.alt_layers1.iter().map(AltLayer1::layer1));
+        layers1
+    }
+
+    /// Detects whether the contract may have seals and witnesses on a given
+    /// network. This requires the network to be of the same kind (test or
+    /// main) as the contract and its layer 1 to be enabled by the genesis.
+    pub fn supports_chain_net(&self, chain_net: ChainNet) -> bool {
+        if chain_net.is_testnet() != self.testnet {
+            return false;
+        }
+        match chain_net.layer1() {
+            Layer1::Bitcoin => true,
+            layer1 => self.alt_layers1.iter().any(|l| l.layer1() == layer1),
+        }
+    }
+}
+
 impl Transition {
     /// Returns reference to information about the owned rights in form of
     /// [`Inputs`] wrapper structure which this operation updates with
     /// state transition ("parent owned rights").
     pub fn prev_state(&self) -> &Inputs { &self.inputs }
+
+    /// Writes commitment encoding of the transition into the buffer, replacing
+    /// its content but keeping the allocated capacity.
+    ///
+    /// Operation ids are computed by streaming the same data directly into a
+    /// hasher, so this method is not required for computing ids; it allows
+    /// to amortize allocations when the commitment data of many operations
+    /// have to be retained or processed otherwise, by reusing a single buffer.
+    pub fn commit_into(&self, buf: &mut Vec<u8>) {
+        buf.clear();
+        self.commit_encode(buf);
+    }
+
+    /// Detects whether two transitions are the same operation, possibly with
+    /// different parts of their state concealed.
+    ///
+    /// Assignments always commit to their concealed seals and state, thus the
+    /// [`OpId`] doesn't change when a transition gets concealed, and it is the
+    /// [`OpId`] which is compared.
+    pub fn same_operation(&self, other: &Transition) -> bool { self.id() == other.id() }
+
+    /// Conservative estimate of the weight units which the transition adds to
+    /// the witness transaction closing its seals with the given `method`, to
+    /// be used by wallet fee estimators. The estimate sums up:
+    /// - for each of the inputs, a transaction input spending the output which
+    ///   holds the state, signed with a taproot key path: 41 bytes of the input
+    ///   data and 66 bytes of the witness, giving 230 WU. Inputs are assumed to
+    ///   spend distinct outputs;
+    /// - for each of the assignments with a seal defined over the witness
+    ///   transaction, a taproot output of 43 bytes (172 WU); assignments
+    ///   sharing the same output are counted once. Concealed seals may be
+    ///   defined over the witness transaction, thus each of them is counted as
+    ///   a separate output, while revealed seals defined over other transactions
+    ///   add nothing;
+    /// - the commitment: for opret, an `OP_RETURN` output of 43 bytes pushing
+    ///   the commitment (172 WU); for tapret, no outputs are added, but the
+    ///   commitment leaf extends the control block of each script path spend
+    ///   of the tweaked output with a 32-byte node (32 WU).
+    ///
+    /// The commitment is shared by all the transitions closing seals with the
+    /// same witness transaction, and its cost is included in full, as if the
+    /// transition were the only one. The bundle and its multi-protocol
+    /// commitment are kept client-side, except for the commitment itself.
+    pub fn estimated_commitment_weight(&self, method: CloseMethod) -> usize {
+        const INPUT_WEIGHT: usize = (32 + 4 + 1 + 4) * 4 + 1 + 1 + 64;
+        const OUTPUT_WEIGHT: usize = (8 + 1 + 34) * 4;
+        const TAPRET_WEIGHT: usize = 32;
+
+        let mut witness_outputs = BTreeSet::new();
+        let mut concealed = 0usize;
+        for assigns in self.assignments.values() {
+            for no in 0..assigns.len_u16() {
+                match assigns.revealed_seal_at(no).expect("index within assignments") {
+                    None => concealed += 1,
+                    Some(seal) => {
+                        let reduced = seal.as_reduced_unsafe();
+                        if reduced.txid == TxPtr::WitnessTx {
+                            witness_outputs.insert((seal.layer1(), reduced.vout));
+                        }
+                    }
+                }
+            }
+        }
+
+        let commitment = match method {
+            CloseMethod::OpretFirst => OUTPUT_WEIGHT,
+            CloseMethod::TapretFirst => TAPRET_WEIGHT,
+        };
+        self.inputs.len() * INPUT_WEIGHT +
+            (witness_outputs.len() + concealed) * OUTPUT_WEIGHT +
+            commitment
+    }
 }
 
 impl Extension {
@@ -396,6 +678,103 @@ impl Extension {
     /// referenced by another state extension, which this operation updates
     /// ("parent public rights").
     pub fn redeemed(&self) -> &Redeemed { &self.redeemed }
+
+    /// Writes commitment encoding of the extension into the buffer, replacing
+    /// its content; see [`Transition::commit_into`].
+    pub fn commit_into(&self, buf: &mut Vec<u8>) {
+        buf.clear();
+        self.commit_encode(buf);
+    }
+
+    /// Checks the chain of valency redemptions leading from the extension to
+    /// the operations which have defined the redeemed valencies. Valencies may
+    /// be defined by other state extensions, which redeem valencies on their
+    /// own; in this case the check proceeds transitively up to the genesis or
+    /// state transitions, which terminate the chain.
+    ///
+    /// Operations are resolved by the provided `resolve` function using the
+    /// ids under which they are referenced by the redeeming extensions. Since
+    /// these ids commit to the redeemed valencies, the chain may not contain
+    /// a cycle without a hash collision; however the check doesn't rely on
+    /// the resolver returning operations matching the requested ids and
+    /// detects cycles anyway.
+    ///
+    /// # Errors
+    ///
+    /// Errors name the first break in the chain discovered by the depth-first
+    /// walk over the parent operations: an operation which can't be resolved,
+    /// a redeemed valency not defined by the referenced operation or a cycle
+    /// of redemptions.
+    pub fn check_redemptions<'op>(
+        &self,
+        resolve: impl Fn(OpId) -> Option<OpRef<'op>>,
+    ) -> Result<(), RedemptionError> {
+        let redemptions =
+            |redeemed: &Redeemed| redeemed.iter().map(|(ty, id)| (*ty, *id)).collect::<Vec<_>>();
+        let mut checked = BTreeSet::new();
+        let mut path = vec![(self.id(), redemptions(&self.redeemed), 0usize)];
+        while let Some((opid, redeemed, next)) = path.last_mut() {
+            let opid = *opid;
+            let Some((valency, prev_id)) = redeemed.get(*next).copied() else {
+                checked.insert(opid);
+                path.pop();
+                continue;
+            };
+            *next += 1;
+            if let Some(pos) = path.iter().position(|(id, ..)| *id == prev_id) {
+                let cycle = path[pos..].iter().map(|(id, ..)| *id).collect();
+                return Err(CycleError { cycle }.into());
+            }
+            let prev_op = resolve(prev_id).ok_or(RedemptionError::UnknownParent {
+                opid,
+                prev_id,
+                valency,
+            })?;
+            if !prev_op.valencies().contains(&valency) {
+                return Err(RedemptionError::UndefinedValency {
+                    opid,
+                    prev_id,
+                    valency,
+                });
+            }
+            if !checked.contains(&prev_id) {
+                match prev_op {
+                    OpRef::Extension(extension) => {
+                        path.push((prev_id, redemptions(&extension.redeemed), 0))
+                    }
+                    OpRef::Genesis(_) | OpRef::Transition(_) => {
+                        checked.insert(prev_id);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Errors in the chain of valency redemptions by state extensions.
+#[derive(Clone, PartialEq, Eq, Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum RedemptionError {
+    /// valency {valency} redeemed by operation {opid} references unknown
+    /// operation {prev_id}.
+    UnknownParent {
+        opid: OpId,
+        prev_id: OpId,
+        valency: schema::ValencyType,
+    },
+
+    /// operation {opid} redeems valency {valency} which is not defined by
+    /// operation {prev_id}.
+    UndefinedValency {
+        opid: OpId,
+        prev_id: OpId,
+        valency: schema::ValencyType,
+    },
+
+    /// valency redemptions are cyclic: {0}.
+    #[from]
+    Cycle(CycleError),
 }
 
 impl Operation for Genesis {
@@ -427,7 +806,7 @@ impl Operation for Genesis {
     fn valencies(&self) -> &Valencies { &self.valencies }
 
     #[inline]
-    fn assignments(&self) -> AssignmentsRef { (&self.assignments).into() }
+    fn assignments(&self) -> AssignmentsRef<'_> { (&self.assignments).into() }
 
     #[inline]
     fn assignments_by_type(&self, t: AssignmentType) -> Option<TypedAssigns<GraphSeal>> {
@@ -469,7 +848,7 @@ impl Operation for Extension {
     fn valencies(&self) -> &Valencies { &self.valencies }
 
     #[inline]
-    fn assignments(&self) -> AssignmentsRef { (&self.assignments).into() }
+    fn assignments(&self) -> AssignmentsRef<'_> { (&self.assignments).into() }
 
     #[inline]
     fn assignments_by_type(&self, t: AssignmentType) -> Option<TypedAssigns<GraphSeal>> {
@@ -511,7 +890,7 @@ impl Operation for Transition {
     fn valencies(&self) -> &Valencies { &self.valencies }
 
     #[inline]
-    fn assignments(&self) -> AssignmentsRef { (&self.assignments).into() }
+    fn assignments(&self) -> AssignmentsRef<'_> { (&self.assignments).into() }
 
     #[inline]
     fn assignments_by_type(&self, t: AssignmentType) -> Option<TypedAssigns<GraphSeal>> {
@@ -531,6 +910,19 @@ pub enum OpRef<'op> {
     Extension(&'op Extension),
 }
 
+/// Displays id of the operation prefixed with the operation kind, like
+/// `transition:<opid>`.
+impl Display for OpRef<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let kind = match self {
+            OpRef::Genesis(_) => "genesis",
+            OpRef::Transition(_) => "transition",
+            OpRef::Extension(_) => "extension",
+        };
+        write!(f, "{kind}:{}", self.id())
+    }
+}
+
 impl<'op> Operation for OpRef<'op> {
     fn op_type(&self) -> OpType {
         match self {
@@ -629,9 +1021,360 @@ impl<'op> Operation for OpRef<'op> {
     }
 }
 
+/// Finalized operation with its [`OpId`] computed once on construction.
+///
+/// Computing operation id requires hashing of the whole operation, and graph
+/// traversal may need the id of the same operation many times. The wrapper
+/// provides only read-only access to the operation, so the cached id can't
+/// get out of sync with the operation data.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct OpIdCache<Op: Operation> {
+    op: Op,
+    id: OpId,
+}
+
+impl<Op: Operation> From<Op> for OpIdCache<Op> {
+    fn from(op: Op) -> Self { OpIdCache::new(op) }
+}
+
+impl<Op: Operation> Deref for OpIdCache<Op> {
+    type Target = Op;
+    fn deref(&self) -> &Self::Target { &self.op }
+}
+
+impl<Op: Operation> OpIdCache<Op> {
+    /// Finalizes the operation, computing its id.
+    pub fn new(op: Op) -> Self {
+        let id = op.id();
+        OpIdCache { op, id }
+    }
+
+    /// Releases the operation, for instance for its modification.
+    pub fn into_inner(self) -> Op { self.op }
+}
+
+impl<Op: Operation> Operation for OpIdCache<Op> {
+    #[inline]
+    fn op_type(&self) -> OpType { self.op.op_type() }
+
+    #[inline]
+    fn full_type(&self) -> OpFullType { self.op.full_type() }
+
+    #[inline]
+    fn id(&self) -> OpId { self.id }
+
+    #[inline]
+    fn contract_id(&self) -> ContractId { self.op.contract_id() }
+
+    #[inline]
+    fn transition_type(&self) -> Option<TransitionType> { self.op.transition_type() }
+
+    #[inline]
+    fn extension_type(&self) -> Option<ExtensionType> { self.op.extension_type() }
+
+    #[inline]
+    fn metadata(&self) -> &SmallBlob { self.op.metadata() }
+
+    #[inline]
+    fn globals(&self) -> &GlobalState { self.op.globals() }
+
+    #[inline]
+    fn valencies(&self) -> &Valencies { self.op.valencies() }
+
+    #[inline]
+    fn assignments(&self) -> AssignmentsRef<'_> { self.op.assignments() }
+
+    #[inline]
+    fn assignments_by_type(&self, t: AssignmentType) -> Option<TypedAssigns<GraphSeal>> {
+        self.op.assignments_by_type(t)
+    }
+
+    #[inline]
+    fn inputs(&self) -> Inputs { self.op.inputs() }
+}
+
+impl OpRef<'_> {
+    /// Resolves operation assignment of a given type and output number.
+    ///
+    /// Genesis and extension seals are converted into graph seals, as in
+    /// [`Operation::assignments_by_type`].
+    pub fn assignment_at(
+        &self,
+        ty: AssignmentType,
+        no: u16,
+    ) -> Result<AssignmentRef, OpoutResolveError> {
+        fn resolve<State: ExposedState>(
+            assignments: &[Assign<State, GraphSeal>],
+            opout: Opout,
+        ) -> Result<AssignmentRef, OpoutResolveError> {
+            let assignment = assignments
+                .get(opout.no as usize)
+                .ok_or(OpoutResolveError::OutOfRange(opout, assignments.len() as u16))?;
+            let state = assignment
+                .as_revealed_state()
+                .ok_or(OpoutResolveError::Concealed(opout))?;
+            Ok(AssignmentRef {
+                seal: assignment.revealed_seal(),
+                state: state.state_data(),
+            })
+        }
+
+        let opout = Opout::new(self.id(), ty, no);
+        match self
+            .assignments_by_type(ty)
+            .ok_or(OpoutResolveError::UnknownType(opout))?
+        {
+            TypedAssigns::Declarative(a) => resolve(&a, opout),
+            TypedAssigns::Fungible(a) => resolve(&a, opout),
+            TypedAssigns::Structured(a) => resolve(&a, opout),
+            TypedAssigns::Attachment(a) => resolve(&a, opout),
+        }
+    }
+}
+
+/// Operation assignment resolved with [`OpRef::assignment_at`].
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct AssignmentRef {
+    /// Assignment seal, if it is revealed.
+    pub seal: Option<XChain<GraphSeal>>,
+    /// Assigned state.
+    pub state: StateData,
+}
+
+/// Errors resolving operation output with [`OpRef::assignment_at`].
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum OpoutResolveError {
+    /// operation doesn't have assignments of the type used in the output {0}.
+    UnknownType(Opout),
+
+    /// output {0} exceeds the number of assignments of that type ({1}).
+    OutOfRange(Opout, u16),
+
+    /// state of the output {0} is concealed.
+    Concealed(Opout),
+}
+
 #[cfg(test)]
 mod test {
+    use std::collections::BTreeMap;
+    use std::io;
+
+    use amplify::confinement::Confined;
+    use bp::dbc::Method;
+    use bp::{ConsensusEncode, Outpoint, ScriptPubkey, SeqNo, TxIn, TxOut, Txid, Witness};
+    use commit_verify::{DigestExt, Sha256};
+    use strict_encoding::{DecodeError, StrictDecode, StrictDumb, StrictReader, StrictWriter};
+
     use super::*;
+    use crate::{
+        AltLayer1, AssetTag, AssignRights, BlindingFactor, ConcealedAttach, ConcealedData,
+        DataState, RevealedData, RevealedValue, StateCommitment, SubSchema, TransitionBundle,
+        VoidState,
+    };
+
+    #[test]
+    fn empty_assignments_id() {
+        // Golden vector: the empty assignments must be committed as a zero-length map
+        let transition = Transition::strict_dumb();
+        assert!(transition.assignments.is_empty());
+        assert_eq!(
+            transition.id().to_string(),
+            "b27ce4444ec7969e699c298c3d67d46465959469a9c23c0cc70e56a1d1009a49"
+        );
+    }
+
+    #[test]
+    fn dyn_assignments() {
+        let genesis = Genesis::strict_dumb();
+        let transition = Transition::strict_dumb();
+        let extension = Extension::strict_dumb();
+        let ops: [&dyn Operation; 3] = [&genesis, &transition, &extension];
+        for op in ops {
+            let assignments = op.assignments();
+            assert!(assignments.is_empty());
+            assert_eq!(assignments.len(), 0);
+            assert!(!assignments.has_type(AssignmentType::with(1)));
+        }
+        assert!(matches!(genesis.assignments(), AssignmentsRef::Genesis(_)));
+        assert!(matches!(transition.assignments(), AssignmentsRef::Graph(_)));
+        assert!(matches!(extension.assignments(), AssignmentsRef::Genesis(_)));
+    }
+
+    #[test]
+    fn dyn_globals_valencies() {
+        let mut genesis = Genesis::strict_dumb();
+        genesis.valencies = Valencies::from_inner(tiny_bset![schema::ValencyType::with(1)]);
+        let mut transition = Transition::strict_dumb();
+        let data = RevealedData::with_salt(DataState::try_from_slice(&[1, 2, 3]).unwrap(), 0);
+        transition.globals.add_state(schema::GlobalStateType::with(2), data).unwrap();
+        let extension = Extension::strict_dumb();
+
+        let ops: [&dyn Operation; 3] = [&genesis, &transition, &extension];
+        let valencies = ops.iter().map(|op| op.valencies().len()).collect::<Vec<_>>();
+        let globals = ops.iter().map(|op| op.globals().len()).collect::<Vec<_>>();
+        assert_eq!(valencies, vec![1, 0, 0]);
+        assert_eq!(globals, vec![0, 1, 0]);
+    }
+
+    #[test]
+    fn same_operation() {
+        let seal = GraphSeal::with_blinded_vout(Method::OpretFirst, 0, 0);
+        let data = RevealedData::with_salt(DataState::try_from_slice(&[1, 2, 3]).unwrap(), 0);
+        let value = RevealedValue::with_blinding(5, BlindingFactor::EMPTY, AssetTag::from([1; 32]));
+        let mut transition = Transition::strict_dumb();
+        transition.assignments = Assignments::from(Confined::from_collection_unsafe(bmap! {
+            AssignmentType::with(1) => TypedAssigns::Declarative(Confined::from_collection_unsafe(
+                vec![Assign::revealed(XChain::Bitcoin(seal), VoidState::default())]
+            )),
+            AssignmentType::with(2) => TypedAssigns::Structured(Confined::from_collection_unsafe(
+                vec![Assign::revealed(XChain::Bitcoin(seal), data.clone())]
+            )),
+            AssignmentType::with(3) => TypedAssigns::Fungible(Confined::from_collection_unsafe(
+                vec![Assign::revealed(XChain::Bitcoin(seal), value)]
+            )),
+        }));
+
+        let concealed = transition.conceal();
+        assert!(concealed.assignments.values().all(|a| a.revealed_seal_at(0) == Ok(None)));
+        assert_eq!(concealed.id(), transition.id());
+        assert!(transition.same_operation(&concealed));
+        assert!(concealed.same_operation(&transition));
+
+        // Partially concealed assignments commit to the same data
+        let mut partial = concealed.clone();
+        let seal_only = Assign::ConfidentialSeal {
+            seal: XChain::Bitcoin(seal).conceal(),
+            state: data,
+        };
+        let assigns = TypedAssigns::Structured(Confined::from_collection_unsafe(vec![seal_only]));
+        partial.assignments.insert(AssignmentType::with(2), assigns).unwrap();
+        assert_eq!(partial.id(), transition.id());
+        assert!(partial.same_operation(&concealed));
+
+        let mut other = transition.clone();
+        other.transition_type = TransitionType::with(1);
+        assert!(!transition.same_operation(&other));
+    }
+
+    #[test]
+    fn commitment_weight() {
+        let txin = TxIn {
+            prev_output: Outpoint::new(Txid::from([1; 32]), 0u32),
+            sig_script: none!(),
+            sequence: SeqNo::from_consensus_u32(0),
+            witness: Witness::from_consensus_stack([vec![0u8; 64]]),
+        };
+        let input = txin.consensus_serialize().len() * 4 + txin.witness.consensus_serialize().len();
+        let output = TxOut::new(ScriptPubkey::op_return(&[0u8; 32]), 0u64);
+        let output = output.consensus_serialize().len() * 4;
+        assert_eq!((input, output), (230, 172));
+
+        let mut transition = Transition::strict_dumb();
+        assert_eq!(transition.estimated_commitment_weight(Method::OpretFirst), output);
+        assert_eq!(transition.estimated_commitment_weight(Method::TapretFirst), 32);
+
+        let witness_seal = |vout: u32| {
+            XChain::Bitcoin(GraphSeal::with_blinded_vout(Method::TapretFirst, vout, 0))
+        };
+        let other_seal = GraphSeal::with_blinding(Method::TapretFirst, Txid::from([2; 32]), 0, 0);
+        let other_seal = XChain::Bitcoin(other_seal);
+        let rights = vec![
+            Assign::revealed(witness_seal(0), VoidState::default()),
+            Assign::revealed(witness_seal(1), VoidState::default()),
+            Assign::revealed(other_seal, VoidState::default()),
+            Assign::ConfidentialSeal {
+                seal: witness_seal(2).conceal(),
+                state: VoidState::default(),
+            },
+        ];
+        let data = RevealedData::with_salt(DataState::try_from_slice(&[1]).unwrap(), 0);
+        transition.assignments = Assignments::from(Confined::from_collection_unsafe(bmap! {
+            AssignmentType::with(1) => TypedAssigns::Declarative(
+                Confined::from_collection_unsafe(rights)
+            ),
+            AssignmentType::with(2) => TypedAssigns::Structured(Confined::from_collection_unsafe(
+                vec![Assign::revealed(witness_seal(1), data)]
+            )),
+        }));
+        transition.inputs = Inputs::from(Confined::from_collection_unsafe(bset![
+            Input::with(Opout::new(OpId::from([1; 32]), AssignmentType::with(1), 0)),
+            Input::with(Opout::new(OpId::from([1; 32]), AssignmentType::with(1), 1)),
+        ]));
+        // Two witness outputs, one of which is shared, and one concealed seal
+        // are counted as three outputs
+        assert_eq!(
+            transition.estimated_commitment_weight(Method::OpretFirst),
+            2 * input + 3 * output + output
+        );
+        assert_eq!(
+            transition.estimated_commitment_weight(Method::TapretFirst),
+            2 * input + 3 * output + 32
+        );
+    }
+
+    #[test]
+    fn cached_id() {
+        let mut transition = Transition::strict_dumb();
+        transition.transition_type = TransitionType::with(1);
+        let cached = OpIdCache::new(transition.clone());
+        assert_eq!(cached.id(), transition.id());
+        assert_eq!(cached.contract_id(), transition.contract_id());
+        assert_eq!(cached.transition_type, TransitionType::with(1));
+        assert_eq!(OpRef::from(&*cached).id(), cached.id());
+
+        let mut transition = cached.into_inner();
+        transition.transition_type = TransitionType::with(2);
+        assert_eq!(OpIdCache::from(transition.clone()).id(), transition.id());
+    }
+
+    #[test]
+    fn commit_into_buffer() {
+        let id = |tag: [u8; 32], buf: &[u8]| {
+            let mut engine = Sha256::from_tag(tag);
+            engine.input_raw(buf);
+            OpId::from(engine.finish())
+        };
+        let mut buf = Vec::with_capacity(1024);
+
+        let genesis = Genesis::strict_dumb();
+        genesis.commit_into(&mut buf);
+        assert_eq!(id(Genesis::TAG, &buf), genesis.id());
+
+        let transition = Transition::strict_dumb();
+        transition.commit_into(&mut buf);
+        assert_eq!(id(Transition::TAG, &buf), transition.id());
+        assert!(buf.capacity() >= 1024);
+
+        let extension = Extension::strict_dumb();
+        extension.commit_into(&mut buf);
+        assert_eq!(id(Extension::TAG, &buf), extension.id());
+    }
+
+    #[test]
+    fn op_ref_display() {
+        let genesis = Genesis::strict_dumb();
+        let transition = Transition::strict_dumb();
+        let extension = Extension::strict_dumb();
+        assert_eq!(OpRef::from(&genesis).to_string(), format!("genesis:{}", genesis.id()));
+        assert_eq!(OpRef::from(&transition).to_string(), format!("transition:{}", transition.id()));
+        assert_eq!(OpRef::from(&extension).to_string(), format!("extension:{}", extension.id()));
+    }
+
+    #[test]
+    fn verify_contract_id() {
+        let genesis = Genesis::strict_dumb();
+        let contract_id = genesis.contract_id();
+        assert_eq!(genesis.verify_contract_id(contract_id), Ok(()));
+        let claimed = ContractId::from([1u8; 32]);
+        assert_eq!(
+            genesis.verify_contract_id(claimed),
+            Err(ContractIdMismatch {
+                claimed,
+                found: contract_id
+            })
+        );
+    }
 
     #[test]
     fn contract_id_display() {
@@ -673,5 +1416,287 @@ mod test {
             ContractId::from_str("rgb:pkXwpsb-aemTWhtSg-VDGF25hEi-jtTAnPjzh-B63ZwSehEWvfhF9")
                 .is_err()
         );
+        // Typos are caught by the checksum
+        assert!(
+            ContractId::from_str("rgb:pkXwpsb-aemTWhtSg-VDGF25hEi-jtTAnPjzh-B63ZwSehE-WvfhF8")
+                .is_err()
+        );
+        assert!(
+            ContractId::from_str("rgb:pkXwpsb-aemTWhtSg-VDGF25hEi-jtTAnPjzh-B63ZwSehF-WvfhF9")
+                .is_err()
+        );
+        assert!(
+            ContractId::from_str("rgb:pkXwpsbaemTWhtSgVDGF25hEijtTAnPjzhB63ZwSehEWvfhF8").is_err()
+        );
+        // Wrong human-readable identifier
+        assert!(
+            ContractId::from_str("bc:pkXwpsb-aemTWhtSg-VDGF25hEi-jtTAnPjzh-B63ZwSehE-WvfhF9")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn genesis_chain_net() {
+        let mut genesis = Genesis::strict_dumb();
+        genesis.testnet = false;
+        assert!(genesis.supports_chain_net(ChainNet::BitcoinMainnet));
+        assert!(!genesis.supports_chain_net(ChainNet::BitcoinTestnet));
+        assert!(!genesis.supports_chain_net(ChainNet::LiquidMainnet));
+
+        genesis.testnet = true;
+        genesis.alt_layers1 = AltLayer1Set::from(tiny_bset!(AltLayer1::Liquid));
+        assert_eq!(genesis.layers1(), bset![Layer1::Bitcoin, Layer1::Liquid]);
+        assert!(!genesis.supports_chain_net(ChainNet::BitcoinMainnet));
+        assert!(genesis.supports_chain_net(ChainNet::BitcoinSignet));
+        assert!(genesis.supports_chain_net(ChainNet::LiquidTestnet));
+        assert!(!genesis.supports_chain_net(ChainNet::LiquidMainnet));
+    }
+
+    #[test]
+    fn inputs_from_iter() {
+        let opout = |op: u8, no: u16| Opout::new(OpId::from([op; 32]), 1.into(), no);
+        let inputs = [opout(2, 0), opout(1, 1), opout(2, 0), opout(1, 0)]
+            .into_iter()
+            .collect::<Inputs>();
+        assert_eq!(inputs.iter().map(|i| i.prev_out).collect::<Vec<_>>(), vec![
+            opout(1, 0),
+            opout(1, 1),
+            opout(2, 0)
+        ]);
+        assert!(Inputs::try_from_iter((0..=255).map(|no| opout(1, no))).is_err());
+
+        let valency = |ty: u16, op: u8| (ty.into(), OpId::from([op; 32]));
+        let redeemed = [valency(2, 1), valency(1, 2), valency(2, 3)]
+            .into_iter()
+            .collect::<Redeemed>();
+        assert_eq!(
+            redeemed.iter().map(|(ty, op)| (*ty, *op)).collect::<Vec<_>>(),
+            vec![valency(1, 2), valency(2, 3)]
+        );
+    }
+
+    #[test]
+    fn inputs_canonical_order() {
+        let input = |op: u8, no: u16| Input::with(Opout::new(OpId::from([op; 32]), 1.into(), no));
+        let inputs = Inputs::from(tiny_bset![input(2, 0), input(1, 1), input(1, 0)]);
+        assert_eq!(inputs.iter().copied().collect::<Vec<_>>(), vec![
+            input(1, 0),
+            input(1, 1),
+            input(2, 0)
+        ]);
+
+        let mut data = inputs
+            .strict_encode(StrictWriter::in_memory(u16::MAX as usize))
+            .unwrap()
+            .unbox();
+        let decode = |data: &[u8]| {
+            Inputs::strict_decode(&mut StrictReader::with(data.len(), io::Cursor::new(data)))
+        };
+        assert_eq!(decode(&data).unwrap(), inputs);
+
+        // Swap first two inputs, breaking the canonical order
+        let len = (data.len() - 1) / 3;
+        let (first, rest) = data[1..].split_at_mut(len);
+        first.swap_with_slice(&mut rest[..len]);
+        assert!(matches!(decode(&data), Err(DecodeError::BrokenSetOrder)));
+    }
+
+    #[test]
+    fn opout_resolution() {
+        let seal = XChain::Bitcoin(GraphSeal::with_blinded_vout(Method::OpretFirst, 0, 0));
+        let data = RevealedData::with_salt(small_vec!(1), 0);
+        let mut transition = Transition::strict_dumb();
+        transition.assignments = Assignments::from(Confined::from_collection_unsafe(bmap! {
+            AssignmentType::with(1) => TypedAssigns::Declarative(Confined::from_collection_unsafe(
+                vec![Assign::revealed(seal, VoidState::default())],
+            )),
+            AssignmentType::with(2) => TypedAssigns::Structured(Confined::from_collection_unsafe(
+                vec![
+                    Assign::ConfidentialSeal {
+                        seal: seal.conceal(),
+                        state: data.clone(),
+                    },
+                    Assign::ConfidentialState {
+                        seal,
+                        state: data.conceal(),
+                    },
+                ],
+            )),
+        }));
+        let op = OpRef::Transition(&transition);
+        let opout = |ty: u16, no: u16| Opout::new(transition.id(), ty.into(), no);
+
+        assert_eq!(
+            op.assignment_at(1.into(), 0),
+            Ok(AssignmentRef {
+                seal: Some(seal),
+                state: StateData::Void
+            })
+        );
+        assert_eq!(
+            op.assignment_at(2.into(), 0),
+            Ok(AssignmentRef {
+                seal: None,
+                state: StateData::Structured(data)
+            })
+        );
+        assert_eq!(op.assignment_at(2.into(), 1), Err(OpoutResolveError::Concealed(opout(2, 1))));
+        assert_eq!(
+            op.assignment_at(1.into(), 1),
+            Err(OpoutResolveError::OutOfRange(opout(1, 1), 1))
+        );
+        assert_eq!(op.assignment_at(3.into(), 0), Err(OpoutResolveError::UnknownType(opout(3, 0))));
+    }
+
+    #[test]
+    fn genesis_creation_order() {
+        let genesis = |timestamp: i64| {
+            let mut genesis = Genesis::strict_dumb();
+            genesis.timestamp = timestamp;
+            genesis
+        };
+        assert_eq!(genesis(0).timestamp(), None);
+        assert_eq!(genesis(1700000000).timestamp(), Some(1700000000));
+
+        let mut contracts = [genesis(0), genesis(1700000002), genesis(1700000001)];
+        contracts.sort_by(Genesis::cmp_by_creation);
+        assert_eq!(
+            contracts.iter().map(Genesis::timestamp).collect::<Vec<_>>(),
+            vec![Some(1700000001), Some(1700000002), None]
+        );
+        assert_eq!(genesis(1).cmp_by_creation(&genesis(1)), Ordering::Equal);
+    }
+
+    #[test]
+    fn initial_assignments() {
+        let seal = |vout: u32| {
+            XChain::Bitcoin(GenesisSeal::with_blinding(
+                Method::TapretFirst,
+                Txid::from([0xA5; 32]),
+                vout,
+                0,
+            ))
+        };
+        let data = RevealedData::with_salt(small_vec!(1), 0);
+        let mut genesis = Genesis::strict_dumb();
+        genesis.assignments = Assignments::from(Confined::from_collection_unsafe(bmap! {
+            AssignmentType::with(1) => TypedAssigns::Declarative(Confined::from_collection_unsafe(
+                vec![
+                    Assign::ConfidentialSeal {
+                        seal: seal(0).conceal(),
+                        state: VoidState::default(),
+                    },
+                    Assign::revealed(seal(1), VoidState::default()),
+                ],
+            )),
+            AssignmentType::with(2) => TypedAssigns::Structured(Confined::from_collection_unsafe(
+                vec![Assign::revealed(seal(2), data.clone())],
+            )),
+        }));
+
+        let opid = genesis.id();
+        let output = |vout: u32| seal(vout).to_output_seal().unwrap();
+        let data = StateData::Structured(data);
+        let assignments = genesis.initial_assignments();
+        assert_eq!(
+            assignments
+                .iter()
+                .map(|(seal, a)| (*seal, a.opout, a.state.clone()))
+                .collect::<Vec<_>>(),
+            vec![
+                (output(1), Opout::new(opid, AssignmentType::with(1), 1), StateData::Void),
+                (output(2), Opout::new(opid, AssignmentType::with(2), 0), data),
+            ]
+        );
+        assert!(assignments.iter().all(|(seal, a)| *seal == a.seal));
+        assert!(assignments.iter().all(|(_, a)| a.op_type == OpType::Genesis));
+    }
+
+    #[test]
+    fn commitment_tags() {
+        let tags = [
+            Genesis::TAG.to_vec(),
+            Transition::TAG.to_vec(),
+            Extension::TAG.to_vec(),
+            TransitionBundle::TAG.to_vec(),
+            SubSchema::TAG.to_vec(),
+            <AssignRights<GenesisSeal> as CommitmentId>::TAG.to_vec(),
+            <GenesisSeal as CommitmentId>::TAG.to_vec(),
+            BlindingFactor::TAG.to_vec(),
+            XOutputSeal::TAG.to_vec(),
+        ];
+        assert_eq!(tags.iter().collect::<BTreeSet<_>>().len(), tags.len());
+
+        // Concealed structured and attachment state share the strict encoding
+        // tag, but are distinguished by the state commitment variant
+        let hash = Bytes32::from([1u8; 32]);
+        let commit = |state: StateCommitment| {
+            let mut buf = vec![];
+            state.commit_encode(&mut buf);
+            buf
+        };
+        assert_ne!(
+            commit(StateCommitment::Structured(ConcealedData::from(hash))),
+            commit(StateCommitment::Attachment(ConcealedAttach::from(hash)))
+        );
+    }
+
+    #[test]
+    fn multi_hop_redemptions() {
+        let valencies = |ty: u16| Valencies::from_inner(tiny_bset![schema::ValencyType::with(ty)]);
+        let redeeming = |valencies: Valencies, redeemed: &[(u16, OpId)]| Extension {
+            redeemed: redeemed.iter().map(|(ty, id)| ((*ty).into(), *id)).collect(),
+            valencies,
+            ..Extension::strict_dumb()
+        };
+        fn resolver<'op>(ops: &[(OpId, OpRef<'op>)]) -> impl Fn(OpId) -> Option<OpRef<'op>> {
+            let ops = ops.iter().copied().collect::<BTreeMap<_, _>>();
+            move |id| ops.get(&id).copied()
+        }
+
+        let genesis = Genesis {
+            valencies: valencies(1),
+            ..Genesis::strict_dumb()
+        };
+        let first = redeeming(valencies(2), &[(1, genesis.id())]);
+        let ops = [
+            (genesis.id(), OpRef::Genesis(&genesis)),
+            (first.id(), OpRef::Extension(&first)),
+        ];
+
+        let second = redeeming(valencies(3), &[(2, first.id())]);
+        assert_eq!(second.check_redemptions(resolver(&ops)), Ok(()));
+        let both = redeeming(valencies(3), &[(1, genesis.id()), (2, first.id())]);
+        assert_eq!(both.check_redemptions(resolver(&ops)), Ok(()));
+
+        // The break is reported on the second hop of the chain
+
+        let err = second.check_redemptions(resolver(&ops[1..])).unwrap_err();
+        assert_eq!(err, RedemptionError::UnknownParent {
+            opid: first.id(),
+            prev_id: genesis.id(),
+            valency: 1.into(),
+        });
+
+        let undefined = redeeming(valencies(4), &[(3, first.id())]);
+        let err = undefined.check_redemptions(resolver(&ops)).unwrap_err();
+        assert_eq!(err, RedemptionError::UndefinedValency {
+            opid: undefined.id(),
+            prev_id: first.id(),
+            valency: 3.into(),
+        });
+
+        // A resolver not matching operations to their ids may produce a cycle
+        let fake_id = OpId::from([0xA5; 32]);
+        let cyclic = redeeming(valencies(2), &[(1, fake_id)]);
+        let parent = redeeming(valencies(1), &[(2, cyclic.id())]);
+        let ops = [(fake_id, OpRef::Extension(&parent)), (cyclic.id(), OpRef::Extension(&cyclic))];
+        let err = cyclic.check_redemptions(resolver(&ops)).unwrap_err();
+        assert_eq!(
+            err,
+            RedemptionError::Cycle(CycleError {
+                cycle: vec![cyclic.id(), fake_id]
+            })
+        );
     }
 }
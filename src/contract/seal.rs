@@ -32,11 +32,11 @@ pub use bp::seals::txout::TxoSeal;
 use bp::seals::txout::{BlindSeal, CloseMethod, ExplicitSeal, SealTxid, VerifyError, Witness};
 pub use bp::seals::SecretSeal;
 use bp::{dbc, Outpoint, Tx, Txid, Vout};
-use commit_verify::{mpc, strategies, CommitEncode, CommitStrategy, Conceal};
+use commit_verify::{mpc, strategies, CommitEncode, CommitStrategy, Conceal, DigestExt, Sha256};
 use single_use_seals::SealWitness;
 use strict_encoding::{StrictDecode, StrictDumb, StrictEncode, StrictType};
 
-use crate::{XChain, LIB_NAME_RGB};
+use crate::{Layer1, XChain, LIB_NAME_RGB};
 
 pub type GenesisSeal = SingleBlindSeal<Method>;
 pub type GraphSeal = ChainBlindSeal<Method>;
@@ -117,6 +117,16 @@ impl<Seal: TxoSeal> TxoSeal for XChain<Seal> {
 pub struct SealPreimage(Bytes32);
  */
 
+impl WitnessId {
+    /// Constructs witness id from a transaction id on a given layer 1.
+    #[inline]
+    pub fn from_txid(layer1: Layer1, txid: Txid) -> Self { XChain::with(layer1, txid) }
+
+    /// Returns transaction id of the witness, dropping layer 1 information.
+    #[inline]
+    pub fn as_txid(&self) -> Txid { *self.as_reduced_unsafe() }
+}
+
 impl XChain<GenesisSeal> {
     pub fn transmutate(self) -> XChain<GraphSeal> { self.map(|seal| seal.transmutate()) }
 }
@@ -159,6 +169,26 @@ impl<U: ExposedSeal> XChain<U> {
     }
 }
 
+impl XOutputSeal {
+    /// Tag of the hash used by [`XOutputSeal::index_key`].
+    pub const TAG: [u8; 32] = *b"urn:lnpbp:rgb:seal-index:v1#2024";
+
+    /// Computes stable key for indexing the seal in a database.
+    ///
+    /// The key is a tagged SHA-256 hash (with [`XOutputSeal::TAG`])
+    /// of the layer 1 byte, the transaction id and the little-endian output
+    /// number. The close method is not hashed, since the key identifies the
+    /// transaction output, which can't be closed twice.
+    pub fn index_key(&self) -> [u8; 32] {
+        let seal = self.as_reduced_unsafe();
+        let mut hasher = Sha256::from_tag(Self::TAG);
+        hasher.input_raw(&[self.layer1() as u8]);
+        hasher.input_raw(seal.txid.as_ref().as_slice());
+        hasher.input_raw(&seal.vout.to_u32().to_le_bytes());
+        hasher.finish()
+    }
+}
+
 #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Display)]
 #[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
 #[strict_type(lib = LIB_NAME_RGB)]
@@ -196,28 +226,94 @@ impl Ord for WitnessPos {
 /// transaction defining the ordering of the contract state data.
 #[derive(Copy, Clone, PartialOrd, Ord, PartialEq, Eq, Hash, Debug, Display, From)]
 #[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
-#[strict_type(lib = LIB_NAME_RGB, tags = order)]
+#[strict_type(lib = LIB_NAME_RGB, tags = custom)]
 #[cfg_attr(
     feature = "serde",
     derive(Serialize, Deserialize),
     serde(crate = "serde_crate", rename_all = "camelCase")
 )]
 pub enum WitnessOrd {
+    // Tags are part of the persisted state encoding and must never change
     #[from]
     #[display(inner)]
+    #[strict_type(tag = 0x00)]
     OnChain(WitnessPos),
 
     #[display("offchain")]
-    #[strict_type(dumb)]
+    #[strict_type(tag = 0x01, dumb)]
     OffChain,
 }
 
 impl WitnessOrd {
+    /// Number of confirmations after which a witness transaction is commonly
+    /// considered safe against chain re-organizations.
+    pub const DEFAULT_FINALITY_DEPTH: u32 = 6;
+
     pub fn with_mempool_or_height(height: u32, timestamp: i64) -> Self {
         WitnessPos::new(height, timestamp)
             .map(WitnessOrd::OnChain)
             .unwrap_or(WitnessOrd::OffChain)
     }
+
+    /// Classifies the witness by its finality for a given height of the most
+    /// recent block (`tip_height`). Witnesses having at least `final_depth`
+    /// confirmations are considered final; mined witnesses with a smaller
+    /// number of confirmations are tentative.
+    ///
+    /// Zero `final_depth` is treated as a single confirmation: a witness is
+    /// never final before it is mined, and mined witnesses above the chain tip
+    /// (having zero confirmations) stay tentative.
+    pub fn finality(&self, tip_height: u32, final_depth: u32) -> WitnessFinality {
+        let final_depth = final_depth.max(1);
+        match self {
+            WitnessOrd::OffChain => WitnessFinality::OffChain,
+            WitnessOrd::OnChain(pos) => {
+                let confirmations = tip_height
+                    .checked_sub(pos.height)
+                    .map(|depth| depth + 1)
+                    .unwrap_or_default();
+                if confirmations >= final_depth {
+                    WitnessFinality::Final
+                } else {
+                    WitnessFinality::Tentative(confirmations)
+                }
+            }
+        }
+    }
+}
+
+/// Finality of a witness transaction, computed with [`WitnessOrd::finality`].
+///
+/// The variants are ordered according to the progress of the witness
+/// confirmation: off-chain witnesses go first, followed by the tentative
+/// ones (ordered by the number of confirmations), followed by the final ones.
+#[derive(Copy, Clone, PartialOrd, Ord, PartialEq, Eq, Hash, Debug, Display)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub enum WitnessFinality {
+    /// Witness is not mined.
+    #[display("offchain")]
+    OffChain,
+
+    /// Witness is mined, but has not yet reached a re-organization safe depth;
+    /// contains the number of confirmations (which is zero if the witness is
+    /// above the current chain tip).
+    #[display("tentative({0})")]
+    Tentative(u32),
+
+    /// Witness is mined and buried in the blockchain deep enough to be
+    /// considered final.
+    #[display("final")]
+    Final,
+}
+
+impl WitnessFinality {
+    pub fn is_mined(self) -> bool { self != WitnessFinality::OffChain }
+
+    pub fn is_final(self) -> bool { self == WitnessFinality::Final }
 }
 
 pub type XPubWitness = XChain<Tx>;
@@ -285,10 +381,44 @@ impl CommitEncode for XChain<SecretSeal> {
 
 #[cfg(test)]
 mod test {
+    use std::str::FromStr;
+
     use amplify::hex::FromHex;
     use bp::seals::txout::TxPtr;
+    use strict_encoding::{StrictDecode, StrictDumb, StrictEncode, StrictReader, StrictWriter};
 
     use super::*;
+    use crate::XChainParseError;
+
+    #[test]
+    fn seal_index_key() {
+        let txid = Txid::from([1u8; 32]);
+        let seal = |method, vout: u32| ExplicitSeal::new(method, Outpoint::new(txid, vout));
+        let key = XChain::Bitcoin(seal(CloseMethod::TapretFirst, 0)).index_key();
+        assert_eq!(key, XChain::Bitcoin(seal(CloseMethod::OpretFirst, 0)).index_key());
+        assert_ne!(key, XChain::Bitcoin(seal(CloseMethod::TapretFirst, 1)).index_key());
+        assert_ne!(key, XChain::Liquid(seal(CloseMethod::TapretFirst, 0)).index_key());
+        let other = ExplicitSeal::new(CloseMethod::TapretFirst, Outpoint::new([2u8; 32].into(), 0));
+        assert_ne!(key, XChain::Bitcoin(other).index_key());
+    }
+
+    #[test]
+    fn witness_ord_encoding() {
+        let encode = |ord: WitnessOrd| {
+            let mut buf = vec![];
+            ord.strict_encode(StrictWriter::with(u32::MAX as usize, &mut buf)).unwrap();
+            buf
+        };
+        let pos = WitnessPos::new(0x0C_3500, 0x64C5_2A80).unwrap();
+        assert_eq!(encode(WitnessOrd::OnChain(pos)), vec![
+            0x00, 0x00, 0x35, 0x0C, 0x00, 0x80, 0x2A, 0xC5, 0x64, 0x00, 0x00, 0x00, 0x00
+        ]);
+        assert_eq!(encode(WitnessOrd::OffChain), vec![0x01]);
+        assert_eq!(WitnessOrd::strict_dumb(), WitnessOrd::OffChain);
+
+        let mut reader = StrictReader::in_memory(encode(WitnessOrd::OnChain(pos)), 0xFF);
+        assert_eq!(WitnessOrd::strict_decode(&mut reader).unwrap(), WitnessOrd::OnChain(pos));
+    }
 
     #[test]
     fn secret_seal_is_sha256d() {
@@ -308,4 +438,56 @@ mod test {
         );
         assert_eq!(reveal.to_secret_seal(), reveal.conceal())
     }
+
+    #[test]
+    fn witness_id_txid() {
+        let txid = Txid::from([0x11; 32]);
+        let witness_id = WitnessId::from_txid(Layer1::Liquid, txid);
+        assert_eq!(witness_id, XChain::Liquid(txid));
+        assert_eq!(witness_id.layer1(), Layer1::Liquid);
+        assert_eq!(witness_id.as_txid(), txid);
+        assert_eq!(WitnessId::from_txid(Layer1::Bitcoin, txid), XChain::Bitcoin(txid));
+    }
+
+    #[test]
+    fn witness_finality() {
+        let depth = WitnessOrd::DEFAULT_FINALITY_DEPTH;
+        let ord = WitnessOrd::with_mempool_or_height(100, 1231006505);
+        assert_eq!(WitnessOrd::OffChain.finality(100, depth), WitnessFinality::OffChain);
+        assert_eq!(ord.finality(99, depth), WitnessFinality::Tentative(0));
+        assert_eq!(ord.finality(100, depth), WitnessFinality::Tentative(1));
+        assert_eq!(ord.finality(104, depth), WitnessFinality::Tentative(5));
+        assert_eq!(ord.finality(105, depth), WitnessFinality::Final);
+        assert_eq!(ord.finality(100, 1), WitnessFinality::Final);
+        assert_eq!(ord.finality(100, 0), WitnessFinality::Final);
+        assert_eq!(ord.finality(99, 0), WitnessFinality::Tentative(0));
+        assert_eq!(WitnessOrd::OffChain.finality(100, 0), WitnessFinality::OffChain);
+        assert_eq!(ord.finality(u32::MAX, depth), WitnessFinality::Final);
+
+        assert!(WitnessFinality::OffChain < WitnessFinality::Tentative(0));
+        assert!(WitnessFinality::Tentative(1) < WitnessFinality::Tentative(2));
+        assert!(WitnessFinality::Tentative(u32::MAX) < WitnessFinality::Final);
+        assert!(!WitnessFinality::OffChain.is_mined());
+        assert!(WitnessFinality::Tentative(0).is_mined());
+        assert!(!WitnessFinality::Tentative(5).is_final());
+        assert!(WitnessFinality::Final.is_final());
+    }
+
+    #[test]
+    fn witness_id_str() {
+        let txid = Txid::from([0xA5; 32]);
+        let hex = "a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5";
+        for (id, s) in [
+            (WitnessId::Bitcoin(txid), format!("bc:{hex}")),
+            (WitnessId::Liquid(txid), format!("lq:{hex}")),
+        ] {
+            assert_eq!(id.to_string(), s);
+            assert_eq!(WitnessId::from_str(&s).unwrap(), id);
+        }
+        assert_eq!(WitnessId::from_str(hex).unwrap(), WitnessId::Bitcoin(txid));
+        assert!(matches!(
+            WitnessId::from_str(&format!("xx:{hex}")),
+            Err(XChainParseError::UnknownPrefix(prefix)) if prefix == "xx"
+        ));
+    }
 }
@@ -23,12 +23,12 @@
 use core::fmt::Debug;
 use core::hash::Hash;
 
-use commit_verify::{CommitEncode, Conceal};
+use commit_verify::{CommitEncode, CommitVerify, Conceal};
 use strict_encoding::{StrictDecode, StrictDumb, StrictEncode};
 
 use crate::{
-    ConcealedAttach, ConcealedData, ConcealedValue, RevealedAttach, RevealedData, RevealedValue,
-    LIB_NAME_RGB,
+    ConcealedAttach, ConcealedData, ConcealedValue, PedersenCommitment, RevealedAttach,
+    RevealedData, RevealedValue, LIB_NAME_RGB,
 };
 
 /// Marker trait for types of state which are just a commitment to the actual
@@ -158,3 +158,55 @@ impl ConfidentialState for StateCommitment {
     }
     fn state_commitment(&self) -> StateCommitment { *self }
 }
+
+impl StateCommitment {
+    /// Checks whether the exposed state is an opening of the commitment, i.e.
+    /// whether concealing the state produces the same commitment.
+    ///
+    /// Unlike the verification methods of the specific state types, the check
+    /// works with any exposed state, so it can be used by generic code which
+    /// doesn't know the concrete type of the state. State of a different type
+    /// never matches the commitment.
+    ///
+    /// For fungible state only the Pedersen commitment is compared: range
+    /// proofs are not committed to and can't be reproduced from the opening,
+    /// thus the state is not concealed but just committed to.
+    pub fn check(&self, state: &impl ExposedState) -> bool {
+        match (self, state.state_data()) {
+            (StateCommitment::Fungible(concealed), StateData::Fungible(value)) => {
+                concealed.commitment == PedersenCommitment::commit(&value)
+            }
+            (_, StateData::Fungible(_)) => false,
+            (commitment, data) => *commitment == data.conceal(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{AssetTag, BlindingFactor, VoidState};
+
+    #[test]
+    fn commitment_check() {
+        let value = |amount| {
+            RevealedValue::with_blinding(amount, BlindingFactor::EMPTY, AssetTag::from([1u8; 32]))
+        };
+        let other = value(6);
+        let value = value(5);
+        let data = RevealedData::with_salt(small_vec!(1), 0);
+
+        let commitment = StateData::Fungible(value).conceal();
+        assert!(commitment.check(&value));
+        assert!(commitment.check(&StateData::Fungible(value)));
+        assert!(!commitment.check(&other));
+        assert!(!commitment.check(&data));
+
+        let commitment = data.conceal().state_commitment();
+        assert!(commitment.check(&data));
+        assert!(!commitment.check(&RevealedData::with_salt(small_vec!(1), 1)));
+
+        assert!(StateCommitment::Void.check(&VoidState::default()));
+        assert!(!StateCommitment::Void.check(&data));
+    }
+}
@@ -0,0 +1,439 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2023 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2023 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2023 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Serialization of contract operations with the structured state rendered as
+//! typed values according to the contract schema.
+
+use amplify::hex::ToHex;
+use serde::ser::{
+    Error as _, SerializeMap, SerializeSeq, SerializeStruct, SerializeStructVariant,
+};
+use serde::de::DeserializeOwned;
+use serde::{Serialize, Serializer};
+use strict_types::value::{EnumTag, StrictNum};
+use strict_types::{SemId, StrictVal};
+
+use crate::schema::{GlobalStateType, StateSchema};
+use crate::{
+    Assign, AssignmentType, Assignments, ExposedSeal, GlobalState, OpRef, Operation, RevealedData,
+    SubSchema, TypedAssigns,
+};
+
+/// Serialization wrapper for a contract operation, which renders structured
+/// global and owned state as typed values decoded with the strict types from
+/// the contract schema instead of opaque byte strings.
+///
+/// The operation is serialized with the same fields as by its own serde
+/// implementation; only the structured state values are replaced with their
+/// typed representation. Serialization fails if the state doesn't match the
+/// schema types, which never happens to operations passing the validation.
+#[derive(Copy, Clone, Debug)]
+pub struct TypedOperation<'op> {
+    op: OpRef<'op>,
+    schema: &'op SubSchema,
+}
+
+impl<'op> TypedOperation<'op> {
+    /// Wraps the operation for the serialization with the schema types.
+    pub fn new(op: impl Into<OpRef<'op>>, schema: &'op SubSchema) -> Self {
+        TypedOperation {
+            op: op.into(),
+            schema,
+        }
+    }
+}
+
+impl<'op> Serialize for TypedOperation<'op> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let globals = TypedGlobals(self.op.globals(), self.schema);
+        match self.op {
+            OpRef::Genesis(genesis) => {
+                let mut s = serializer.serialize_struct("Genesis", 9)?;
+                s.serialize_field("ffv", &genesis.ffv)?;
+                s.serialize_field("schemaId", &genesis.schema_id)?;
+                s.serialize_field("timestamp", &genesis.timestamp)?;
+                s.serialize_field("testnet", &genesis.testnet)?;
+                s.serialize_field("altLayers1", &genesis.alt_layers1)?;
+                s.serialize_field("metadata", &genesis.metadata)?;
+                s.serialize_field("globals", &globals)?;
+                let assignments = TypedAssignments(&genesis.assignments, self.schema);
+                s.serialize_field("assignments", &assignments)?;
+                s.serialize_field("valencies", &genesis.valencies)?;
+                s.end()
+            }
+            OpRef::Transition(transition) => {
+                let mut s = serializer.serialize_struct("Transition", 8)?;
+                s.serialize_field("ffv", &transition.ffv)?;
+                s.serialize_field("contractId", &transition.contract_id)?;
+                s.serialize_field("transitionType", &transition.transition_type)?;
+                s.serialize_field("metadata", &transition.metadata)?;
+                s.serialize_field("globals", &globals)?;
+                s.serialize_field("inputs", &transition.inputs)?;
+                let assignments = TypedAssignments(&transition.assignments, self.schema);
+                s.serialize_field("assignments", &assignments)?;
+                s.serialize_field("valencies", &transition.valencies)?;
+                s.end()
+            }
+            OpRef::Extension(extension) => {
+                let mut s = serializer.serialize_struct("Extension", 8)?;
+                s.serialize_field("ffv", &extension.ffv)?;
+                s.serialize_field("contractId", &extension.contract_id)?;
+                s.serialize_field("extensionType", &extension.extension_type)?;
+                s.serialize_field("metadata", &extension.metadata)?;
+                s.serialize_field("globals", &globals)?;
+                let assignments = TypedAssignments(&extension.assignments, self.schema);
+                s.serialize_field("assignments", &assignments)?;
+                s.serialize_field("redeemed", &extension.redeemed)?;
+                s.serialize_field("valencies", &extension.valencies)?;
+                s.end()
+            }
+        }
+    }
+}
+
+struct TypedGlobals<'op>(&'op GlobalState, &'op SubSchema);
+
+impl<'op> Serialize for TypedGlobals<'op> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (ty, values) in self.0.iter() {
+            let sem_id = global_sem_id(*ty, self.1).map_err(S::Error::custom)?;
+            let values = values
+                .iter()
+                .map(|data| TypedData(data, sem_id, self.1))
+                .collect::<Vec<_>>();
+            map.serialize_entry(ty, &values)?;
+        }
+        map.end()
+    }
+}
+
+struct TypedAssignments<'op, Seal: ExposedSeal>(&'op Assignments<Seal>, &'op SubSchema);
+
+impl<'op, Seal> Serialize for TypedAssignments<'op, Seal>
+where Seal: ExposedSeal + Serialize + DeserializeOwned
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (ty, assigns) in self.0.iter() {
+            let TypedAssigns::Structured(assigns) = assigns else {
+                map.serialize_entry(ty, assigns)?;
+                continue;
+            };
+            let sem_id = owned_sem_id(*ty, self.1).map_err(S::Error::custom)?;
+            let assigns = assigns
+                .iter()
+                .map(|assign| TypedAssign(assign, sem_id, self.1))
+                .collect::<Vec<_>>();
+            map.serialize_entry(ty, &Structured(assigns))?;
+        }
+        map.end()
+    }
+}
+
+/// Mirrors the serialization of [`TypedAssigns::Structured`] variant.
+struct Structured<T>(T);
+
+impl<T: Serialize> Serialize for Structured<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_variant("TypedAssigns", 2, "structured", &self.0)
+    }
+}
+
+struct TypedAssign<'op, Seal: ExposedSeal>(
+    &'op Assign<RevealedData, Seal>,
+    SemId,
+    &'op SubSchema,
+);
+
+impl<'op, Seal> Serialize for TypedAssign<'op, Seal>
+where Seal: ExposedSeal + Serialize + DeserializeOwned
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        fn variant<S: Serializer>(
+            serializer: S,
+            index: u32,
+            variant: &'static str,
+            seal: &impl Serialize,
+            state: TypedData,
+        ) -> Result<S::Ok, S::Error> {
+            let mut s = serializer.serialize_struct_variant("Assign", index, variant, 2)?;
+            s.serialize_field("seal", seal)?;
+            s.serialize_field("state", &state)?;
+            s.end()
+        }
+
+        let typed = |state| TypedData(state, self.1, self.2);
+        match self.0 {
+            Assign::Confidential { .. } | Assign::ConfidentialState { .. } => {
+                self.0.serialize(serializer)
+            }
+            Assign::Revealed { seal, state } => {
+                variant(serializer, 1, "revealed", seal, typed(state))
+            }
+            Assign::ConfidentialSeal { seal, state } => {
+                variant(serializer, 2, "confidentialSeal", seal, typed(state))
+            }
+        }
+    }
+}
+
+struct TypedData<'op>(&'op RevealedData, SemId, &'op SubSchema);
+
+impl<'op> Serialize for TypedData<'op> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let value = self
+            .2
+            .type_system
+            .strict_deserialize_type(self.1, self.0.value.as_slice())
+            .map_err(|err| {
+                S::Error::custom(format!("state doesn't match type {}: {err}", self.1))
+            })?;
+        let mut s = serializer.serialize_struct("RevealedData", 2)?;
+        s.serialize_field("value", &Value(value.as_val()))?;
+        s.serialize_field("salt", &self.0.salt)?;
+        s.end()
+    }
+}
+
+/// Serde representation of a strict value: structures become maps, byte
+/// strings are rendered as hex, and enums and unions use their variant names.
+struct Value<'a>(&'a StrictVal);
+
+impl<'a> Serialize for Value<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.0 {
+            StrictVal::Unit => serializer.serialize_unit(),
+            StrictVal::Number(StrictNum::Uint(n)) => match u64::try_from(*n) {
+                Ok(n) => serializer.serialize_u64(n),
+                Err(_) => serializer.serialize_u128(*n),
+            },
+            StrictVal::Number(StrictNum::Int(n)) => match i64::try_from(*n) {
+                Ok(n) => serializer.serialize_i64(n),
+                Err(_) => serializer.serialize_i128(*n),
+            },
+            StrictVal::Number(n) => serializer.collect_str(n),
+            StrictVal::String(s) => serializer.serialize_str(s),
+            StrictVal::Bytes(bytes) => serializer.serialize_str(&bytes.to_hex()),
+            StrictVal::Tuple(fields) if fields.len() == 1 => {
+                Value(&fields[0]).serialize(serializer)
+            }
+            StrictVal::Tuple(items) | StrictVal::List(items) | StrictVal::Set(items) => {
+                let mut seq = serializer.serialize_seq(Some(items.len()))?;
+                for item in items {
+                    seq.serialize_element(&Value(item))?;
+                }
+                seq.end()
+            }
+            StrictVal::Struct(fields) => {
+                let mut map = serializer.serialize_map(Some(fields.len()))?;
+                for (name, val) in fields {
+                    map.serialize_entry(name.as_str(), &Value(val))?;
+                }
+                map.end()
+            }
+            StrictVal::Enum(tag) => Tag(tag).serialize(serializer),
+            StrictVal::Union(tag, val) if **val == StrictVal::Unit => {
+                Tag(tag).serialize(serializer)
+            }
+            StrictVal::Union(tag, val) => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry(&Tag(tag), &Value(val))?;
+                map.end()
+            }
+            StrictVal::Map(items) => {
+                let mut map = serializer.serialize_map(Some(items.len()))?;
+                for (key, val) in items {
+                    map.serialize_entry(&Value(key), &Value(val))?;
+                }
+                map.end()
+            }
+        }
+    }
+}
+
+struct Tag<'a>(&'a EnumTag);
+
+impl<'a> Serialize for Tag<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.0 {
+            EnumTag::Name(name) => serializer.serialize_str(name.as_str()),
+            EnumTag::Ord(ord) => serializer.serialize_u8(*ord),
+        }
+    }
+}
+
+fn global_sem_id(ty: GlobalStateType, schema: &SubSchema) -> Result<SemId, String> {
+    schema
+        .global_types
+        .get(&ty)
+        .map(|schema| schema.sem_id)
+        .ok_or_else(|| format!("global state type {ty} is unknown to the schema"))
+}
+
+fn owned_sem_id(ty: AssignmentType, schema: &SubSchema) -> Result<SemId, String> {
+    match schema.owned_types.get(&ty) {
+        Some(StateSchema::Structured(sem_id)) => Ok(*sem_id),
+        _ => Err(format!("assignment type {ty} is not a structured state type in the schema")),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use amplify::confinement::{Confined, SmallBlob, TinyOrdSet};
+    use amplify::Wrapper;
+    use bp::dbc::Method;
+    use bp::Txid;
+    use commit_verify::Conceal;
+    use strict_encoding::{StrictDumb, StrictSerialize};
+    use strict_types::typelib::LibBuilder;
+    use strict_types::SystemBuilder;
+
+    use super::*;
+    use crate::schema::{GlobalStateSchema, ValencyType};
+    use crate::{
+        Extension, Genesis, GenesisSeal, GraphSeal, Inputs, Opout, Redeemed, Transition,
+        Valencies, VoidState, XChain,
+    };
+
+    #[test]
+    fn typed_state() {
+        #[derive(Clone, Debug)]
+        #[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
+        #[strict_type(lib = "TestLib")]
+        struct Asset {
+            ticker: SmallBlob,
+            supply: u64,
+        }
+        impl StrictSerialize for Asset {}
+
+        let lib = LibBuilder::new(libname!("TestLib"), TinyOrdSet::new())
+            .transpile::<Asset>()
+            .compile()
+            .unwrap();
+        let sys = SystemBuilder::new().import(lib).unwrap().finalize().unwrap();
+        let sem_id = sys.to_sem_id("TestLib.Asset").unwrap();
+
+        let mut schema = SubSchema {
+            type_system: sys.into_type_system(),
+            ..default!()
+        };
+        schema.global_types = Confined::from_collection_unsafe(bmap! {
+            GlobalStateType::with(2000) => GlobalStateSchema::once(sem_id),
+        });
+        schema.owned_types = Confined::from_collection_unsafe(bmap! {
+            AssignmentType::with(1) => StateSchema::Structured(sem_id),
+        });
+
+        let asset = Asset {
+            ticker: SmallBlob::try_from(b"RGB".to_vec()).unwrap(),
+            supply: 1_000_000,
+        };
+        let data = RevealedData::with_salt(asset.to_strict_serialized::<0xFFFF>().unwrap(), 5);
+        let seal = |vout: u32| {
+            XChain::Bitcoin(GenesisSeal::with_blinding(
+                Method::TapretFirst,
+                Txid::from([0xA5; 32]),
+                vout,
+                0,
+            ))
+        };
+        let mut genesis = Genesis::strict_dumb();
+        genesis.globals.add_state(GlobalStateType::with(2000), data.clone()).unwrap();
+        genesis.assignments = Assignments::from(Confined::from_collection_unsafe(bmap! {
+            AssignmentType::with(1) => TypedAssigns::Structured(Confined::from_collection_unsafe(
+                vec![
+                    Assign::revealed(seal(0), data.clone()),
+                    Assign::Confidential {
+                        seal: seal(1).conceal(),
+                        state: data.conceal(),
+                    },
+                ],
+            )),
+        }));
+
+        let json = serde_json::to_value(TypedOperation::new(&genesis, &schema)).unwrap();
+        let plain = serde_json::to_value(&genesis).unwrap();
+        let typed = serde_json::json!({
+            "value": { "ticker": "524742", "supply": 1_000_000 },
+            "salt": 5
+        });
+        assert_eq!(json["globals"]["2000"], serde_json::json!([typed]));
+        let assigns = &json["assignments"]["1"]["structured"];
+        let plain_assigns = &plain["assignments"]["1"]["structured"];
+        assert_eq!(assigns[0]["revealed"]["state"], typed);
+        assert_eq!(assigns[0]["revealed"]["seal"], plain_assigns[0]["revealed"]["seal"]);
+        assert_eq!(assigns[1], plain_assigns[1]);
+        for field in ["ffv", "schemaId", "timestamp", "testnet", "altLayers1", "valencies"] {
+            assert_eq!(json[field], plain[field]);
+        }
+
+        let mut invalid = genesis.clone();
+        let garbage = RevealedData::with_salt(small_vec!(1), 0);
+        invalid.globals.add_state(GlobalStateType::with(2000), garbage).unwrap();
+        assert!(serde_json::to_value(TypedOperation::new(&invalid, &schema)).is_err());
+        schema.global_types.clear();
+        assert!(serde_json::to_value(TypedOperation::new(&genesis, &schema)).is_err());
+    }
+
+    #[test]
+    fn operation_fields() {
+        fn assignments<Seal: ExposedSeal>(seal: Seal) -> Assignments<Seal> {
+            let assign = Assign::revealed(XChain::Bitcoin(seal), VoidState::default());
+            Assignments::from(Confined::from_collection_unsafe(bmap! {
+                AssignmentType::with(1) => TypedAssigns::Declarative(
+                    Confined::from_collection_unsafe(vec![assign]),
+                ),
+            }))
+        }
+
+        // Without structured state the typed serialization must match the
+        // operation's own serialization field by field
+        let schema = SubSchema::strict_dumb();
+        let txid = Txid::from([0xA5; 32]);
+        let mut genesis = Genesis::strict_dumb();
+        let genesis_seal = GenesisSeal::with_blinding(Method::OpretFirst, txid, 0, 0);
+        genesis.assignments = assignments(genesis_seal);
+        genesis.valencies = Valencies::from_inner(tiny_bset![ValencyType::with(1)]);
+        let opout = Opout::new(genesis.id(), AssignmentType::with(1), 0);
+        let seal = GraphSeal::with_blinding(Method::OpretFirst, txid, 0, 0);
+
+        let mut transition = Transition::strict_dumb();
+        transition.inputs = Inputs::from_iter([opout]);
+        transition.assignments = assignments(seal);
+
+        let mut extension = Extension::strict_dumb();
+        extension.redeemed = Redeemed::from_iter([(ValencyType::with(1), genesis.id())]);
+        extension.assignments = assignments(genesis_seal);
+
+        for op in [OpRef::from(&genesis), OpRef::from(&transition), OpRef::from(&extension)] {
+            let json = serde_json::to_value(TypedOperation::new(op, &schema)).unwrap();
+            let plain = match op {
+                OpRef::Genesis(genesis) => serde_json::to_value(genesis),
+                OpRef::Transition(transition) => serde_json::to_value(transition),
+                OpRef::Extension(extension) => serde_json::to_value(extension),
+            }
+            .unwrap();
+            assert_eq!(json, plain);
+        }
+    }
+}
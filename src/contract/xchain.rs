@@ -106,15 +106,22 @@ impl<T: Ord> PartialOrd for XChain<T> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> { Some(self.cmp(other)) }
 }
 
+// Consensus-critical!
+// Sets of cross-chain data (seals, witness ids) are serialized in their
+// order, so the order must be total and deterministic: the data are ordered
+// by the layer 1 first (Bitcoin goes before Liquid, matching the `Layer1`
+// discriminant), and only then by the inner value.
+//
+// Migration note: previously Liquid data went before Bitcoin. Persisted sorted
+// collections keyed by such data are rejected by the strict decoder if their
+// order doesn't match; the only one of them is the global state of the
+// contract history, which is re-sorted when legacy contract state snapshots
+// are read with `ContractState::migrate`.
 impl<T: Ord> Ord for XChain<T> {
     fn cmp(&self, other: &Self) -> Ordering {
-        match (self, other) {
-            (Self::Bitcoin(_), Self::Liquid(_)) => Ordering::Greater,
-            (Self::Liquid(_), Self::Bitcoin(_)) => Ordering::Less,
-            (Self::Bitcoin(t1) | Self::Liquid(t1), Self::Bitcoin(t2) | Self::Liquid(t2)) => {
-                t1.cmp(t2)
-            }
-        }
+        self.layer1()
+            .cmp(&other.layer1())
+            .then_with(|| self.as_reduced_unsafe().cmp(other.as_reduced_unsafe()))
     }
 }
 
@@ -187,6 +194,21 @@ impl<T> XChain<T> {
         }
     }
 
+    /// Constructs cross-chain value from the prefix byte, which tags the layer 1
+    /// in the binary encoding of the cross-chain data.
+    pub fn from_prefix<E: Debug + Display>(
+        prefix: u8,
+        inner: T,
+    ) -> Result<Self, XChainParseError<E>> {
+        let layer1 =
+            Layer1::try_from(prefix).map_err(|_| XChainParseError::UnknownPrefixByte(prefix))?;
+        Ok(XChain::with(layer1, inner))
+    }
+
+    /// Returns prefix byte tagging the layer 1 in the binary encoding of the
+    /// cross-chain data.
+    pub fn prefix(&self) -> u8 { self.layer1() as u8 }
+
     pub fn is_bitcoin(&self) -> bool { matches!(self, XChain::Bitcoin(_)) }
     pub fn is_liquid(&self) -> bool { matches!(self, XChain::Liquid(_)) }
     pub fn is_bp(&self) -> bool {
@@ -218,6 +240,24 @@ impl<T> XChain<T> {
         }
     }
 
+    /// Provides access to the inner value for deployments which operate on
+    /// bitcoin only, returning `None` for the data of other chains.
+    pub fn as_bitcoin(&self) -> Option<&T> {
+        match self {
+            XChain::Bitcoin(t) => Some(t),
+            XChain::Liquid(_) => None,
+        }
+    }
+
+    /// Converts into the inner value for deployments which operate on bitcoin
+    /// only, returning `None` for the data of other chains.
+    pub fn into_bitcoin(self) -> Option<T> {
+        match self {
+            XChain::Bitcoin(t) => Some(t),
+            XChain::Liquid(_) => None,
+        }
+    }
+
     pub fn as_reduced_unsafe(&self) -> &T {
         match self {
             XChain::Bitcoin(t) | XChain::Liquid(t) => t,
@@ -304,6 +344,9 @@ pub enum XChainParseError<E: Debug + Display> {
     #[display("unknown chain prefix '{0}'; only 'bc:' and 'lq:' are currently supported")]
     UnknownPrefix(String),
 
+    #[display("unknown chain prefix byte {0:#04x}")]
+    UnknownPrefixByte(u8),
+
     #[from]
     #[display(inner)]
     Inner(E),
@@ -347,3 +390,47 @@ where T: StrictDumb + StrictEncode + StrictDecode
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn chain_order() {
+        assert!(XChain::Bitcoin(2u8) < XChain::Liquid(1u8));
+        assert!(XChain::Liquid(1u8) > XChain::Bitcoin(2u8));
+        assert!(XChain::Bitcoin(1u8) < XChain::Bitcoin(2u8));
+        assert!(XChain::Liquid(1u8) < XChain::Liquid(2u8));
+        assert_eq!(XChain::Liquid(1u8).cmp(&XChain::Liquid(1u8)), Ordering::Equal);
+
+        let set = bset![XChain::Liquid(0u8), XChain::Bitcoin(3u8), XChain::Bitcoin(1u8)];
+        assert_eq!(set.into_iter().collect::<Vec<_>>(), vec![
+            XChain::Bitcoin(1u8),
+            XChain::Bitcoin(3u8),
+            XChain::Liquid(0u8)
+        ]);
+    }
+
+    #[test]
+    fn from_prefix() {
+        type Res = Result<XChain<u8>, XChainParseError<String>>;
+        for value in [XChain::Bitcoin(1u8), XChain::Liquid(2u8)] {
+            let res: Res = XChain::from_prefix(value.prefix(), *value.as_reduced_unsafe());
+            assert_eq!(res.unwrap(), value);
+        }
+        let res: Res = XChain::from_prefix(2, 1u8);
+        assert!(matches!(res, Err(XChainParseError::UnknownPrefixByte(2))));
+        assert_eq!(res.unwrap_err().to_string(), "unknown chain prefix byte 0x02");
+    }
+
+    #[test]
+    fn bitcoin_access() {
+        let val = XChain::Bitcoin(5u8);
+        assert_eq!(val.as_bitcoin(), Some(&5));
+        assert_eq!(val.into_bitcoin(), Some(5));
+
+        let val = XChain::Liquid(5u8);
+        assert_eq!(val.as_bitcoin(), None);
+        assert_eq!(val.into_bitcoin(), None);
+    }
+}
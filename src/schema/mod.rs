@@ -37,4 +37,14 @@ pub use schema::{
     TransitionType,
 };
 pub use script::{Script, VmType};
-pub use state::{FungibleType, GlobalStateSchema, MediaType, StateSchema};
+pub use state::{FungibleType, GlobalStateSchema, MediaType, StateSchema, UnknownMediaType};
+
+/// Parses schema type identifier either from its hexadecimal representation
+/// prefixed with `0x`, as produced by the `Display` implementation, or from a
+/// decimal number.
+fn parse_type_id(s: &str) -> Result<u16, std::num::ParseIntError> {
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u16::from_str_radix(hex, 16),
+        None => s.parse(),
+    }
+}
@@ -162,6 +162,7 @@ pub struct OccurrencesMismatch {
 }
 
 #[cfg(test)]
+#[allow(clippy::legacy_numeric_constants)]
 mod test {
     use super::Occurrences;
 
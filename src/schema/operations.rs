@@ -20,15 +20,18 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::num::ParseIntError;
+use std::str::FromStr;
+
 use amplify::confinement::{TinyOrdMap, TinyOrdSet};
 use amplify::Wrapper;
 use strict_types::SemId;
 
-use super::{ExtensionType, GlobalStateType, Occurrences, TransitionType};
+use super::{parse_type_id, ExtensionType, GlobalStateType, Occurrences, TransitionType};
 use crate::LIB_NAME_RGB;
 
 #[derive(Wrapper, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, From, Display)]
-#[wrapper(FromStr, LowerHex, UpperHex)]
+#[wrapper(LowerHex, UpperHex)]
 #[display("0x{0:04X}")]
 #[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
 #[strict_type(lib = LIB_NAME_RGB)]
@@ -44,8 +47,13 @@ impl AssignmentType {
     pub fn to_le_bytes(&self) -> [u8; 2] { self.0.to_le_bytes() }
 }
 
+impl FromStr for AssignmentType {
+    type Err = ParseIntError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> { parse_type_id(s).map(Self) }
+}
+
 #[derive(Wrapper, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, From, Display)]
-#[wrapper(FromStr, LowerHex, UpperHex)]
+#[wrapper(LowerHex, UpperHex)]
 #[display("0x{0:04X}")]
 #[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
 #[strict_type(lib = LIB_NAME_RGB)]
@@ -59,12 +67,19 @@ impl ValencyType {
     pub const fn with(ty: u16) -> Self { Self(ty) }
 }
 
+impl FromStr for ValencyType {
+    type Err = ParseIntError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> { parse_type_id(s).map(Self) }
+}
+
 pub type GlobalSchema = TinyOrdMap<GlobalStateType, Occurrences>;
 pub type ValencySchema = TinyOrdSet<ValencyType>;
 pub type InputsSchema = TinyOrdMap<AssignmentType, Occurrences>;
 pub type AssignmentsSchema = TinyOrdMap<AssignmentType, Occurrences>;
 
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Display)]
+#[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB, tags = repr, into_u8, try_from_u8)]
 #[cfg_attr(
     feature = "serde",
     derive(Serialize, Deserialize),
@@ -76,6 +91,7 @@ pub enum OpType {
     /// Genesis: single operation per contract, defining contract and
     /// committing to a specific schema and underlying chain hash
     #[display("genesis")]
+    #[strict_type(dumb)]
     Genesis = 0,
 
     /// Multiple points for decentralized & unowned contract extension,
@@ -22,6 +22,7 @@
 
 use std::cmp::Ordering;
 use std::fmt::{self, Display, Formatter};
+use std::num::ParseIntError;
 use std::str::FromStr;
 
 use amplify::confinement::{TinyOrdMap, TinyOrdSet};
@@ -32,13 +33,13 @@ use strict_encoding::{StrictDecode, StrictDeserialize, StrictEncode, StrictSeria
 use strict_types::TypeSystem;
 
 use super::{
-    AssignmentType, ExtensionSchema, GenesisSchema, Script, StateSchema, TransitionSchema,
-    ValencyType,
+    parse_type_id, AssignmentType, ExtensionSchema, GenesisSchema, Script, StateSchema,
+    TransitionSchema, ValencyType,
 };
 use crate::{Ffv, GlobalStateSchema, Occurrences, LIB_NAME_RGB};
 
 #[derive(Wrapper, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, From, Display)]
-#[wrapper(FromStr, LowerHex, UpperHex)]
+#[wrapper(LowerHex, UpperHex)]
 #[display("0x{0:04X}")]
 #[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
 #[strict_type(lib = LIB_NAME_RGB)]
@@ -52,8 +53,13 @@ impl GlobalStateType {
     pub const fn with(ty: u16) -> Self { Self(ty) }
 }
 
+impl FromStr for GlobalStateType {
+    type Err = ParseIntError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> { parse_type_id(s).map(Self) }
+}
+
 #[derive(Wrapper, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, From, Display)]
-#[wrapper(FromStr, LowerHex, UpperHex)]
+#[wrapper(LowerHex, UpperHex)]
 #[display("0x{0:04X}")]
 #[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
 #[strict_type(lib = LIB_NAME_RGB)]
@@ -68,8 +74,13 @@ impl ExtensionType {
     pub const fn with(ty: u16) -> Self { Self(ty) }
 }
 
+impl FromStr for ExtensionType {
+    type Err = ParseIntError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> { parse_type_id(s).map(Self) }
+}
+
 #[derive(Wrapper, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, From, Display)]
-#[wrapper(FromStr, LowerHex, UpperHex)]
+#[wrapper(LowerHex, UpperHex)]
 #[display("0x{0:04X}")]
 #[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
 #[strict_type(lib = LIB_NAME_RGB)]
@@ -84,6 +95,11 @@ impl TransitionType {
     pub const fn with(ty: u16) -> Self { Self(ty) }
 }
 
+impl FromStr for TransitionType {
+    type Err = ParseIntError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> { parse_type_id(s).map(Self) }
+}
+
 impl TransitionType {
     pub const BLANK: Self = TransitionType(u16::MAX);
 }
@@ -235,4 +251,19 @@ mod test {
         );
         assert_eq!(&format!("{less_dumb:-#}"), "5ffNUk-MTVSnWqu-PLT6xKb7-VmAxUbw8-CUNqCkUW-sZfkwz");
     }
+
+    #[test]
+    fn type_id_parse() {
+        let ty = GlobalStateType::from(2000);
+        assert_eq!(ty.to_string(), "0x07D0");
+        assert_eq!(GlobalStateType::from_str(&ty.to_string()), Ok(ty));
+        assert_eq!(GlobalStateType::from_str("2000"), Ok(ty));
+        assert_eq!(AssignmentType::from_str("0x0fa0"), Ok(AssignmentType::with(4000)));
+        assert_eq!(ValencyType::from_str("1"), Ok(ValencyType::from(1)));
+        assert_eq!(TransitionType::from_str("0xFFFF"), Ok(TransitionType::BLANK));
+        assert_eq!(ExtensionType::from_str("0X0001"), Ok(ExtensionType::with(1)));
+        assert!(AssignmentType::from_str("0x10000").is_err());
+        assert!(AssignmentType::from_str("-1").is_err());
+        assert!(AssignmentType::from_str("").is_err());
+    }
 }
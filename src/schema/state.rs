@@ -20,6 +20,8 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::str::FromStr;
+
 use strict_encoding::constants::U64;
 use strict_types::SemId;
 
@@ -36,18 +38,141 @@ use crate::{StateType, LIB_NAME_RGB};
 #[non_exhaustive]
 #[repr(u8)]
 pub enum MediaType {
+    #[display("text/*")]
+    Text = 0x10,
+    #[display("text/plain")]
+    TextPlain = 0x11,
+    #[display("text/markdown")]
+    TextMarkdown = 0x12,
+    #[display("text/html")]
+    TextHtml = 0x13,
+
+    #[display("image/*")]
+    Image = 0x20,
+    #[display("image/png")]
+    ImagePng = 0x21,
+    #[display("image/jpeg")]
+    ImageJpeg = 0x22,
+    #[display("image/gif")]
+    ImageGif = 0x23,
+    #[display("image/webp")]
+    ImageWebp = 0x24,
+    #[display("image/svg+xml")]
+    ImageSvg = 0x25,
+
+    #[display("audio/*")]
+    Audio = 0x30,
+    #[display("audio/mpeg")]
+    AudioMpeg = 0x31,
+    #[display("audio/ogg")]
+    AudioOgg = 0x32,
+    #[display("audio/wav")]
+    AudioWav = 0x33,
+
+    #[display("video/*")]
+    Video = 0x40,
+    #[display("video/mp4")]
+    VideoMp4 = 0x41,
+    #[display("video/webm")]
+    VideoWebm = 0x42,
+
+    #[display("application/*")]
+    Application = 0x50,
+    #[display("application/json")]
+    ApplicationJson = 0x51,
+    #[display("application/pdf")]
+    ApplicationPdf = 0x52,
+    #[display("application/octet-stream")]
+    ApplicationOctetStream = 0x53,
+
     #[display("*/*")]
     #[strict_type(dumb)]
     Any = 0xFF,
-    // TODO: Complete MIME type implementation
 }
 
 impl MediaType {
-    pub fn conforms(&self, other: &MediaType) -> bool {
-        match (self, other) {
-            (MediaType::Any, MediaType::Any) => true,
+    /// All media types known to RGB.
+    pub const ALL: [MediaType; 22] = [
+        MediaType::Text,
+        MediaType::TextPlain,
+        MediaType::TextMarkdown,
+        MediaType::TextHtml,
+        MediaType::Image,
+        MediaType::ImagePng,
+        MediaType::ImageJpeg,
+        MediaType::ImageGif,
+        MediaType::ImageWebp,
+        MediaType::ImageSvg,
+        MediaType::Audio,
+        MediaType::AudioMpeg,
+        MediaType::AudioOgg,
+        MediaType::AudioWav,
+        MediaType::Video,
+        MediaType::VideoMp4,
+        MediaType::VideoWebm,
+        MediaType::Application,
+        MediaType::ApplicationJson,
+        MediaType::ApplicationPdf,
+        MediaType::ApplicationOctetStream,
+        MediaType::Any,
+    ];
+
+    /// Returns wildcard media type covering the whole category (top-level
+    /// MIME type) of this media type.
+    pub fn category(self) -> MediaType {
+        match self {
+            MediaType::Text |
+            MediaType::TextPlain |
+            MediaType::TextMarkdown |
+            MediaType::TextHtml => MediaType::Text,
+            MediaType::Image |
+            MediaType::ImagePng |
+            MediaType::ImageJpeg |
+            MediaType::ImageGif |
+            MediaType::ImageWebp |
+            MediaType::ImageSvg => MediaType::Image,
+            MediaType::Audio |
+            MediaType::AudioMpeg |
+            MediaType::AudioOgg |
+            MediaType::AudioWav => MediaType::Audio,
+            MediaType::Video | MediaType::VideoMp4 | MediaType::VideoWebm => MediaType::Video,
+            MediaType::Application |
+            MediaType::ApplicationJson |
+            MediaType::ApplicationPdf |
+            MediaType::ApplicationOctetStream => MediaType::Application,
+            MediaType::Any => MediaType::Any,
         }
     }
+
+    /// Detects whether the media type is a wildcard covering a category of
+    /// media types.
+    pub fn is_wildcard(self) -> bool { self.category() == self }
+
+    /// Checks whether the media type conforms to the `other` one, i.e. is
+    /// either equal to it, or `other` is a wildcard covering the category of
+    /// this media type.
+    pub fn conforms(&self, other: &MediaType) -> bool {
+        self == other || *other == MediaType::Any || *other == self.category()
+    }
+}
+
+/// Media type string which doesn't match any of the media types known to RGB.
+#[derive(Clone, PartialEq, Eq, Debug, Display, Error)]
+#[display("unknown media type '{0}'")]
+pub struct UnknownMediaType(String);
+
+impl FromStr for MediaType {
+    type Err = UnknownMediaType;
+
+    /// Parses MIME type string, ignoring the case and the MIME type parameters
+    /// (like `charset`).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mime = mime::Mime::from_str(s).map_err(|_| UnknownMediaType(s.to_owned()))?;
+        MediaType::ALL
+            .into_iter()
+            .find(|media_type| media_type.to_string() == mime.essence_str())
+            .ok_or_else(|| UnknownMediaType(s.to_owned()))
+    }
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
@@ -127,3 +252,36 @@ impl GlobalStateSchema {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn media_type_parse() {
+        for media_type in MediaType::ALL {
+            assert_eq!(MediaType::from_str(&media_type.to_string()), Ok(media_type));
+        }
+        assert_eq!(MediaType::from_str("Image/PNG"), Ok(MediaType::ImagePng));
+        assert_eq!(MediaType::from_str("text/plain; charset=utf-8"), Ok(MediaType::TextPlain));
+        assert_eq!(
+            MediaType::from_str("image/x-unknown"),
+            Err(UnknownMediaType("image/x-unknown".to_owned()))
+        );
+        assert!(MediaType::from_str("").is_err());
+    }
+
+    #[test]
+    fn media_type_conformance() {
+        assert!(MediaType::ImagePng.conforms(&MediaType::ImagePng));
+        assert!(MediaType::ImagePng.conforms(&MediaType::Image));
+        assert!(MediaType::ImagePng.conforms(&MediaType::Any));
+        assert!(MediaType::Any.conforms(&MediaType::Any));
+        assert!(!MediaType::ImagePng.conforms(&MediaType::ImageJpeg));
+        assert!(!MediaType::ImagePng.conforms(&MediaType::Text));
+        assert!(!MediaType::Image.conforms(&MediaType::ImagePng));
+        assert!(!MediaType::Any.conforms(&MediaType::Image));
+        assert!(MediaType::Audio.is_wildcard());
+        assert!(!MediaType::AudioOgg.is_wildcard());
+    }
+}
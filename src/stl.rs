@@ -32,8 +32,9 @@ use crate::{AnchoredBundle, ContractState, Extension, Genesis, SubSchema, LIB_NA
 
 /// Strict types id for the library providing data types for RGB consensus.
 pub const LIB_ID_RGB: &str =
-    "urn:ubideco:stl:141hHBYBr2mzKyskZbRuwazYC9ki5x9ZrrzQHLbgBzx#oscar-rufus-tractor";
+    "urn:ubideco:stl:HFvdsGq2qTyesZ3sJjUXbjTcd7DWaNmovCkbjAkiBRMH#shannon-ammonia-deluxe";
 
+#[allow(clippy::result_large_err)]
 fn _rgb_core_stl() -> Result<TypeLib, CompileError> {
     LibBuilder::new(libname!(LIB_NAME_RGB), tiny_bset! {
         std_stl().to_dependency(),
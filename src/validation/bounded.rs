@@ -0,0 +1,349 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2023 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2023 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2023 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Strict decoding of untrusted data (like consignments received from the
+//! network) with a bounded budget of bytes which can be read and a bounded
+//! depth of nested data structures.
+
+use std::io;
+
+use amplify::num::u24;
+use strict_encoding::{
+    DecodeError, FieldName, ReadStruct, ReadTuple, ReadUnion, StrictDecode, StrictEnum,
+    StrictStruct, StrictSum, StrictTuple, StrictUnion, TypedRead, VariantName,
+};
+
+/// Errors happening during bounded decoding.
+#[derive(Clone, PartialEq, Eq, Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum BoundedDecodeError {
+    /// the data try to read beyond the resource limit of {0} bytes.
+    ResourceLimit(usize),
+
+    /// the data nest structures deeper than the limit of {0} levels.
+    RecursionLimit(usize),
+
+    #[display(inner)]
+    #[from]
+    #[from(io::Error)]
+    Decode(DecodeError),
+}
+
+/// Strict decoder reading no more than a given number of bytes from the
+/// underlying reader.
+///
+/// Unlike a reader limiting the number of actually read bytes, the budget is
+/// checked against the length of collections and byte strings declared in the
+/// data, before the memory for them is allocated. Thus, a malicious
+/// consignment declaring a huge collection length can't force unbounded
+/// allocation. Since any element of a collection takes at least a single
+/// byte, a collection declaring more elements than the remaining budget of
+/// bytes is rejected right away.
+///
+/// The reader also limits the nesting depth of the decoded tuples, structures
+/// and unions, such that data of recursive types can't exhaust the stack.
+#[derive(Clone, Debug)]
+pub struct BoundedReader<R: io::Read> {
+    reader: R,
+    budget: usize,
+    consumed: usize,
+    exceeded: bool,
+    depth: usize,
+    depth_limit: usize,
+    too_deep: bool,
+}
+
+impl<R: io::Read> BoundedReader<R> {
+    /// Default limit for the nesting depth of the decoded data structures.
+    pub const DEFAULT_DEPTH_LIMIT: usize = 64;
+
+    /// Constructs reader which can read up to `budget` bytes, nested no more
+    /// than [`Self::DEFAULT_DEPTH_LIMIT`] levels deep.
+    pub fn with(budget: usize, reader: R) -> Self {
+        BoundedReader {
+            reader,
+            budget,
+            consumed: 0,
+            exceeded: false,
+            depth: 0,
+            depth_limit: Self::DEFAULT_DEPTH_LIMIT,
+            too_deep: false,
+        }
+    }
+
+    /// Sets the limit for the nesting depth of the decoded data structures.
+    pub fn with_depth_limit(mut self, depth_limit: usize) -> Self {
+        self.depth_limit = depth_limit;
+        self
+    }
+
+    /// Returns the limit for the nesting depth of the decoded data
+    /// structures.
+    pub fn depth_limit(&self) -> usize { self.depth_limit }
+
+    /// Returns the total budget of bytes the reader was constructed with.
+    pub fn budget(&self) -> usize { self.budget }
+
+    /// Returns the number of bytes read so far.
+    pub fn consumed(&self) -> usize { self.consumed }
+
+    /// Returns the number of bytes which still can be read.
+    pub fn remaining(&self) -> usize { self.budget - self.consumed }
+
+    /// Releases the underlying reader.
+    pub fn unbox(self) -> R { self.reader }
+
+    /// Decodes the next value from the data, distinguishing budget
+    /// exhaustion from other decoding errors.
+    pub fn decode<T: StrictDecode>(&mut self) -> Result<T, BoundedDecodeError> {
+        T::strict_decode(self).map_err(|err| match (self.too_deep, self.exceeded) {
+            (true, _) => BoundedDecodeError::RecursionLimit(self.depth_limit),
+            (false, true) => BoundedDecodeError::ResourceLimit(self.budget),
+            (false, false) => err.into(),
+        })
+    }
+
+    fn enter(&mut self) -> io::Result<()> {
+        if self.depth >= self.depth_limit {
+            self.too_deep = true;
+            return Err(io::ErrorKind::InvalidInput.into());
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn leave(&mut self) { self.depth -= 1; }
+
+    fn nested<'parent, T>(
+        &'parent mut self,
+        inner: impl FnOnce(&mut FieldReader<'parent, R>) -> Result<T, DecodeError>,
+    ) -> Result<T, DecodeError> {
+        self.enter()?;
+        let mut fields = FieldReader(self);
+        let res = inner(&mut fields);
+        fields.0.leave();
+        res
+    }
+
+    fn claim(&mut self, len: usize) -> io::Result<()> {
+        if len > self.remaining() {
+            self.exceeded = true;
+            return Err(io::ErrorKind::InvalidInput.into());
+        }
+        Ok(())
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        self.claim(buf.len())?;
+        self.reader.read_exact(buf)?;
+        self.consumed += buf.len();
+        Ok(())
+    }
+}
+
+impl<R: io::Read> TypedRead for BoundedReader<R> {
+    type TupleReader<'parent> = FieldReader<'parent, R> where Self: 'parent;
+    type StructReader<'parent> = FieldReader<'parent, R> where Self: 'parent;
+    type UnionReader = Self;
+
+    fn read_union<T: StrictUnion>(
+        &mut self,
+        inner: impl FnOnce(VariantName, &mut Self::UnionReader) -> Result<T, DecodeError>,
+    ) -> Result<T, DecodeError> {
+        let name = T::strict_name().unwrap_or_else(|| tn!("__unnamed"));
+        let tag = u8::strict_decode(self)?;
+        let variant_name = T::variant_name_by_tag(tag)
+            .ok_or(DecodeError::UnionTagNotKnown(name.to_string(), tag))?;
+        self.enter()?;
+        let res = inner(variant_name, self);
+        self.leave();
+        res
+    }
+
+    fn read_enum<T: StrictEnum>(&mut self) -> Result<T, DecodeError>
+    where u8: From<T> {
+        let name = T::strict_name().unwrap_or_else(|| tn!("__unnamed"));
+        let tag = u8::strict_decode(self)?;
+        T::try_from(tag).map_err(|_| DecodeError::EnumTagNotKnown(name.to_string(), tag))
+    }
+
+    fn read_tuple<'parent, 'me, T: StrictTuple>(
+        &'me mut self,
+        inner: impl FnOnce(&mut Self::TupleReader<'parent>) -> Result<T, DecodeError>,
+    ) -> Result<T, DecodeError>
+    where
+        Self: 'parent,
+        'me: 'parent,
+    {
+        self.nested(inner)
+    }
+
+    fn read_struct<'parent, 'me, T: StrictStruct>(
+        &'me mut self,
+        inner: impl FnOnce(&mut Self::StructReader<'parent>) -> Result<T, DecodeError>,
+    ) -> Result<T, DecodeError>
+    where
+        Self: 'parent,
+        'me: 'parent,
+    {
+        self.nested(inner)
+    }
+
+    unsafe fn _read_raw<const MAX_LEN: usize>(&mut self, len: usize) -> io::Result<Vec<u8>> {
+        self.claim(len)?;
+        let mut buf = vec![0u8; len];
+        self.read_exact(&mut buf)?;
+        Ok(buf)
+    }
+
+    unsafe fn _read_raw_array<const LEN: usize>(&mut self) -> io::Result<[u8; LEN]> {
+        let mut buf = [0u8; LEN];
+        self.read_exact(&mut buf)?;
+        Ok(buf)
+    }
+
+    unsafe fn _read_raw_len<const MAX_LEN: usize>(&mut self) -> Result<usize, DecodeError> {
+        let len = match MAX_LEN {
+            tiny if tiny <= u8::MAX as usize => u8::strict_decode(self)? as usize,
+            small if small <= u16::MAX as usize => u16::strict_decode(self)? as usize,
+            medium if medium <= u24::MAX.into_usize() => u24::strict_decode(self)?.into_usize(),
+            large if large <= u32::MAX as usize => u32::strict_decode(self)? as usize,
+            _ => u64::strict_decode(self)? as usize,
+        };
+        self.claim(len)?;
+        Ok(len)
+    }
+}
+
+impl<R: io::Read> ReadUnion for BoundedReader<R> {
+    type TupleReader<'parent> = FieldReader<'parent, R> where Self: 'parent;
+    type StructReader<'parent> = FieldReader<'parent, R> where Self: 'parent;
+
+    fn read_tuple<'parent, 'me, T: StrictSum>(
+        &'me mut self,
+        inner: impl FnOnce(&mut Self::TupleReader<'parent>) -> Result<T, DecodeError>,
+    ) -> Result<T, DecodeError>
+    where
+        Self: 'parent,
+        'me: 'parent,
+    {
+        self.nested(inner)
+    }
+
+    fn read_struct<'parent, 'me, T: StrictSum>(
+        &'me mut self,
+        inner: impl FnOnce(&mut Self::StructReader<'parent>) -> Result<T, DecodeError>,
+    ) -> Result<T, DecodeError>
+    where
+        Self: 'parent,
+        'me: 'parent,
+    {
+        self.nested(inner)
+    }
+}
+
+/// Reader of tuple and struct fields for [`BoundedReader`].
+#[derive(Debug)]
+pub struct FieldReader<'parent, R: io::Read>(&'parent mut BoundedReader<R>);
+
+impl<R: io::Read> ReadTuple for FieldReader<'_, R> {
+    fn read_field<T: StrictDecode>(&mut self) -> Result<T, DecodeError> {
+        T::strict_decode(self.0)
+    }
+}
+
+impl<R: io::Read> ReadStruct for FieldReader<'_, R> {
+    fn read_field<T: StrictDecode>(&mut self, _field: FieldName) -> Result<T, DecodeError> {
+        T::strict_decode(self.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use amplify::confinement::{Confined, SmallBlob, U32};
+    use strict_encoding::{StrictDumb, StrictSerialize};
+
+    use super::*;
+    use crate::Transition;
+
+    #[test]
+    fn bounded_decode() {
+        let transition = Transition::strict_dumb();
+        let data = transition.to_strict_serialized::<U32>().unwrap();
+
+        let mut reader = BoundedReader::with(data.len(), data.as_slice());
+        assert_eq!(reader.decode::<Transition>(), Ok(transition.clone()));
+        assert_eq!(reader.remaining(), 0);
+
+        let mut reader = BoundedReader::with(data.len() - 1, data.as_slice());
+        assert_eq!(
+            reader.decode::<Transition>(),
+            Err(BoundedDecodeError::ResourceLimit(data.len() - 1))
+        );
+
+        let mut reader = BoundedReader::with(data.len() * 2, &data[..data.len() - 1]);
+        assert!(matches!(reader.decode::<Transition>(), Err(BoundedDecodeError::Decode(_))));
+    }
+
+    #[test]
+    fn declared_len_limit() {
+        // Blob declaring 65535 bytes, with only 4 bytes of actual data
+        let data = [0xFF, 0xFF, 1, 2, 3, 4];
+        let mut reader = BoundedReader::with(1024, data.as_slice());
+        assert_eq!(reader.decode::<SmallBlob>(), Err(BoundedDecodeError::ResourceLimit(1024)));
+        assert_eq!(reader.consumed(), 2);
+
+        let data = [4, 0, 1, 2, 3, 4];
+        let mut reader = BoundedReader::with(data.len(), data.as_slice());
+        let blob = Confined::try_from(vec![1u8, 2, 3, 4]).unwrap();
+        assert_eq!(reader.decode::<SmallBlob>(), Ok(blob));
+    }
+
+    #[test]
+    fn depth_limit() {
+        // Each option is a union with a tuple variant, nesting two levels
+        type Nested = Option<Option<Option<u8>>>;
+        let data = [1, 1, 1, 7];
+        let reader = || BoundedReader::with(data.len(), data.as_slice());
+        assert_eq!(reader().decode::<Nested>(), Ok(Some(Some(Some(7)))));
+        assert_eq!(reader().with_depth_limit(6).decode::<Nested>(), Ok(Some(Some(Some(7)))));
+        assert_eq!(
+            reader().with_depth_limit(5).decode::<Nested>(),
+            Err(BoundedDecodeError::RecursionLimit(5))
+        );
+
+        // Depth is released when leaving a nested structure
+        type Pair = (Option<u8>, Option<u8>);
+        let data = [1, 7, 1, 8];
+        let reader = || BoundedReader::with(data.len(), data.as_slice());
+        assert_eq!(reader().with_depth_limit(3).decode::<Pair>(), Ok((Some(7), Some(8))));
+        assert_eq!(
+            reader().with_depth_limit(2).decode::<Pair>(),
+            Err(BoundedDecodeError::RecursionLimit(2))
+        );
+
+        let transition = Transition::strict_dumb();
+        let data = transition.to_strict_serialized::<U32>().unwrap();
+        let mut reader = BoundedReader::with(data.len(), data.as_slice()).with_depth_limit(1);
+        assert_eq!(reader.decode::<Transition>(), Err(BoundedDecodeError::RecursionLimit(1)));
+    }
+}
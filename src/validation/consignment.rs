@@ -28,10 +28,61 @@ use std::collections::{BTreeMap, BTreeSet};
 use std::rc::Rc;
 
 use crate::{
-    AnchoredBundle, AssetTag, AssignmentType, BundleId, Genesis, OpId, OpRef, Operation,
-    SecretSeal, SubSchema, WitnessId, XChain,
+    AnchoredBundle, AssetTag, AssignmentType, BundleId, ContractId, Genesis, OpId, OpRef,
+    Operation, SecretSeal, SubSchema, WitnessId, XChain,
 };
 
+/// Errors detected by [`check_genesis`].
+#[derive(Clone, PartialEq, Eq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum GenesisError {
+    /// consignment doesn't contain contract genesis.
+    Absent,
+
+    /// consignment contains multiple genesis operations {0} and {1}.
+    Duplicate(OpId, OpId),
+
+    /// consignment is claimed to be for contract {expected}, while its genesis
+    /// defines contract {found}.
+    ContractIdMismatch {
+        expected: ContractId,
+        found: ContractId,
+    },
+}
+
+/// Checks that the operations of a consignment contain exactly one genesis,
+/// and that the genesis defines the contract with the `expected` id.
+///
+/// A consignment with more than one genesis could make the validator to track
+/// a wrong root of the contract operation graph, thus the check must be
+/// performed on the consignment import, before the validation.
+///
+/// Returns the genesis if the check succeeds.
+pub fn check_genesis<'op>(
+    expected: ContractId,
+    ops: impl IntoIterator<Item = OpRef<'op>>,
+) -> Result<&'op Genesis, GenesisError> {
+    let mut found = None;
+    for op in ops {
+        let OpRef::Genesis(genesis) = op else {
+            continue;
+        };
+        match found {
+            None => found = Some(genesis),
+            Some(prev) if prev.id() == genesis.id() => {}
+            Some(prev) => return Err(GenesisError::Duplicate(prev.id(), genesis.id())),
+        }
+    }
+    let genesis = found.ok_or(GenesisError::Absent)?;
+    genesis
+        .verify_contract_id(expected)
+        .map_err(|err| GenesisError::ContractIdMismatch {
+            expected: err.claimed,
+            found: err.found,
+        })?;
+    Ok(genesis)
+}
+
 pub struct CheckedConsignment<'consignment, C: ConsignmentApi>(&'consignment C);
 
 impl<'consignment, C: ConsignmentApi> CheckedConsignment<'consignment, C> {
@@ -45,8 +96,11 @@ impl<'consignment, C: ConsignmentApi> ConsignmentApi for CheckedConsignment<'con
 
     fn asset_tags(&self) -> &BTreeMap<AssignmentType, AssetTag> { self.0.asset_tags() }
 
-    fn operation(&self, opid: OpId) -> Option<OpRef> {
-        self.0.operation(opid).filter(|op| op.id() == opid)
+    fn operation(&self, opid: OpId) -> Option<OpRef<'_>> {
+        self.0.operation(opid).filter(|op| {
+            op.id() == opid &&
+                (!matches!(op, OpRef::Genesis(_)) || self.0.genesis().id() == opid)
+        })
     }
 
     fn genesis(&self) -> &Genesis { self.0.genesis() }
@@ -83,7 +137,7 @@ pub trait ConsignmentApi {
 
     /// Retrieves reference to a operation (genesis, state transition or state
     /// extension) matching the provided id, or `None` otherwise
-    fn operation(&self, opid: OpId) -> Option<OpRef>;
+    fn operation(&self, opid: OpId) -> Option<OpRef<'_>>;
 
     /// Contract genesis.
     fn genesis(&self) -> &Genesis;
@@ -108,3 +162,36 @@ pub trait ConsignmentApi {
     /// Returns witness id for a given operation.
     fn op_witness_id(&self, opid: OpId) -> Option<WitnessId>;
 }
+
+#[cfg(test)]
+mod test {
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+
+    #[test]
+    fn genesis_uniqueness() {
+        let genesis = Genesis::strict_dumb();
+        let mut other = Genesis::strict_dumb();
+        other.testnet = !genesis.testnet;
+        let contract_id = genesis.contract_id();
+
+        assert_eq!(check_genesis(contract_id, [OpRef::Genesis(&genesis)]), Ok(&genesis));
+        assert_eq!(
+            check_genesis(contract_id, [OpRef::Genesis(&genesis), OpRef::Genesis(&genesis)]),
+            Ok(&genesis)
+        );
+        assert_eq!(
+            check_genesis(contract_id, [OpRef::Genesis(&genesis), OpRef::Genesis(&other)]),
+            Err(GenesisError::Duplicate(genesis.id(), other.id()))
+        );
+        assert_eq!(
+            check_genesis(other.contract_id(), [OpRef::Genesis(&genesis)]),
+            Err(GenesisError::ContractIdMismatch {
+                expected: other.contract_id(),
+                found: contract_id
+            })
+        );
+        assert_eq!(check_genesis(contract_id, []), Err(GenesisError::Absent));
+    }
+}
@@ -29,9 +29,9 @@ use strict_types::SemId;
 use crate::schema::{AssignmentsSchema, GlobalSchema, ValencySchema};
 use crate::validation::{CheckedConsignment, ConsignmentApi, VirtualMachine};
 use crate::{
-    validation, AssetTag, AssignmentType, Assignments, AssignmentsRef, ContractId, ExposedSeal,
-    GlobalState, GlobalStateSchema, GlobalValues, GraphSeal, Inputs, OpFullType, OpId, OpRef,
-    Operation, Opout, Schema, SchemaRoot, TransitionType, TypedAssigns, Valencies,
+    validation, AssetTag, Assign, AssignmentType, Assignments, AssignmentsRef, ContractId,
+    ExposedSeal, GlobalState, GlobalStateSchema, GlobalValues, GraphSeal, Inputs, OpFullType, OpId,
+    OpRef, Operation, Opout, Schema, SchemaRoot, TransitionType, TypedAssigns, Valencies,
 };
 
 impl<Root: SchemaRoot> Schema<Root> {
@@ -40,6 +40,7 @@ impl<Root: SchemaRoot> Schema<Root> {
         consignment: &'validator CheckedConsignment<'consignment, C>,
         op: OpRef,
         vm: &'consignment dyn VirtualMachine,
+        verify_range_proofs: bool,
     ) -> validation::Status {
         let id = op.id();
 
@@ -155,10 +156,21 @@ impl<Root: SchemaRoot> Schema<Root> {
         }
         status += match op.assignments() {
             AssignmentsRef::Genesis(assignments) => {
-                self.validate_owned_state(id, assignments, assign_schema)
+                self.validate_owned_state(id, assignments, assign_schema, verify_range_proofs)
             }
             AssignmentsRef::Graph(assignments) => {
-                self.validate_owned_state(id, assignments, assign_schema)
+                self.validate_owned_state(id, assignments, assign_schema, verify_range_proofs)
+            }
+        };
+
+        // Previous state is not checked, since it was assigned by other operations
+        // of the same contract and has been checked during their validation
+        status += match op.assignments() {
+            AssignmentsRef::Genesis(assignments) => {
+                validate_asset_tags(id, assignments, consignment.asset_tags())
+            }
+            AssignmentsRef::Graph(assignments) => {
+                validate_asset_tags(id, assignments, consignment.asset_tags())
             }
         };
 
@@ -336,6 +348,7 @@ impl<Root: SchemaRoot> Schema<Root> {
         id: OpId,
         owned_state: &Assignments<Seal>,
         assign_schema: &AssignmentsSchema,
+        range_proofs: bool,
     ) -> validation::Status {
         let mut status = validation::Status::new();
 
@@ -368,19 +381,20 @@ impl<Root: SchemaRoot> Schema<Root> {
                  validation and we would not reach this point",
             );
 
+            let type_system = &self.type_system;
             match owned_state.get(state_id) {
                 None => {}
                 Some(TypedAssigns::Declarative(set)) => set.iter().for_each(|data| {
-                    status += assignment.validate(&self.type_system, &id, *state_id, data)
+                    status += assignment.validate(type_system, &id, *state_id, data, range_proofs)
                 }),
                 Some(TypedAssigns::Fungible(set)) => set.iter().for_each(|data| {
-                    status += assignment.validate(&self.type_system, &id, *state_id, data)
+                    status += assignment.validate(type_system, &id, *state_id, data, range_proofs)
                 }),
                 Some(TypedAssigns::Structured(set)) => set.iter().for_each(|data| {
-                    status += assignment.validate(&self.type_system, &id, *state_id, data)
+                    status += assignment.validate(type_system, &id, *state_id, data, range_proofs)
                 }),
                 Some(TypedAssigns::Attachment(set)) => set.iter().for_each(|data| {
-                    status += assignment.validate(&self.type_system, &id, *state_id, data)
+                    status += assignment.validate(type_system, &id, *state_id, data, range_proofs)
                 }),
             };
         }
@@ -466,6 +480,65 @@ impl<'op> OpInfo<'op> {
     }
 }
 
+/// Checks that the fungible state spent by an operation is balanced with the
+/// fungible state it assigns, i.e. that the Pedersen commitments to `inputs`
+/// and to `outputs` sum up to the same value. Fails if either of the
+/// assignments is not fungible.
+///
+/// This is the check performed by the `PcVs` instruction of the embedded VM,
+/// done directly over the confidential state.
+pub fn verify_fungible_sum<Seal1: ExposedSeal, Seal2: ExposedSeal>(
+    inputs: &TypedAssigns<Seal1>,
+    outputs: &TypedAssigns<Seal2>,
+) -> bool {
+    fn commitments<Seal: ExposedSeal>(
+        assigns: &TypedAssigns<Seal>,
+    ) -> Vec<secp256k1_zkp::PedersenCommitment> {
+        assigns
+            .as_fungible()
+            .iter()
+            .map(Assign::to_confidential_state)
+            .map(|s| s.commitment.into_inner())
+            .collect()
+    }
+    if !inputs.is_fungible() || !outputs.is_fungible() {
+        return false;
+    }
+    secp256k1_zkp::verify_commitments_sum_to_equal(
+        secp256k1_zkp::SECP256K1,
+        &commitments(inputs),
+        &commitments(outputs),
+    )
+}
+
+/// Checks that the revealed fungible state is bound to the asset tags defined
+/// by the contract for its assignment types. Concealed state is bound to the
+/// tag by its Pedersen commitment, such that mixing commitments bound to
+/// different tags fails the commitment sum check.
+fn validate_asset_tags<Seal: ExposedSeal>(
+    id: OpId,
+    owned_state: &Assignments<Seal>,
+    asset_tags: &BTreeMap<AssignmentType, AssetTag>,
+) -> validation::Status {
+    let mut status = validation::Status::new();
+    for (state_type, assignments) in owned_state.iter() {
+        let Some(expected) = asset_tags.get(state_type) else {
+            continue;
+        };
+        for state in assignments.as_fungible().iter().filter_map(|a| a.as_revealed_state()) {
+            if let Err(err) = state.check_asset_tag(*expected) {
+                status.add_failure(validation::Failure::AssetTagMismatch {
+                    opid: id,
+                    state_type: *state_type,
+                    expected: err.expected,
+                    found: err.found,
+                });
+            }
+        }
+    }
+    status
+}
+
 fn extract_prev_state<C: ConsignmentApi>(
     consignment: &C,
     opid: OpId,
@@ -549,3 +622,42 @@ fn extract_prev_state<C: ConsignmentApi>(
         .expect("collections is assembled from another collection with the same size requirements")
         .into()
 }
+
+#[cfg(test)]
+mod test {
+    use amplify::confinement::Confined;
+    use amplify::ByteArray;
+    use bp::dbc::Method;
+
+    use super::*;
+    use crate::{Assign, RevealedValue, XChain};
+
+    #[test]
+    fn asset_tags() {
+        let ty = AssignmentType::with(1);
+        let tag = AssetTag::from_byte_array([1u8; 32]);
+        let other = AssetTag::from_byte_array([2u8; 32]);
+        let seal = XChain::Bitcoin(GraphSeal::with_blinded_vout(Method::OpretFirst, 0, 0));
+        let assignments = |tag| {
+            Assignments::from(Confined::from_collection_unsafe(bmap! {
+                ty => TypedAssigns::Fungible(Confined::from_collection_unsafe(vec![
+                    Assign::revealed(seal, RevealedValue::new_random_blinding(10u64, tag)),
+                ])),
+            }))
+        };
+        let opid = OpId::from([3u8; 32]);
+        let asset_tags = bmap! { ty => tag };
+
+        let status = validate_asset_tags(opid, &assignments(tag), &asset_tags);
+        assert!(status.failures.is_empty());
+        let status = validate_asset_tags(opid, &assignments(other), &asset_tags);
+        assert_eq!(status.failures, vec![validation::Failure::AssetTagMismatch {
+            opid,
+            state_type: ty,
+            expected: tag,
+            found: other,
+        }]);
+        let status = validate_asset_tags(opid, &assignments(other), &bmap! {});
+        assert!(status.failures.is_empty());
+    }
+}
@@ -27,9 +27,14 @@ mod state;
 mod validator;
 mod consignment;
 mod status;
+mod report;
+mod bounded;
 
-pub use consignment::{CheckedConsignment, ConsignmentApi};
+pub use bounded::{BoundedDecodeError, BoundedReader, FieldReader};
+pub use consignment::{check_genesis, CheckedConsignment, ConsignmentApi, GenesisError};
 pub(crate) use logic::OpInfo;
+pub use logic::verify_fungible_sum;
+pub use report::{validate_consignment_structure, ValidationReport};
 pub use script::VirtualMachine;
 pub use status::{Failure, Info, Status, Validity, Warning};
-pub use validator::{ResolveWitness, Validator, WitnessResolverError};
+pub use validator::{RangeProofPolicy, ResolveWitness, Validator, WitnessResolverError};
@@ -0,0 +1,426 @@
+// RGB Core Library: consensus layer for RGB smart contracts.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2023 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2023 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2019-2023 Dr Maxim Orlovsky. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeMap;
+
+use super::status::Failure;
+use super::{Status, Validity};
+use crate::{
+    check_acyclic, AnchorVerifyError, AnchoredBundle, BundleId, ContractId, Extension, Genesis,
+    OpId, OpRef, Operation, OpoutResolveError,
+};
+
+/// Report on the consignment data produced by [`validate_consignment_structure`].
+///
+/// Failures are grouped by the contract operations and transition bundles
+/// they relate to; operations and bundles which have passed all the checks
+/// are present in the report with an empty status.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ValidationReport {
+    /// Id of the validated contract.
+    pub contract_id: ContractId,
+    /// Validation status of each of the contract operations.
+    pub operations: BTreeMap<OpId, Status>,
+    /// Validation status of each of the anchored transition bundles.
+    pub bundles: BTreeMap<BundleId, Status>,
+}
+
+impl ValidationReport {
+    fn new(contract_id: ContractId) -> Self {
+        ValidationReport {
+            contract_id,
+            operations: empty!(),
+            bundles: empty!(),
+        }
+    }
+
+    /// Aggregates statuses of all operations and bundles into a single
+    /// status object.
+    pub fn status(&self) -> Status {
+        let mut status = Status::new();
+        for s in self.operations.values().chain(self.bundles.values()) {
+            status += s.clone();
+        }
+        status
+    }
+
+    pub fn validity(&self) -> Validity { self.status().validity() }
+
+    /// Lists ids of the operations which have failed validation.
+    pub fn failed_operations(&self) -> impl Iterator<Item = OpId> + '_ {
+        self.operations
+            .iter()
+            .filter(|(_, status)| !status.failures.is_empty())
+            .map(|(opid, _)| *opid)
+    }
+
+    /// Lists ids of the bundles which have failed validation.
+    pub fn failed_bundles(&self) -> impl Iterator<Item = BundleId> + '_ {
+        self.bundles
+            .iter()
+            .filter(|(_, status)| !status.failures.is_empty())
+            .map(|(bundle_id, _)| *bundle_id)
+    }
+}
+
+/// Validates structure of the consignment data: genesis, all state
+/// transitions contained in the anchored bundles and state extensions.
+///
+/// The validation is not stopped on the first failure; all detected failures
+/// are collected into the [`ValidationReport`]. The procedure checks that:
+/// - all operations belong to the contract defined by the genesis, are not
+///   present in the consignment more than once and form an acyclic graph;
+/// - each bundle is anchored on one of the layers 1 allowed by the genesis,
+///   and passes [`AnchoredBundle::verify`], which stops on the first failure
+///   of the bundle;
+/// - each of the transition inputs refers to an existing output of an
+///   operation present in the consignment, having revealed seal;
+/// - each of the valencies redeemed by a state extension is defined by an
+///   operation present in the consignment.
+///
+/// The scope is reduced to the checks which don't require the contract
+/// schema or the witness transactions. Thus, state transition balance and
+/// other rules of the schema scripts, schema rules for the state, metadata
+/// and valencies, seal closing and the actual anchor commitments are not
+/// covered: an operation passing this check may still be invalid. The full
+/// rule set is run by [`super::Validator::validate`].
+pub fn validate_consignment_structure(
+    genesis: &Genesis,
+    bundles: &[AnchoredBundle],
+    extensions: &[Extension],
+) -> ValidationReport {
+    let contract_id = genesis.contract_id();
+    let layers1 = genesis.layers1();
+    let mut report = ValidationReport::new(contract_id);
+
+    // Index of all operations present in the consignment
+    let mut ops = bmap! { genesis.id() => OpRef::Genesis(genesis) };
+    let mut duplicates = vec![];
+
+    for anchored_bundle in bundles {
+        let bundle_id = anchored_bundle.bundle_id();
+        let bundle = &anchored_bundle.bundle;
+        let status = report.bundles.entry(bundle_id).or_default();
+
+        // [VALIDATION]: Checking that the anchor layer 1 is allowed by the genesis
+        let layer1 = anchored_bundle.anchor.layer1();
+        if !layers1.contains(&layer1) {
+            status.add_failure(Failure::AnchorLayerMismatch(bundle_id, layer1));
+        }
+
+        // [VALIDATION]: Checking anchor MPC proofs and the bundle commitments to the known
+        //               transitions
+        let witness_id = anchored_bundle.anchor.witness_id_unchecked();
+        match anchored_bundle.verify(contract_id) {
+            Ok(()) => {}
+            Err(AnchorVerifyError::WitnessMismatch(bundle_id)) => {
+                status.add_failure(Failure::AnchorSetInvalid(bundle_id));
+            }
+            Err(AnchorVerifyError::Mpc(bundle_id, err)) => {
+                status.add_failure(Failure::MpcInvalid(bundle_id, witness_id, err));
+            }
+            Err(AnchorVerifyError::DualRootMismatch(bundle_id)) => {
+                status.add_failure(Failure::MpcRootMismatch(bundle_id, witness_id));
+            }
+            Err(AnchorVerifyError::TransitionIdMismatch(bundle_id, opid, id)) => {
+                status.add_failure(Failure::BundleTransitionIdMismatch(bundle_id, opid, id));
+            }
+            // Reported for the operation itself
+            Err(AnchorVerifyError::ContractMismatch(..)) => {}
+            Err(AnchorVerifyError::UncommittedTransition(bundle_id, opid)) => {
+                status.add_failure(Failure::BundleExtraTransition(bundle_id, opid));
+            }
+            Err(AnchorVerifyError::TooLarge(err)) => {
+                status.add_failure(Failure::BundleTooLarge(bundle_id, err.to_string()));
+            }
+        }
+
+        for transition in bundle.known_transitions.values() {
+            if ops.insert(transition.id(), OpRef::Transition(transition)).is_some() {
+                duplicates.push(transition.id());
+            }
+        }
+    }
+    for extension in extensions {
+        if ops.insert(extension.id(), OpRef::Extension(extension)).is_some() {
+            duplicates.push(extension.id());
+        }
+    }
+    for opid in duplicates {
+        report
+            .operations
+            .entry(opid)
+            .or_default()
+            .add_failure(Failure::DuplicateOperation(opid));
+    }
+
+    // [VALIDATION]: Checking that the operations form a directed acyclic graph
+    if let Err(err) = check_acyclic(ops.values().copied()) {
+        for opid in err.cycle {
+            report
+                .operations
+                .entry(opid)
+                .or_default()
+                .add_failure(Failure::CyclicGraph(opid));
+        }
+    }
+
+    for (opid, op) in &ops {
+        let status = report.operations.entry(*opid).or_default();
+
+        // [VALIDATION]: Checking that the operation belongs to the contract
+        if op.contract_id() != contract_id {
+            status.add_failure(Failure::ContractMismatch(*opid, op.contract_id()));
+        }
+
+        match op {
+            OpRef::Genesis(_) => {}
+            // [VALIDATION]: Checking that the transition inputs exist
+            OpRef::Transition(transition) => {
+                for input in &transition.inputs {
+                    let prev_out = input.prev_out;
+                    let Some(prev_op) = ops.get(&prev_out.op) else {
+                        status.add_failure(Failure::OperationAbsent(prev_out.op));
+                        continue;
+                    };
+                    match prev_op.assignment_at(prev_out.ty, prev_out.no) {
+                        Err(OpoutResolveError::UnknownType(_)) |
+                        Err(OpoutResolveError::OutOfRange(..)) => {
+                            status.add_failure(Failure::NoPrevOut(*opid, prev_out));
+                        }
+                        Ok(assignment) if assignment.seal.is_none() => {
+                            status.add_failure(Failure::ConfidentialSeal(prev_out));
+                        }
+                        Ok(_) | Err(OpoutResolveError::Concealed(_)) => {}
+                    }
+                }
+            }
+            // [VALIDATION]: Checking that the redeemed valencies exist
+            OpRef::Extension(extension) => {
+                for (valency, prev_id) in &extension.redeemed {
+                    let Some(prev_op) = ops.get(prev_id) else {
+                        status.add_failure(Failure::ValencyNoParent {
+                            opid: *opid,
+                            prev_id: *prev_id,
+                            valency: *valency,
+                        });
+                        continue;
+                    };
+                    if !prev_op.valencies().contains(valency) {
+                        status.add_failure(Failure::NoPrevValency {
+                            opid: *opid,
+                            prev_id: *prev_id,
+                            valency: *valency,
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod test {
+    use amplify::confinement::Confined;
+    use bp::dbc::{Anchor, Method};
+    use bp::Txid;
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+    use crate::{
+        AltLayer1, AltLayer1Set, AnchorSet, Assign, AssignmentType, Assignments, GenesisSeal,
+        Input, Layer1, Opout, Transition, TransitionBundle, TypedAssigns, VoidState, XAnchor,
+        XChain,
+    };
+
+    fn genesis() -> Genesis {
+        let seal = XChain::Bitcoin(GenesisSeal::with_blinding(
+            Method::TapretFirst,
+            Txid::from([0xA5; 32]),
+            0,
+            0,
+        ));
+        let rights = TypedAssigns::Declarative(Confined::from_collection_unsafe(vec![
+            Assign::revealed(seal, VoidState::default()),
+        ]));
+        let mut genesis = Genesis::strict_dumb();
+        genesis.assignments = Assignments::from(Confined::from_collection_unsafe(bmap! {
+            AssignmentType::with(1) => rights,
+        }));
+        genesis
+    }
+
+    fn transition(contract_id: ContractId, prev_out: Opout) -> Transition {
+        let mut transition = Transition::strict_dumb();
+        transition.contract_id = contract_id;
+        transition.inputs = Confined::from_collection_unsafe(bset![Input::with(prev_out)]).into();
+        transition
+    }
+
+    fn anchored(transitions: impl IntoIterator<Item = Transition>) -> AnchoredBundle {
+        let known_transitions = transitions
+            .into_iter()
+            .map(|t| (t.id(), t))
+            .collect::<BTreeMap<_, _>>();
+        let input_map = known_transitions
+            .keys()
+            .enumerate()
+            .map(|(vin, opid)| ((vin as u32).into(), *opid))
+            .collect::<BTreeMap<_, _>>();
+        AnchoredBundle {
+            anchor: XAnchor::Bitcoin(AnchorSet::Opret(Anchor {
+                txid: Txid::from([3u8; 32]),
+                mpc_proof: strict_dumb!(),
+                dbc_proof: strict_dumb!(),
+                _method: default!(),
+            })),
+            bundle: TransitionBundle {
+                input_map: Confined::from_collection_unsafe(input_map),
+                known_transitions: Confined::from_collection_unsafe(known_transitions),
+            },
+        }
+    }
+
+    #[test]
+    fn valid_consignment() {
+        let genesis = genesis();
+        let contract_id = genesis.contract_id();
+        let genesis_id = genesis.id();
+        let t1 = transition(contract_id, Opout::new(genesis_id, AssignmentType::with(1), 0));
+        let bundle = anchored([t1.clone()]);
+        let bundle_id = bundle.bundle_id();
+
+        let report = validate_consignment_structure(&genesis, &[bundle], &[]);
+        assert_eq!(report.contract_id, contract_id);
+        assert_eq!(report.validity(), Validity::Valid);
+        assert_eq!(report.operations.len(), 2);
+        assert!(report.operations.contains_key(&genesis_id));
+        assert!(report.operations.contains_key(&t1.id()));
+        assert_eq!(report.bundles.len(), 1);
+        assert!(report.bundles.contains_key(&bundle_id));
+    }
+
+    #[test]
+    fn anchor_layers() {
+        let mut genesis = genesis();
+        let t1 = transition(genesis.contract_id(), Opout::new(genesis.id(), 1u16.into(), 0));
+        let mut bundle = anchored([t1]);
+        bundle.anchor = XAnchor::Liquid(bundle.anchor.as_reduced_unsafe().clone());
+        let bundle_id = bundle.bundle_id();
+
+        let report = validate_consignment_structure(&genesis, &[bundle.clone()], &[]);
+        assert_eq!(report.bundles[&bundle_id].failures, vec![Failure::AnchorLayerMismatch(
+            bundle_id,
+            Layer1::Liquid
+        )]);
+
+        genesis.alt_layers1 = AltLayer1Set::from(tiny_bset!(AltLayer1::Liquid));
+        let t1 = transition(genesis.contract_id(), Opout::new(genesis.id(), 1u16.into(), 0));
+        let mut bundle = anchored([t1]);
+        bundle.anchor = XAnchor::Liquid(bundle.anchor.as_reduced_unsafe().clone());
+        let report = validate_consignment_structure(&genesis, &[bundle], &[]);
+        assert_eq!(report.validity(), Validity::Valid);
+    }
+
+    #[test]
+    fn transition_id_mismatch() {
+        let genesis = genesis();
+        let t1 = transition(genesis.contract_id(), Opout::new(genesis.id(), 1u16.into(), 0));
+        let opid = t1.id();
+        let mut bundle = anchored([t1]);
+        bundle
+            .bundle
+            .known_transitions
+            .keyed_values_mut()
+            .for_each(|(_, t)| t.transition_type = 2u16.into());
+        let bundle_id = bundle.bundle_id();
+        let id = bundle.bundle.known_transitions[&opid].id();
+
+        let report = validate_consignment_structure(&genesis, &[bundle], &[]);
+        assert!(report.operations[&id].failures.is_empty());
+        assert_eq!(report.bundles[&bundle_id].failures, vec![
+            Failure::BundleTransitionIdMismatch(bundle_id, opid, id)
+        ]);
+    }
+
+    #[test]
+    fn collects_all_failures() {
+        let genesis = genesis();
+        let contract_id = genesis.contract_id();
+        let genesis_id = genesis.id();
+
+        let wrong_out = Opout::new(genesis_id, AssignmentType::with(1), 5);
+        let t1 = transition(contract_id, wrong_out);
+        let absent = OpId::from([7u8; 32]);
+        let t2 = transition(contract_id, Opout::new(absent, AssignmentType::with(1), 0));
+        let other_id = ContractId::from([1u8; 32]);
+        let t3 = transition(other_id, Opout::new(genesis_id, AssignmentType::with(1), 0));
+
+        let mut extension = Extension::strict_dumb();
+        extension.contract_id = contract_id;
+        extension.redeemed = Confined::from_collection_unsafe(bmap! {
+            1u16.into() => genesis_id,
+            2u16.into() => absent,
+        })
+        .into();
+
+        let bundles = [anchored([t1.clone(), t2.clone()]), anchored([t3.clone()])];
+        let report = validate_consignment_structure(&genesis, &bundles, &[extension.clone()]);
+        assert_eq!(report.validity(), Validity::Invalid);
+        assert_eq!(report.operations.len(), 5);
+        assert_eq!(
+            report.failed_operations().collect::<Vec<_>>().len(),
+            4,
+            "all operations except genesis must fail"
+        );
+        assert!(report.operations[&genesis_id].failures.is_empty());
+        assert_eq!(report.operations[&t1.id()].failures, vec![Failure::NoPrevOut(
+            t1.id(),
+            wrong_out
+        )]);
+        assert_eq!(report.operations[&t2.id()].failures, vec![Failure::OperationAbsent(absent)]);
+        assert_eq!(report.operations[&t3.id()].failures, vec![Failure::ContractMismatch(
+            t3.id(),
+            other_id
+        )]);
+        let opid = extension.id();
+        assert_eq!(report.operations[&opid].failures, vec![
+            Failure::NoPrevValency {
+                opid,
+                prev_id: genesis_id,
+                valency: 1u16.into()
+            },
+            Failure::ValencyNoParent {
+                opid,
+                prev_id: absent,
+                valency: 2u16.into()
+            },
+        ]);
+        assert_eq!(report.status().failures.len(), 5);
+
+        let duplicated = [bundles[0].clone(), bundles[0].clone()];
+        let report = validate_consignment_structure(&genesis, &duplicated, &[]);
+        assert_eq!(report.operations[&t1.id()].failures[0], Failure::DuplicateOperation(t1.id()));
+    }
+}
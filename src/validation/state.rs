@@ -35,12 +35,18 @@ impl StateSchema {
         opid: &OpId,
         state_type: AssignmentType,
         data: &Assign<State, Seal>,
+        verify_range_proof: bool,
     ) -> validation::Status {
         let mut status = validation::Status::new();
         match data {
             Assign::Confidential { state, .. } | Assign::ConfidentialState { state, .. } => {
                 match (self, state.state_commitment()) {
                     (StateSchema::Declarative, StateCommitment::Void) => {}
+                    (StateSchema::Fungible(_), StateCommitment::Fungible(_))
+                        if !verify_range_proof =>
+                    {
+                        status.add_info(validation::Info::RangeProofUnchecked(*opid, state_type));
+                    }
                     (StateSchema::Fungible(_), StateCommitment::Fungible(value)) => {
                         // [SECURITY-CRITICAL]: Bulletproofs validation
                         if let Err(err) = value.verify_range_proof() {
@@ -121,3 +127,45 @@ impl StateSchema {
         status
     }
 }
+
+#[cfg(test)]
+mod test {
+    use amplify::ByteArray;
+    use bp::dbc::Method;
+    use commit_verify::Conceal;
+
+    use super::*;
+    use crate::validation::RangeProofPolicy;
+    use crate::{AssetTag, ContractId, FungibleType, GraphSeal, RevealedValue, XChain};
+
+    #[test]
+    fn range_proof_policy() {
+        let contract_id = ContractId::from([1u8; 32]);
+        let other_id = ContractId::from([2u8; 32]);
+        assert!(RangeProofPolicy::default().verifies(contract_id));
+        assert!(!RangeProofPolicy::Never.verifies(contract_id));
+        let trusted = RangeProofPolicy::TrustOwn(bset![contract_id]);
+        assert!(!trusted.verifies(contract_id));
+        assert!(trusted.verifies(other_id));
+
+        let opid = OpId::from([3u8; 32]);
+        let ty = AssignmentType::with(1);
+        let seal = XChain::Bitcoin(GraphSeal::with_blinded_vout(Method::OpretFirst, 0, 0));
+        let value = RevealedValue::new_random_blinding(10u64, AssetTag::from_byte_array([1; 32]));
+        let assign = Assign::<RevealedValue, GraphSeal>::ConfidentialState {
+            seal,
+            state: value.conceal(),
+        };
+        let schema = StateSchema::Fungible(FungibleType::Unsigned64Bit);
+        let type_system = TypeSystem::default();
+
+        let status = schema.validate(&type_system, &opid, ty, &assign, true);
+        assert!(matches!(
+            status.failures.as_slice(),
+            [validation::Failure::BulletproofsInvalid(id, t, _)] if *id == opid && *t == ty
+        ));
+        let status = schema.validate(&type_system, &opid, ty, &assign, false);
+        assert!(status.failures.is_empty());
+        assert_eq!(status.info, vec![validation::Info::RangeProofUnchecked(opid, ty)]);
+    }
+}
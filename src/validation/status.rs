@@ -31,8 +31,8 @@ use strict_types::SemId;
 use crate::contract::Opout;
 use crate::schema::{self, SchemaId};
 use crate::{
-    BundleId, ContractId, Layer1, OccurrencesMismatch, OpFullType, OpId, SecretSeal, StateType,
-    Vin, WitnessId, XChain, XGraphSeal,
+    AssetTag, BundleId, ContractId, Layer1, OccurrencesMismatch, OpFullType, OpId, SecretSeal,
+    StateType, Vin, WitnessId, XChain, XGraphSeal,
 };
 
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Display)]
@@ -297,6 +297,8 @@ pub enum Failure {
     /// operation {0} is referenced within the history multiple times. RGB
     /// contracts allow only direct acyclic graphs.
     CyclicGraph(OpId),
+    /// operation {0} is present in the consignment more than once.
+    DuplicateOperation(OpId),
     /// operation {0} is absent from the consignment.
     OperationAbsent(OpId),
     /// transition bundle {0} referenced in consignment terminals is absent from
@@ -311,6 +313,11 @@ pub enum Failure {
     /// transition bundle {0} references state transition {1} which is not
     /// included into the bundle input map.
     BundleExtraTransition(BundleId, OpId),
+    /// transition bundle {0} references under id {1} a state transition which
+    /// has id {2}.
+    BundleTransitionIdMismatch(BundleId, OpId, OpId),
+    /// transition bundle {0} exceeds the size limits. Details: {1}
+    BundleTooLarge(BundleId, String),
     /// transition bundle {0} references non-existing input in witness {2} for
     /// the state transition {1}.
     BundleInvalidInput(BundleId, OpId, WitnessId),
@@ -330,6 +337,9 @@ pub enum Failure {
     NoPrevOut(OpId, Opout),
     /// anchors used inside bundle {0} reference different public witness ids.
     AnchorSetInvalid(BundleId),
+    /// bundle {0} is anchored on {1} which is not in the set of layers allowed
+    /// by the contract genesis.
+    AnchorLayerMismatch(BundleId, Layer1),
     /// seal defined in the history as a part of operation output {0} is
     /// confidential and can't be validated.
     ConfidentialSeal(Opout),
@@ -349,6 +359,9 @@ pub enum Failure {
     /// transition bundle {0} is not properly anchored to the witness {1}.
     /// Details: {2}
     MpcInvalid(BundleId, WitnessId, InvalidProof),
+    /// tapret and opret anchors of transition bundle {0} in the witness {1}
+    /// commit to different MPC roots.
+    MpcRootMismatch(BundleId, WitnessId),
 
     // State extensions errors
     /// valency {valency} redeemed by state extension {opid} references
@@ -365,6 +378,9 @@ pub enum Failure {
         prev_id: OpId,
         valency: schema::ValencyType,
     },
+    /// valency redemptions leading from state extension {0} form a cycle
+    /// {1:?}.
+    RedemptionCycle(OpId, Vec<OpId>),
 
     // State check errors
     /// state in {opid}/{state_type} is of {found} type, while schema requires
@@ -391,6 +407,14 @@ pub enum Failure {
         expected: schema::FungibleType,
         found: schema::FungibleType,
     },
+    /// fungible state in {opid}/{state_type} is bound to asset tag {found},
+    /// while the contract defines asset tag {expected} for it.
+    AssetTagMismatch {
+        opid: OpId,
+        state_type: schema::AssignmentType,
+        expected: AssetTag,
+        found: AssetTag,
+    },
     /// invalid bulletproofs in {0}:{1}: {2}
     BulletproofsInvalid(OpId, schema::AssignmentType, String),
     /// operation {0} is invalid: {1}
@@ -436,6 +460,10 @@ pub enum Info {
     /// thus was not validated.
     UncheckableConfidentialState(OpId, schema::AssignmentType),
 
+    /// range proof of confidential fungible state in assignment {1} of
+    /// operation {0} was not verified according to the validation policy.
+    RangeProofUnchecked(OpId, schema::AssignmentType),
+
     /// Custom info by external services on top of RGB Core.
     #[display(inner)]
     Custom(String),
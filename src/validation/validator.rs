@@ -32,7 +32,7 @@ use super::status::{Failure, Warning};
 use super::{CheckedConsignment, ConsignmentApi, Status, Validity, VirtualMachine};
 use crate::vm::AluRuntime;
 use crate::{
-    AltLayer1, BundleId, ContractId, Layer1, OpId, OpRef, OpType, Operation, Opout, Schema,
+    BundleId, ContractId, Layer1, OpId, OpRef, OpType, Operation, Opout, RedemptionError, Schema,
     SchemaId, SchemaRoot, Script, SubSchema, Transition, TransitionBundle, TypedAssigns, WitnessId,
     XAnchor, XChain, XOutpoint, XOutputSeal, XPubWitness, XWitness,
 };
@@ -53,6 +53,39 @@ pub trait ResolveWitness {
     ) -> Result<XPubWitness, WitnessResolverError>;
 }
 
+/// Policy of verifying range proofs (bulletproofs) of the confidential
+/// fungible state during the consignment validation.
+///
+/// Range proofs are the only guarantee that the concealed amounts don't
+/// overflow, i.e. that no one has inflated the asset supply. A consignment of
+/// a contract issued by the wallet still contains transitions created by other
+/// parties, thus skipping the range proofs is safe only if the wallet has
+/// created or already verified all the confidential state in the consignment.
+/// Each skipped proof is reported with [`super::Info::RangeProofUnchecked`].
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub enum RangeProofPolicy {
+    /// Verify range proofs of all contracts.
+    #[default]
+    Always,
+
+    /// Do not verify range proofs of the listed contracts.
+    TrustOwn(BTreeSet<ContractId>),
+
+    /// Do not verify range proofs at all.
+    Never,
+}
+
+impl RangeProofPolicy {
+    /// Detects whether range proofs of the contract must be verified.
+    pub fn verifies(&self, contract_id: ContractId) -> bool {
+        match self {
+            RangeProofPolicy::Always => true,
+            RangeProofPolicy::TrustOwn(trusted) => !trusted.contains(&contract_id),
+            RangeProofPolicy::Never => false,
+        }
+    }
+}
+
 pub struct Validator<'consignment, 'resolver, C: ConsignmentApi, R: ResolveWitness> {
     consignment: CheckedConsignment<'consignment, C>,
 
@@ -65,6 +98,7 @@ pub struct Validator<'consignment, 'resolver, C: ConsignmentApi, R: ResolveWitne
 
     validated_op_seals: BTreeSet<OpId>,
     validated_op_state: BTreeSet<OpId>,
+    verify_range_proofs: bool,
 
     vm: Box<dyn VirtualMachine + 'consignment>,
     resolver: &'resolver R,
@@ -73,7 +107,11 @@ pub struct Validator<'consignment, 'resolver, C: ConsignmentApi, R: ResolveWitne
 impl<'consignment, 'resolver, C: ConsignmentApi, R: ResolveWitness>
     Validator<'consignment, 'resolver, C, R>
 {
-    fn init(consignment: &'consignment C, resolver: &'resolver R) -> Self {
+    fn init(
+        consignment: &'consignment C,
+        resolver: &'resolver R,
+        range_proofs: &RangeProofPolicy,
+    ) -> Self {
         // We use validation status object to store all detected failures and
         // warnings
         let mut status = Status::default();
@@ -120,8 +158,8 @@ impl<'consignment, 'resolver, C: ConsignmentApi, R: ResolveWitness>
         let validated_op_state = BTreeSet::<OpId>::new();
         let validated_op_seals = BTreeSet::<OpId>::new();
 
-        let mut layers1 = bset! { Layer1::Bitcoin };
-        layers1.extend(genesis.alt_layers1.iter().map(AltLayer1::layer1));
+        let layers1 = genesis.layers1();
+        let verify_range_proofs = range_proofs.verifies(contract_id);
 
         Self {
             consignment,
@@ -132,6 +170,7 @@ impl<'consignment, 'resolver, C: ConsignmentApi, R: ResolveWitness>
             layers1,
             validated_op_state,
             validated_op_seals,
+            verify_range_proofs,
             vm,
             resolver,
         }
@@ -147,7 +186,18 @@ impl<'consignment, 'resolver, C: ConsignmentApi, R: ResolveWitness>
     /// rest of the consignment data. This can help it debugging and
     /// detecting all problems with the consignment.
     pub fn validate(consignment: &'consignment C, resolver: &'resolver R, testnet: bool) -> Status {
-        let mut validator = Validator::init(consignment, resolver);
+        Self::validate_with_policy(consignment, resolver, testnet, &RangeProofPolicy::Always)
+    }
+
+    /// Validates consignment like [`Self::validate`], verifying range proofs
+    /// of the confidential fungible state according to the provided policy.
+    pub fn validate_with_policy(
+        consignment: &'consignment C,
+        resolver: &'resolver R,
+        testnet: bool,
+        range_proofs: &RangeProofPolicy,
+    ) -> Status {
+        let mut validator = Validator::init(consignment, resolver, range_proofs);
         // If the network mismatches there is no point in validating the contract since
         // all witness transactions will be missed.
         if testnet != validator.consignment.genesis().testnet {
@@ -200,6 +250,7 @@ impl<'consignment, 'resolver, C: ConsignmentApi, R: ResolveWitness>
             &self.consignment,
             OpRef::Genesis(self.consignment.genesis()),
             self.vm.as_ref(),
+            self.verify_range_proofs,
         );
         self.validated_op_state.insert(self.genesis_id);
 
@@ -253,8 +304,12 @@ impl<'consignment, 'resolver, C: ConsignmentApi, R: ResolveWitness>
             }
             // [VALIDATION]: Verify operation against the schema and scripts
             if self.validated_op_state.insert(opid) {
-                self.status +=
-                    schema.validate_state(&self.consignment, operation, self.vm.as_ref());
+                self.status += schema.validate_state(
+                    &self.consignment,
+                    operation,
+                    self.vm.as_ref(),
+                    self.verify_range_proofs,
+                );
             }
 
             match operation {
@@ -274,6 +329,16 @@ impl<'consignment, 'resolver, C: ConsignmentApi, R: ResolveWitness>
                     queue.extend(parent_nodes);
                 }
                 OpRef::Extension(extension) => {
+                    // [VALIDATION]: Redemptions referencing unknown operations or undefined
+                    //               valencies are reported below for each of the parents as
+                    //               they get validated; here we need to detect cycles only,
+                    //               which would keep the parents queued forever.
+                    let redemptions =
+                        extension.check_redemptions(|id| self.consignment.operation(id));
+                    if let Err(RedemptionError::Cycle(err)) = redemptions {
+                        self.status.add_failure(Failure::RedemptionCycle(opid, err.cycle));
+                        continue;
+                    }
                     for (valency, prev_id) in &extension.redeemed {
                         let Some(prev_op) = self.consignment.operation(*prev_id) else {
                             self.status.add_failure(Failure::ValencyNoParent {
@@ -309,6 +374,13 @@ impl<'consignment, 'resolver, C: ConsignmentApi, R: ResolveWitness>
             };
 
             let layer1 = anchored_bundle.anchor.layer1();
+            // [VALIDATION]: We validate that the bundle is anchored on a layer allowed by the
+            //               contract genesis
+            if !self.layers1.contains(&layer1) {
+                self.status
+                    .add_failure(Failure::AnchorLayerMismatch(bundle_id, layer1));
+                continue;
+            }
 
             let anchors = &anchored_bundle.anchor;
             let bundle = &anchored_bundle.bundle;
@@ -374,6 +446,18 @@ impl<'consignment, 'resolver, C: ConsignmentApi, R: ResolveWitness>
             return None;
         };
 
+        // [VALIDATION]: Checking that dual anchors commit to the same MPC root; invalid MPC
+        //               proofs are reported during the seal closing validation
+        if let (Some(tapret), Some(opret)) = anchors.as_reduced_unsafe().as_split() {
+            let message = mpc::Message::from(bundle_id);
+            let tapret = tapret.convolve(self.contract_id, message);
+            let opret = opret.convolve(self.contract_id, message);
+            if matches!((tapret, opret), (Ok(tapret), Ok(opret)) if tapret != opret) {
+                self.status
+                    .add_failure(Failure::MpcRootMismatch(bundle_id, witness_id));
+            }
+        }
+
         // Check that the anchor is committed into a transaction spending all of the
         // transition inputs.
         match self.resolver.resolve_pub_witness(witness_id) {
@@ -567,3 +651,161 @@ impl<'consignment, 'resolver, C: ConsignmentApi, R: ResolveWitness>
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::rc::Rc;
+
+    use amplify::confinement::{Confined, TinyOrdSet};
+    use amplify::num::u5;
+    use bp::dbc::opret::OpretProof;
+    use bp::dbc::tapret::TapretProof;
+    use bp::Txid;
+    use commit_verify::TryCommitVerify;
+    use strict_encoding::StrictDumb;
+    use strict_types::typelib::LibBuilder;
+    use strict_types::SystemBuilder;
+
+    use super::*;
+    use crate::{AnchorSet, AnchoredBundle, AssetTag, AssignmentType, Genesis, SecretSeal};
+
+    struct Consignment {
+        schema: SubSchema,
+        asset_tags: BTreeMap<AssignmentType, AssetTag>,
+        genesis: Genesis,
+        bundle: AnchoredBundle,
+    }
+
+    impl ConsignmentApi for Consignment {
+        type Iter<'a> = std::iter::Once<BundleId>;
+
+        fn schema(&self) -> &SubSchema { &self.schema }
+
+        fn asset_tags(&self) -> &BTreeMap<AssignmentType, AssetTag> { &self.asset_tags }
+
+        fn operation(&self, opid: OpId) -> Option<OpRef<'_>> {
+            if opid == self.genesis.id() {
+                return Some(OpRef::Genesis(&self.genesis));
+            }
+            self.bundle
+                .bundle
+                .known_transitions
+                .get(&opid)
+                .map(OpRef::Transition)
+        }
+
+        fn genesis(&self) -> &Genesis { &self.genesis }
+
+        fn terminals(&self) -> BTreeSet<(BundleId, XChain<SecretSeal>)> { empty!() }
+
+        fn bundle_ids<'a>(&self) -> Self::Iter<'a> { std::iter::once(self.bundle.bundle_id()) }
+
+        fn anchored_bundle(&self, bundle_id: BundleId) -> Option<Rc<AnchoredBundle>> {
+            Some(Rc::new(self.bundle.clone())).filter(|ab| ab.bundle_id() == bundle_id)
+        }
+
+        fn op_witness_id(&self, opid: OpId) -> Option<WitnessId> {
+            self.bundle
+                .bundle
+                .known_transitions
+                .get(&opid)
+                .and_then(|_| self.bundle.anchor.witness_id())
+        }
+    }
+
+    struct Resolver;
+
+    impl ResolveWitness for Resolver {
+        fn resolve_pub_witness(
+            &self,
+            witness_id: WitnessId,
+        ) -> Result<XPubWitness, WitnessResolverError> {
+            Err(WitnessResolverError::Unknown(witness_id))
+        }
+    }
+
+    fn schema() -> SubSchema {
+        #[derive(Clone, Debug)]
+        #[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
+        #[strict_type(lib = "TestLib")]
+        struct Meta(u8);
+
+        let lib = LibBuilder::new(libname!("TestLib"), TinyOrdSet::new())
+            .transpile::<Meta>()
+            .compile()
+            .unwrap();
+        let sys = SystemBuilder::new().import(lib).unwrap().finalize().unwrap();
+        let sem_id = sys.to_sem_id("TestLib.Meta").unwrap();
+        let mut schema = SubSchema {
+            type_system: sys.into_type_system(),
+            ..default!()
+        };
+        schema.genesis.metadata = sem_id;
+        schema
+    }
+
+    #[test]
+    fn dual_root_mismatch() {
+        let genesis = Genesis::strict_dumb();
+        let contract_id = genesis.contract_id();
+        let mut transition = Transition::strict_dumb();
+        transition.contract_id = contract_id;
+        let bundle = TransitionBundle::with_single(0u32.into(), transition);
+        let bundle_id = bundle.bundle_id();
+
+        let src = mpc::MultiSource {
+            min_depth: u5::ZERO,
+            messages: Confined::from_collection_unsafe(bmap! {
+                mpc::ProtocolId::from(contract_id) => mpc::Message::from(bundle_id),
+                mpc::ProtocolId::from([6u8; 32]) => mpc::Message::from([7u8; 32]),
+            }),
+            static_entropy: Some(0),
+        };
+        let opret = Anchor {
+            txid: Txid::from([3u8; 32]),
+            mpc_proof: mpc::MerkleProof::strict_dumb(),
+            dbc_proof: OpretProof::default(),
+            _method: default!(),
+        };
+        let tapret = Anchor {
+            txid: opret.txid,
+            mpc_proof: mpc::MerkleBlock::from(mpc::MerkleTree::try_commit(&src).unwrap())
+                .into_merkle_proof(contract_id.into())
+                .unwrap(),
+            dbc_proof: TapretProof::strict_dumb(),
+            _method: default!(),
+        };
+        let mut consignment = Consignment {
+            schema: schema(),
+            asset_tags: empty!(),
+            genesis,
+            bundle: AnchoredBundle {
+                anchor: XAnchor::Bitcoin(AnchorSet::Dual {
+                    tapret: tapret.clone(),
+                    opret: opret.clone(),
+                }),
+                bundle,
+            },
+        };
+        let witness_id = consignment.bundle.anchor.witness_id().unwrap();
+        let testnet = consignment.genesis.testnet;
+
+        let status = Validator::validate(&consignment, &Resolver, testnet);
+        assert!(status
+            .failures
+            .contains(&Failure::MpcRootMismatch(bundle_id, witness_id)));
+
+        consignment.bundle.anchor = XAnchor::Bitcoin(AnchorSet::Dual {
+            tapret: Anchor {
+                mpc_proof: opret.mpc_proof.clone(),
+                ..tapret
+            },
+            opret,
+        });
+        let status = Validator::validate(&consignment, &Resolver, testnet);
+        assert!(status.failures.contains(&Failure::SealNoWitnessTx(witness_id)));
+        assert!(!status
+            .failures
+            .contains(&Failure::MpcRootMismatch(bundle_id, witness_id)));
+    }
+}
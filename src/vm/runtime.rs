@@ -116,3 +116,138 @@ struct RegSetup {
     pub nums: BTreeMap<(RegAFR, Reg32), Number>,
     pub data: BTreeMap<RegS, Vec<u8>>,
 }
+
+#[cfg(test)]
+mod test {
+    use aluvm::isa::ControlFlowOp;
+    use aluvm::library::{Lib, LibSite};
+    use amplify::confinement::Confined;
+    use amplify::ByteArray;
+    use bp::dbc::Method;
+
+    use super::*;
+    use crate::validation::verify_fungible_sum;
+    use crate::vm::ContractOp;
+    use crate::{
+        Assign, AssetTag, AssignmentType, Assignments, BlindingFactor, Ffv, GraphSeal, OpRef,
+        Operation, RevealedValue, Transition, TransitionType, TypedAssigns, Valencies, XChain,
+    };
+
+    fn script(transition_type: TransitionType, ty: AssignmentType) -> AluScript {
+        let code = [
+            Instr::ExtensionCodes(RgbIsa::Contract(ContractOp::PcVs(ty))),
+            Instr::ControlFlow(ControlFlowOp::Succ),
+        ];
+        let lib = Lib::assemble::<Instr<RgbIsa>>(&code).unwrap();
+        let id = lib.id();
+        AluScript {
+            libs: Confined::from_collection_unsafe(bmap! { id => lib }),
+            entry_points: Confined::from_collection_unsafe(bmap! {
+                EntryPoint::ValidateTransition(transition_type) => LibSite::with(0, id),
+            }),
+        }
+    }
+
+    fn assigns(
+        values: &[u64],
+        blindings: &[BlindingFactor],
+        tag: AssetTag,
+    ) -> TypedAssigns<GraphSeal> {
+        let assigns = values
+            .iter()
+            .zip(blindings)
+            .enumerate()
+            .map(|(vout, (value, blinding))| {
+                let seal = GraphSeal::with_blinded_vout(Method::OpretFirst, vout as u32, 0);
+                Assign::revealed(
+                    XChain::Bitcoin(seal),
+                    RevealedValue::with_blinding(*value, *blinding, tag),
+                )
+            })
+            .collect::<Vec<_>>();
+        TypedAssigns::Fungible(Confined::from_collection_unsafe(assigns))
+    }
+
+    /// Checks the balance of a transition spending `inputs` and creating
+    /// `outputs`, blinded such that the blinding factors are balanced, with
+    /// the pedersen commitment sum check of the VM and with
+    /// [`verify_fungible_sum`]. Returns the decisions in the same order.
+    fn check_balance(inputs: &[u64], outputs: &[u64]) -> (bool, bool) {
+        let ty = AssignmentType::with(1);
+        let transition_type = TransitionType::with(1);
+        let tag = AssetTag::from_byte_array([1u8; 32]);
+        // Distinct powers of two never sum up to the same factor, so the
+        // balancing factor can't be zero.
+        let blinding = |n: usize| {
+            let mut bytes = [0u8; 32];
+            bytes[31] = 1 << n;
+            BlindingFactor::try_from(bytes).unwrap()
+        };
+
+        let input_blindings = (0..inputs.len()).map(blinding).collect::<Vec<_>>();
+        let mut output_blindings = (inputs.len()..inputs.len() + outputs.len() - 1)
+            .map(blinding)
+            .collect::<Vec<_>>();
+        let last = BlindingFactor::zero_balanced(
+            input_blindings.iter().copied(),
+            output_blindings.iter().copied(),
+        )
+        .unwrap();
+        output_blindings.push(last);
+
+        let prev_state = Assignments::from(Confined::from_collection_unsafe(bmap! {
+            ty => assigns(inputs, &input_blindings, tag),
+        }));
+        let transition = Transition {
+            ffv: Ffv::default(),
+            contract_id: strict_dumb!(),
+            transition_type,
+            metadata: empty!(),
+            globals: none!(),
+            inputs: none!(),
+            assignments: Assignments::from(Confined::from_collection_unsafe(bmap! {
+                ty => assigns(outputs, &output_blindings, tag),
+            })),
+            valencies: none!(),
+        };
+        let op = OpRef::Transition(&transition);
+        let redeemed = Valencies::default();
+        let asset_tags = bmap! { ty => tag };
+        let info = OpInfo::with(
+            strict_dumb!(),
+            transition.id(),
+            false,
+            &op,
+            &prev_state,
+            &redeemed,
+            &asset_tags,
+        );
+        let script = script(transition_type, ty);
+        let vm = AluRuntime::new(&script).run_validations(&info).is_ok();
+        let modern = verify_fungible_sum(&prev_state[&ty], &transition.assignments[&ty]);
+        (vm, modern)
+    }
+
+    #[test]
+    fn balance_agreement() {
+        let cases: &[(&[u64], &[u64], bool)] = &[
+            (&[10], &[10], true),
+            (&[10], &[4, 6], true),
+            (&[7, 8], &[15], true),
+            (&[7, 8], &[5, 5, 5], true),
+            (&[u64::MAX, 1], &[1, u64::MAX], true),
+            (&[u64::MAX], &[u64::MAX / 2, u64::MAX / 2 + 1], true),
+            (&[10], &[11], false),
+            (&[10], &[4, 5], false),
+            (&[7, 8], &[16], false),
+            (&[7, 8], &[5, 5, 4], false),
+            (&[u64::MAX, 1], &[u64::MAX], false),
+            (&[0, 1], &[0], false),
+        ];
+        for (inputs, outputs, balanced) in cases {
+            let (vm, modern) = check_balance(inputs, outputs);
+            assert_eq!(vm, modern, "VM and modern check disagree on {inputs:?} -> {outputs:?}");
+            assert_eq!(vm, *balanced, "wrong balance decision on {inputs:?} -> {outputs:?}");
+        }
+    }
+}